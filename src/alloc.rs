@@ -1,6 +1,11 @@
 use std::{
     alloc::{GlobalAlloc, System},
-    cell::{Cell, RefCell},
+    cell::{Cell, RefCell, UnsafeCell},
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
     time::{Duration, Instant},
 };
 
@@ -10,10 +15,23 @@ mod buffer;
 
 #[derive(Copy, Clone)]
 pub enum EventKind {
-    Alloc { size: usize },
-    Free { size: usize },
+    Alloc {
+        size: usize,
+    },
+    Free {
+        size: usize,
+    },
+    /// A block was resized, possibly by moving it. The old and new blocks are never counted
+    /// as live at the same time.
+    Realloc {
+        old_size: usize,
+        new_size: usize,
+    },
     Start,
     End,
+    /// The start of a [`TracingAlloc::region`], with its name.
+    RegionStart(&'static str),
+    RegionEnd,
 }
 
 #[derive(Clone, Copy)]
@@ -22,18 +40,240 @@ pub struct Event {
     pub kind: EventKind,
 }
 
+/// Running totals for the traced function, kept exactly even once the buffer is full.
+#[derive(Clone, Copy, Default)]
+pub struct TraceCounters {
+    pub num_allocs: usize,
+    /// The sum of every allocation's size, saturating rather than overflowing.
+    pub total_allocated: usize,
+    pub num_frees: usize,
+    /// The bytes allocated on every traced thread, less those freed.
+    pub current_bytes: usize,
+    pub peak_bytes: usize,
+    /// How many events didn't fit in the buffer.
+    pub dropped_events: usize,
+    /// Whether the buffer reached its limit, so the events stop before the end of the trace.
+    pub overflowed: bool,
+}
+
 struct TraceData {
     buffer: Buffer,
     start_time: Instant,
+    counters: TraceCounters,
+    /// Events skipped by sampling since the last recorded event.
+    unsampled: usize,
+    /// The change in allocated bytes of the skipped events, so the recorded events still
+    /// add up to the current total.
+    unsampled_bytes: isize,
+}
+
+impl TraceData {
+    fn new() -> Self {
+        Self {
+            buffer: Buffer::new(),
+            start_time: Instant::now(),
+            counters: TraceCounters::default(),
+            unsampled: 0,
+            unsampled_bytes: 0,
+        }
+    }
+
+    // Pushes the event if there's room, counting it as dropped if not.
+    fn try_push(&mut self, event: Event) {
+        if !self.buffer.try_push(event) {
+            self.counters.dropped_events += 1;
+            self.counters.overflowed = true;
+        }
+    }
+
+    // Records the skipped events' bytes as one event, so the graph stays in step.
+    fn push_change(&mut self, time: Duration, bytes: isize) {
+        let kind = if bytes >= 0 {
+            EventKind::Alloc {
+                size: bytes as usize,
+            }
+        } else {
+            EventKind::Free {
+                size: bytes.unsigned_abs(),
+            }
+        };
+
+        self.try_push(Event { time, kind });
+    }
+
+    // Records the bytes of any events skipped by sampling, such as before a marker.
+    fn flush_unsampled(&mut self, time: Duration) {
+        let unsampled_bytes = mem::take(&mut self.unsampled_bytes);
+        if unsampled_bytes != 0 {
+            self.push_change(time, unsampled_bytes);
+        }
+        self.unsampled = 0;
+    }
+
+    // Adds another thread's trace, keeping the events in order. Both traces are already in
+    // order, as each takes the time of its events as they're recorded.
+    fn merge(&mut self, other: &TraceData) {
+        let counters = &mut self.counters;
+        counters.num_allocs += other.counters.num_allocs;
+        counters.total_allocated = counters
+            .total_allocated
+            .saturating_add(other.counters.total_allocated);
+        counters.num_frees += other.counters.num_frees;
+        counters.dropped_events += other.counters.dropped_events;
+        counters.overflowed |= other.counters.overflowed;
+
+        if other.buffer.is_empty() {
+            return;
+        }
+
+        let mut merged = Buffer::new();
+        merged.reserve(self.buffer.len() + other.buffer.len());
+        let mut others = other.buffer.iter().peekable();
+        for event in self.buffer.iter() {
+            while let Some(other) = others.next_if(|other| other.time < event.time) {
+                merged.push(*other);
+            }
+            merged.push(*event);
+        }
+        others.for_each(|other| merged.push(*other));
+        self.buffer = merged;
+    }
+
+    fn record(&mut self, kind: EventKind) {
+        let counters = &mut self.counters;
+        let bytes = match kind {
+            EventKind::Alloc { size } => {
+                counters.num_allocs += 1;
+                counters.total_allocated = counters.total_allocated.saturating_add(size);
+                update_live_bytes(|live| live.saturating_add(size));
+                size as isize
+            }
+            EventKind::Free { size } => {
+                counters.num_frees += 1;
+                update_live_bytes(|live| live.saturating_sub(size));
+                -(size as isize)
+            }
+            // Counted as the allocation and free it replaces, but applied in one step so
+            // the peak doesn't include both blocks.
+            EventKind::Realloc { old_size, new_size } => {
+                counters.num_allocs += 1;
+                counters.num_frees += 1;
+                counters.total_allocated = counters.total_allocated.saturating_add(new_size);
+                update_live_bytes(|live| live.saturating_sub(old_size).saturating_add(new_size));
+                new_size as isize - old_size as isize
+            }
+            // The start and end always get recorded, so the graph has its full duration. The
+            // skipped events are recorded before any marker, so they're in the right region.
+            EventKind::Start
+            | EventKind::End
+            | EventKind::RegionStart(_)
+            | EventKind::RegionEnd => {
+                let time = self.start_time.elapsed();
+                self.flush_unsampled(time);
+
+                let event = Event { time, kind };
+                if matches!(kind, EventKind::Start | EventKind::End) {
+                    self.buffer.push(event);
+                } else {
+                    self.try_push(event);
+                }
+                return;
+            }
+        };
+
+        self.unsampled += 1;
+        self.unsampled_bytes += bytes;
+        if self.unsampled < SAMPLE_RATE.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let time = self.start_time.elapsed();
+        let unsampled_bytes = mem::take(&mut self.unsampled_bytes);
+        // A lone event is recorded as it is, so reallocations stay as one event.
+        if mem::take(&mut self.unsampled) == 1 {
+            self.try_push(Event { time, kind });
+        } else {
+            self.push_change(time, unsampled_bytes);
+        }
+    }
+}
+
+// The buffer's size limit, in events. Can't be read from the arguments here, as that
+// could allocate.
+static MAX_EVENTS: AtomicUsize = AtomicUsize::new(usize::MAX);
+static SAMPLE_RATE: AtomicUsize = AtomicUsize::new(1);
+
+/// Limits the memory used by each thread's trace buffer.
+pub(crate) fn set_trace_buffer_cap(bytes: usize) {
+    MAX_EVENTS.store(bytes / mem::size_of::<Event>(), Ordering::Relaxed);
+}
+
+/// Only records every Nth allocation or free in the trace, as getting the time of every
+/// one can slow allocation-heavy functions right down. The counters stay exact.
+pub(crate) fn set_sample_rate(rate: usize) {
+    SAMPLE_RATE.store(rate.max(1), Ordering::Relaxed);
+}
+
+// Only one thread traces at a time, as there's no telling which trace an allocation on
+// another thread belongs to.
+static TRACE_SESSION: Mutex<()> = Mutex::new(());
+// Set while a thread is tracing, so allocations on the threads it starts are traced too.
+static IS_TRACING: AtomicBool = AtomicBool::new(false);
+// The allocations on the threads other than the tracing one, which are merged into its trace
+// when it finishes. Only there while a thread is tracing.
+static OTHER_THREADS_TRACE: Mutex<Option<TraceData>> = Mutex::new(None);
+// The bytes live on every traced thread, and the most there have been, so the peak includes
+// every thread's allocations.
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+fn update_live_bytes(f: impl Fn(usize) -> usize) {
+    let prev = LIVE_BYTES
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |live| Some(f(live)))
+        .unwrap_or_else(|live| live);
+    PEAK_BYTES.fetch_max(f(prev), Ordering::Relaxed);
 }
 
 thread_local! {
-    static TRACE_BUFFER: RefCell<TraceData> = RefCell::new(TraceData {
-        buffer: Buffer::new(),
-        start_time: Instant::now(),
-    });
+    // Only this thread uses its trace, so the allocations it makes while tracing don't need a
+    // lock or a borrow flag. See `with_own_trace`.
+    static TRACE_BUFFER: UnsafeCell<TraceData> = UnsafeCell::new(TraceData::new());
+    static SESSION_GUARD: RefCell<Option<MutexGuard<'static, ()>>> = const { RefCell::new(None) };
+
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+    static EXCLUDED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Keeps the current thread's allocations out of other threads' traces. Called by the bench's
+/// own threads, such as the workers and UI. Threads started by anything else, such as indicatif
+/// or other functions being benched at the same time, can still show up in a trace.
+pub(crate) fn exclude_current_thread() {
+    EXCLUDED.set(true);
+}
+
+fn is_traced() -> bool {
+    ACTIVE.get() || (IS_TRACING.load(Ordering::Relaxed) && !EXCLUDED.get())
+}
+
+fn lock_other_threads() -> MutexGuard<'static, Option<TraceData>> {
+    OTHER_THREADS_TRACE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+// The allocator only writes to the thread's trace while the thread is ACTIVE, and the trace's
+// buffer allocates from the system allocator, so recording an event never re-enters this. The
+// trace is only read or reset while the thread isn't ACTIVE, so there's never a second reference.
+fn with_own_trace<R>(f: impl FnOnce(&mut TraceData) -> R) -> R {
+    // SAFETY: See above.
+    TRACE_BUFFER.with(|trace| f(unsafe { &mut *trace.get() }))
+}
 
-    static ACTIVE: Cell<bool> = Cell::new(false);
+fn assert_not_tracing() {
+    assert!(
+        !ACTIVE.get(),
+        "The trace can't be read while this thread is tracing"
+    );
 }
 
 pub struct TracingAlloc;
@@ -41,38 +281,120 @@ pub struct TracingAlloc;
 unsafe impl Sync for TracingAlloc {}
 
 impl TracingAlloc {
+    /// Starts tracing allocations on this thread, and on any thread not excluded with
+    /// `exclude_current_thread`. Waits for any other thread's trace to finish first.
+    ///
+    /// The thread's previous trace is cleared, so each one starts afresh. Panics if this thread
+    /// is already tracing, as it would otherwise wait on itself.
     pub fn enable_tracing(&self) {
-        TRACE_BUFFER.with_borrow_mut(|buffer| {
-            buffer.start_time = Instant::now();
+        assert!(
+            !ACTIVE.get(),
+            "Tracing enabled while this thread was already tracing"
+        );
+        self.clear_buffer();
+
+        let session = TRACE_SESSION.lock().unwrap_or_else(|e| e.into_inner());
+        SESSION_GUARD.set(Some(session));
+
+        LIVE_BYTES.store(0, Ordering::Relaxed);
+        PEAK_BYTES.store(0, Ordering::Relaxed);
+        let start_time = Instant::now();
+        let mut others = TraceData::new();
+        others.start_time = start_time;
+        others
+            .buffer
+            .set_max_length(MAX_EVENTS.load(Ordering::Relaxed));
+        *lock_other_threads() = Some(others);
+
+        with_own_trace(|trace| {
+            trace.start_time = start_time;
+            trace.record(EventKind::Start);
         });
 
-        self.write_ev(EventKind::Start);
-        ACTIVE.with(|active| active.set(true));
+        IS_TRACING.store(true, Ordering::Relaxed);
+        ACTIVE.set(true);
     }
 
     pub fn disable_tracing(&self) {
-        self.write_ev(EventKind::End);
-        ACTIVE.with(|active| active.set(false));
+        ACTIVE.set(false);
+        IS_TRACING.store(false, Ordering::Relaxed);
+
+        let others = lock_other_threads().take();
+        with_own_trace(|trace| {
+            if let Some(mut others) = others {
+                others.flush_unsampled(others.start_time.elapsed());
+                trace.merge(&others);
+            }
+            trace.record(EventKind::End);
+            trace.counters.current_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+            trace.counters.peak_bytes = PEAK_BYTES.load(Ordering::Relaxed);
+        });
+
+        SESSION_GUARD.take();
     }
 
+    /// Marks the allocations made by the function as a named region of the trace, such as a
+    /// solution's parse or solve phase, so the detailed bench can break the memory use down.
+    /// Regions can be nested. Does nothing unless this thread is being traced.
+    pub fn region<R>(&self, name: &'static str, f: impl FnOnce() -> R) -> R {
+        // Ends the region even if the function panics.
+        struct RegionGuard<'a>(&'a TracingAlloc);
+        impl Drop for RegionGuard<'_> {
+            fn drop(&mut self) {
+                self.0.write_ev(EventKind::RegionEnd);
+            }
+        }
+
+        if !is_traced() {
+            return f();
+        }
+
+        self.write_ev(EventKind::RegionStart(name));
+        let _guard = RegionGuard(self);
+        f()
+    }
+
+    /// Sizes this thread's trace buffer for at least this many events, up to the buffer's
+    /// limit, so it doesn't have to grow while tracing. Growing reallocates the buffer, which
+    /// takes longer the bigger it gets, and shows up in the timestamps of the trace.
+    pub fn reserve_events(&self, events: usize) {
+        assert_not_tracing();
+        let events = events.min(MAX_EVENTS.load(Ordering::Relaxed));
+        with_own_trace(|trace| trace.buffer.reserve(events));
+    }
+
+    /// Goes through the events of this thread's last trace. Panics if it's still tracing.
     pub fn iter_with(&self, f: impl FnMut(&Event)) {
-        TRACE_BUFFER.with_borrow(|buffer| {
-            buffer.buffer.iter().for_each(f);
-        })
+        assert_not_tracing();
+        with_own_trace(|trace| trace.buffer.iter().for_each(f));
+    }
+
+    pub fn counters(&self) -> TraceCounters {
+        assert_not_tracing();
+        with_own_trace(|trace| trace.counters)
     }
 
     pub fn clear_buffer(&self) {
-        TRACE_BUFFER.with_borrow_mut(|buffer| {
+        assert_not_tracing();
+        with_own_trace(|buffer| {
             buffer.buffer.clear();
+            buffer
+                .buffer
+                .set_max_length(MAX_EVENTS.load(Ordering::Relaxed));
+            buffer.counters = TraceCounters::default();
+            buffer.unsampled = 0;
+            buffer.unsampled_bytes = 0;
         })
     }
 
+    // The tracing thread records into its own trace without locking. The other threads share
+    // one, and take the time under its lock, so their events are in order too.
     fn write_ev(&self, kind: EventKind) {
-        TRACE_BUFFER.with(|output_file| {
-            let mut lock = output_file.borrow_mut();
-            let time = lock.start_time.elapsed();
-            lock.buffer.push(Event { time, kind })
-        });
+        if ACTIVE.get() {
+            with_own_trace(|trace| trace.record(kind));
+        } else if let Some(trace) = lock_other_threads().as_mut() {
+            trace.record(kind);
+        }
     }
 }
 
@@ -80,7 +402,7 @@ unsafe impl GlobalAlloc for TracingAlloc {
     unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
         let res = System.alloc(layout);
 
-        if ACTIVE.get() {
+        if is_traced() {
             self.write_ev(EventKind::Alloc {
                 size: layout.size(),
             });
@@ -90,7 +412,7 @@ unsafe impl GlobalAlloc for TracingAlloc {
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
-        if ACTIVE.get() {
+        if is_traced() {
             self.write_ev(EventKind::Free {
                 size: layout.size(),
             });
@@ -98,4 +420,88 @@ unsafe impl GlobalAlloc for TracingAlloc {
 
         System.dealloc(ptr, layout)
     }
+
+    unsafe fn alloc_zeroed(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let res = System.alloc_zeroed(layout);
+
+        if is_traced() {
+            self.write_ev(EventKind::Alloc {
+                size: layout.size(),
+            });
+        }
+
+        res
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        let res = System.realloc(ptr, layout, new_size);
+
+        // The old block is still allocated if the reallocation failed.
+        if is_traced() && !res.is_null() {
+            self.write_ev(EventKind::Realloc {
+                old_size: layout.size(),
+                new_size,
+            });
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{hint::black_box, thread};
+
+    use super::*;
+    use crate::TEST_ALLOC;
+
+    // The buffer's limit is shared, so tests expecting it not to overflow can't run alongside
+    // the one lowering it.
+    pub(crate) static BUFFER_CAP: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn other_threads_merged_in_order() {
+        let _cap = BUFFER_CAP.lock().unwrap_or_else(|e| e.into_inner());
+        TEST_ALLOC.enable_tracing();
+        let local = black_box(vec![0u8; 100]);
+        thread::spawn(|| drop(black_box(vec![0u8; 1 << 16])))
+            .join()
+            .unwrap();
+        drop(local);
+        TEST_ALLOC.disable_tracing();
+
+        let counters = TEST_ALLOC.counters();
+        assert!(counters.peak_bytes >= (1 << 16) + 100);
+        assert!(!counters.overflowed);
+
+        let mut times = Vec::new();
+        TEST_ALLOC.iter_with(|event| times.push(event.time));
+        assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn overflow_keeps_counting() {
+        let _cap = BUFFER_CAP.lock().unwrap_or_else(|e| e.into_inner());
+        set_trace_buffer_cap(16 * mem::size_of::<Event>());
+        TEST_ALLOC.enable_tracing();
+        for i in 0..100 {
+            drop(black_box(Box::new(i)));
+        }
+        TEST_ALLOC.disable_tracing();
+        MAX_EVENTS.store(usize::MAX, Ordering::Relaxed);
+
+        let counters = TEST_ALLOC.counters();
+        assert!(counters.overflowed);
+        assert!(counters.num_allocs >= 100);
+        assert!(counters.dropped_events > 0);
+
+        let mut num_events = 0;
+        let mut last = None;
+        TEST_ALLOC.iter_with(|event| {
+            num_events += 1;
+            last = Some(event.kind);
+        });
+        assert!(num_events <= 16);
+        assert!(matches!(last, Some(EventKind::End)));
+    }
 }
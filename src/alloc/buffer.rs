@@ -23,6 +23,8 @@ struct EventPtr {
 pub struct Buffer {
     capacity: usize,
     length: usize,
+    /// The most events the buffer will hold.
+    max_length: usize,
     buf: EventPtr,
 }
 
@@ -38,6 +40,7 @@ impl Buffer {
         Self {
             capacity: INITIAL_SIZE,
             length: 0,
+            max_length: usize::MAX,
             buf: EventPtr {
                 ptr,
                 _marker: PhantomData,
@@ -45,11 +48,41 @@ impl Buffer {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
     pub fn clear(&mut self) {
         self.length = 0;
     }
 
+    /// Limits how many events the buffer will hold.
+    pub fn set_max_length(&mut self, max_length: usize) {
+        // Always leave room for the start and end events.
+        self.max_length = max_length.max(2);
+    }
+
+    /// Pushes the event unless that would leave no room for a final event, returning
+    /// whether it was recorded.
+    pub fn try_push(&mut self, event: Event) -> bool {
+        if self.length + 1 >= self.max_length {
+            return false;
+        }
+
+        self.push(event);
+        true
+    }
+
+    /// Pushes the event, even into the space kept by [`try_push`](Self::try_push).
     pub fn push(&mut self, event: Event) {
+        if self.length >= self.max_length {
+            return;
+        }
+
         if self.length == self.capacity {
             self.grow();
         }
@@ -61,13 +94,25 @@ impl Buffer {
         }
     }
 
+    /// Makes room for at least this many events, so they can be recorded without the buffer
+    /// reallocating part way through a trace.
+    pub fn reserve(&mut self, events: usize) {
+        if events > self.capacity {
+            self.grow_to(events);
+        }
+    }
+
     fn grow(&mut self) {
-        let layout = Layout::array::<Event>(self.capacity).expect("Overflowed layout calculation");
         let new_capacity = self
             .capacity
             .checked_mul(2)
-            .expect("Buffer grow overflowed usize");
+            .expect("Buffer grow overflowed usize")
+            .min(self.max_length);
+        self.grow_to(new_capacity);
+    }
 
+    fn grow_to(&mut self, new_capacity: usize) {
+        let layout = Layout::array::<Event>(self.capacity).expect("Overflowed layout calculation");
         let new_layout =
             Layout::array::<Event>(new_capacity).expect("Overflowed layout calculation");
 
@@ -95,6 +140,9 @@ impl Buffer {
     }
 }
 
+// SAFETY: The buffer owns its allocation, like a Vec, so it can be moved between threads.
+unsafe impl Send for Buffer {}
+
 impl Drop for Buffer {
     fn drop(&mut self) {
         let layout = Layout::array::<Event>(self.capacity).expect("Overflowed layout calculation");
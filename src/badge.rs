@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+const HEIGHT: u32 = 20;
+// Padding either side of each segment's text.
+const PADDING: u32 = 6;
+
+const GREEN: &str = "#4c1";
+const YELLOW: &str = "#dfb317";
+const RED: &str = "#e05d44";
+
+/// Estimates the width in pixels of text in 11px Verdana, close enough that the badge
+/// doesn't clip its text.
+pub(crate) fn text_width(text: &str) -> u32 {
+    // Widths in tenths of a pixel.
+    let tenths: u32 = text
+        .chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '!' | '|' => 35,
+            ' ' | 'f' | 'r' | 't' | 'I' | '(' | ')' | '[' | ']' | '-' => 45,
+            'm' | 'w' => 100,
+            'M' | 'W' | '%' | '@' => 110,
+            'µ' => 66,
+            'a'..='z' => 66,
+            'A'..='Z' => 75,
+            '0'..='9' => 70,
+            _ => 80,
+        })
+        .sum();
+
+    tenths.div_ceil(10)
+}
+
+/// Picks the badge colour for the total run time: green under the first threshold, yellow
+/// under the second, and red otherwise.
+pub(crate) fn badge_colour(total: Duration, thresholds: (Duration, Duration)) -> &'static str {
+    if total < thresholds.0 {
+        GREEN
+    } else if total < thresholds.1 {
+        YELLOW
+    } else {
+        RED
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders a flat, shields.io-style badge with a grey label and a coloured value.
+pub(crate) fn render_badge(label: &str, value: &str, colour: &str) -> String {
+    let label_width = text_width(label) + PADDING * 2;
+    let value_width = text_width(value) + PADDING * 2;
+    let width = label_width + value_width;
+
+    let label = escape_xml(label);
+    let value = escape_xml(value);
+
+    // The text positions are in tenths, and scaled down, so the text can be centred on half pixels.
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" role="img" aria-label="{label}: {value}">
+<title>{label}: {value}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="{height}" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{label_width}" height="{height}" fill="#555"/><rect x="{label_width}" width="{value_width}" height="{height}" fill="{colour}"/><rect width="{width}" height="{height}" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="110" transform="scale(.1)">
+<text x="{label_x}" y="140">{label}</text><text x="{value_x}" y="140">{value}</text>
+</g>
+</svg>
+"##,
+        width = width,
+        height = HEIGHT,
+        label = label,
+        value = value,
+        label_width = label_width,
+        value_width = value_width,
+        colour = colour,
+        label_x = label_width * 5,
+        value_x = label_width * 10 + value_width * 5,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden_badge() {
+        let badge = render_badge("AoC 2022", "312 ms total", GREEN);
+        assert_eq!(
+            badge,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="152" height="20" role="img" aria-label="AoC 2022: 312 ms total">
+<title>AoC 2022: 312 ms total</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="152" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="67" height="20" fill="#555"/><rect x="67" width="85" height="20" fill="#4c1"/><rect width="152" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="110" transform="scale(.1)">
+<text x="335" y="140">AoC 2022</text><text x="1095" y="140">312 ms total</text>
+</g>
+</svg>
+"##
+        );
+    }
+
+    #[test]
+    fn badge_text_is_escaped() {
+        let badge = render_badge("<b>&\"", "1 s", RED);
+        assert!(badge.contains("<title>&lt;b&gt;&amp;&quot;: 1 s</title>"));
+        assert!(!badge.contains("<b>"));
+    }
+
+    #[test]
+    fn long_label_widths() {
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("i"), 4);
+        assert_eq!(text_width("W"), 11);
+
+        // Rounded up once for the whole text, not per character, so long labels don't drift.
+        assert_eq!(text_width(&"il".repeat(100)), 700);
+
+        // Wider characters always make for a wider badge.
+        let widths: Vec<_> = ["iiii", "aaaa", "AAAA", "MMMM"]
+            .iter()
+            .map(|text| text_width(text))
+            .collect();
+        assert!(widths.windows(2).all(|w| w[0] < w[1]), "{:?}", widths);
+        assert_eq!(text_width(&"M".repeat(500)), 5500);
+    }
+
+    #[test]
+    fn colour_thresholds() {
+        let thresholds = (Duration::from_secs(1), Duration::from_secs(5));
+        assert_eq!(badge_colour(Duration::from_millis(999), thresholds), GREEN);
+        assert_eq!(badge_colour(Duration::from_secs(1), thresholds), YELLOW);
+        assert_eq!(badge_colour(Duration::from_secs(5), thresholds), RED);
+    }
+}
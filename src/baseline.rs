@@ -0,0 +1,258 @@
+//! Named runs saved with `--save-baseline`, which later runs can be compared against with
+//! `--baseline`, like criterion's baselines.
+//!
+//! Each baseline is a JSON file in `.aoc_baselines` in the working directory. Saving only
+//! replaces the functions that were benched, so a baseline can be built up from several runs.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use console::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::Timestamp,
+    is_background_bench,
+    output::{write_atomic, OUTPUT},
+    speedup::Measurement,
+    BenchError, BACKGROUND_NOTE,
+};
+
+const BASELINE_DIR: &str = "./.aoc_baselines";
+
+// Changes within this fraction of the baseline's mean aren't coloured, as they're likely noise.
+const NOISE_THRESHOLD: f64 = 0.05;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct BaselineEntry {
+    year: u16,
+    day: u8,
+    part: Option<u8>,
+    /// The other function's name, empty for the parts.
+    name: String,
+    mean_ns: u64,
+    num_allocs: Option<usize>,
+    /// Saved from a `--background` run with `--force`, so the mean isn't comparable.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    background: bool,
+}
+
+impl BaselineEntry {
+    fn is_for(&self, year: u16, day: u8, part: Option<u8>, name: &str) -> bool {
+        self.year == year
+            && self.day == day
+            && self.part == part
+            && (part.is_some() || self.name == name)
+    }
+
+    fn sort_key(&self) -> (u16, u8, bool, Option<u8>, &str) {
+        (
+            self.year,
+            self.day,
+            self.part.is_none(),
+            self.part,
+            &self.name,
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBaseline {
+    saved: Timestamp,
+    entries: Vec<BaselineEntry>,
+}
+
+// The name becomes part of the path, so it's kept to characters that are safe everywhere.
+pub(crate) fn parse_baseline_name(src: &str) -> Result<String, String> {
+    let is_valid = !src.is_empty()
+        && !src.starts_with('.')
+        && src
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if is_valid {
+        Ok(src.to_owned())
+    } else {
+        Err(format!(
+            "invalid baseline name '{}', only letters, digits, '-', '_' and '.' are allowed",
+            src
+        ))
+    }
+}
+
+fn baseline_path(name: &str) -> PathBuf {
+    Path::new(BASELINE_DIR).join(format!("{}.json", name))
+}
+
+fn read_saved(name: &str, path: &Path) -> Result<Option<SavedBaseline>, BenchError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(BenchError::InputFileError {
+                inner: e,
+                name: path.display().to_string(),
+            })
+        }
+    };
+
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| BenchError::CorruptBaseline {
+            inner: e,
+            name: name.to_owned(),
+            path: path.display().to_string(),
+        })
+}
+
+/// A saved baseline, to compare this run's functions against.
+pub(crate) struct Baseline {
+    name: String,
+    saved: Timestamp,
+    entries: Vec<BaselineEntry>,
+}
+
+impl Baseline {
+    pub(crate) fn load(name: &str) -> Result<Self, BenchError> {
+        let path = baseline_path(name);
+        let saved =
+            read_saved(name, &path)?.ok_or_else(|| BenchError::MissingBaseline(name.to_owned()))?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            saved: saved.saved,
+            entries: saved.entries,
+        })
+    }
+
+    /// The function's entry, if it was in the baseline.
+    pub(crate) fn find(
+        &self,
+        year: u16,
+        day: u8,
+        part: Option<u8>,
+        name: &str,
+    ) -> Option<BaselineEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.is_for(year, day, part, name))
+            .cloned()
+    }
+
+    /// The function's saved mean, and when the baseline was saved.
+    pub(crate) fn mean(
+        &self,
+        year: u16,
+        day: u8,
+        part: Option<u8>,
+        name: &str,
+    ) -> Option<(Duration, Timestamp)> {
+        self.find(year, day, part, name)
+            .map(|entry| (Duration::from_nanos(entry.mean_ns), self.saved))
+    }
+
+    pub(crate) fn print_note(&self) {
+        println!(
+            "Compared with baseline '{}', saved {}. Changes within {}% aren't coloured",
+            self.name,
+            self.saved,
+            NOISE_THRESHOLD * 100.0
+        );
+        if self.entries.iter().any(|e| e.background) {
+            println!(
+                "Parts of the baseline were saved in {}",
+                BACKGROUND_NOTE.to_lowercase()
+            );
+        }
+    }
+}
+
+/// Renders the change from the baseline: the percentage change of the mean, and of the
+/// number of allocations if it changed. Functions missing from the baseline are `new`.
+pub(crate) fn render_delta(
+    entry: Option<&BaselineEntry>,
+    mean: Option<Duration>,
+    num_allocs: Option<usize>,
+) -> String {
+    let Some(entry) = entry else {
+        return "new".to_owned();
+    };
+    let Some(mean) = mean else {
+        return String::new();
+    };
+
+    let mut delta = if entry.mean_ns == 0 {
+        "-".to_owned()
+    } else {
+        let change = mean.as_nanos() as f64 / entry.mean_ns as f64 - 1.0;
+        let rendered = format!("{:>+6.1}%", change * 100.0);
+        if change > NOISE_THRESHOLD {
+            OUTPUT.style().paint(rendered, Color::Red)
+        } else if change < -NOISE_THRESHOLD {
+            OUTPUT.style().paint(rendered, Color::Green)
+        } else {
+            rendered
+        }
+    };
+
+    if let (Some(old), Some(new)) = (entry.num_allocs, num_allocs) {
+        if old != new {
+            delta += &format!(" ({:+} allocs)", new as i64 - old as i64);
+        }
+    }
+
+    delta
+}
+
+/// Saves the measurements under the name, replacing those already saved for the same
+/// functions.
+pub(crate) fn save_baseline(
+    name: &str,
+    year: u16,
+    measurements: &[Measurement],
+) -> Result<(), BenchError> {
+    let path = baseline_path(name);
+    let mut entries = read_saved(name, &path)?.map_or_else(Vec::new, |saved| saved.entries);
+
+    for measurement in measurements {
+        let entry = BaselineEntry {
+            year,
+            day: measurement.day,
+            part: measurement.part,
+            // The detailed bench names the parts, but they're matched by number.
+            name: match measurement.part {
+                Some(_) => String::new(),
+                None => measurement.name.clone(),
+            },
+            mean_ns: measurement.mean.as_nanos() as u64,
+            num_allocs: measurement.num_allocs,
+            background: is_background_bench(),
+        };
+
+        match entries
+            .iter_mut()
+            .find(|e| e.is_for(year, entry.day, entry.part, &entry.name))
+        {
+            Some(old) => *old = entry,
+            None => entries.push(entry),
+        }
+    }
+
+    entries.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    let saved = SavedBaseline {
+        saved: Timestamp::now(),
+        entries,
+    };
+
+    fs::create_dir_all(BASELINE_DIR).map_err(|e| BenchError::OutputFileError {
+        inner: e,
+        name: BASELINE_DIR.to_owned(),
+    })?;
+    let json = serde_json::to_string_pretty(&saved).expect("Failed to serialize baseline");
+    write_atomic(&path, json.as_bytes())?;
+    println!("Saved baseline '{}' to {}", name, path.display());
+
+    Ok(())
+}
@@ -1,22 +1,45 @@
 use std::{
-    fmt::Display,
+    any::Any,
+    fmt::{Display, Write},
     hint::black_box,
-    panic::catch_unwind,
+    mem,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
-use crossbeam_channel::Sender;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
 use thiserror::Error;
 
-use crate::{alloc::EventKind, input, BenchError, BenchResult, TracingAlloc};
+use crate::{
+    alloc::{Event, EventKind},
+    capture, day_input, describe_input_error,
+    plan::{run_with_stack, spawn_with_stack, timing_guard},
+    platform,
+    provenance::InputProvenance,
+    render_decimal, BenchError, BenchResult, TracingAlloc, ARGS,
+};
+
+use self::history::AnswerHistory;
 
 pub mod detailed;
+mod history;
 pub mod simple;
 
 pub type SetupFunction = for<'a> fn(&'a str, Bench) -> BenchResult;
 
+/// Named values reported by a solution alongside its answer, such as the number of states searched.
+pub type Metrics = Vec<(&'static str, u64)>;
+
 const NANOS_PER_SECOND: f64 = 1_000_000_000.0;
+
+// Test harness benches never measure anything, so they never touch the allocator's trace.
+static TEST_ALLOC: TracingAlloc = TracingAlloc;
 const MAX_SAMPLES: usize = 1_000_000;
+const NON_IDEMPOTENT_WARNING: &str =
+    "non-deterministic or non-idempotent result, the first timed iteration differed from the answer";
 
 #[derive(Debug, Error)]
 #[error("Error benching memory use: {:?}", .inner)]
@@ -26,69 +49,403 @@ pub struct MemoryBenchError {
     pub inner: std::io::Error,
 }
 
-#[derive(Default)]
-pub(crate) struct RuntimeData {
+/// How the number of timing iterations is decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SampleMode {
+    /// Sample for this many seconds, and at least `--min-samples` iterations.
+    Timed(u64),
+    /// Run exactly this many iterations, regardless of how long they take.
+    Iterations(usize),
+}
+
+/// Which samples are dropped before working out the statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OutlierFilter {
+    /// Drop samples outside Tukey's fences, 1.5 times the interquartile range beyond the
+    /// quartiles, as criterion does.
+    Iqr,
+    /// Drop samples more than 2 standard deviations from the mean.
+    TwoSigma,
+    /// Keep every sample, for chasing rare slow paths.
+    None,
+}
+
+impl FromStr for OutlierFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iqr" => Ok(OutlierFilter::Iqr),
+            "2sigma" => Ok(OutlierFilter::TwoSigma),
+            "none" => Ok(OutlierFilter::None),
+            _ => Err(format!(
+                "invalid outlier filter '{}', expected iqr, 2sigma or none",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for OutlierFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OutlierFilter::Iqr => "iqr",
+            OutlierFilter::TwoSigma => "2sigma",
+            OutlierFilter::None => "none",
+        })
+    }
+}
+
+/// The timing of a function. The statistics are of the samples left once the outliers have
+/// been filtered out.
+pub struct RuntimeData {
     pub(crate) sample_count: usize,
     pub(crate) mean: Duration,
     pub(crate) std_dev: Duration,
     pub(crate) first_quartile: Duration,
     pub(crate) third_quartile: Duration,
+    /// The fastest of the samples left after filtering.
+    pub(crate) min: Duration,
+    /// The slowest of the samples left after filtering.
+    pub(crate) max: Duration,
     pub(crate) outlier_count: usize,
+    /// The slowest sample, even if it was filtered out.
+    pub(crate) max_sample: Duration,
+    pub(crate) outlier_filter: OutlierFilter,
+    pub(crate) answer_history: Option<Box<AnswerHistory>>,
+    pub(crate) sample_mode: SampleMode,
+    /// How many times the function ran during the warm-up, before sampling.
+    pub(crate) warmup_iterations: usize,
+    /// How many bytes each call processes, usually the input's length.
+    pub(crate) input_bytes: Option<usize>,
+}
+
+impl RuntimeData {
+    pub fn mean(&self) -> Duration {
+        self.mean
+    }
+
+    pub fn std_dev(&self) -> Duration {
+        self.std_dev
+    }
+
+    pub fn first_quartile(&self) -> Duration {
+        self.first_quartile
+    }
+
+    pub fn third_quartile(&self) -> Duration {
+        self.third_quartile
+    }
+
+    /// The fastest of the samples left after filtering.
+    pub fn min(&self) -> Duration {
+        self.min
+    }
+
+    /// The slowest of the samples left after filtering.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// The slowest sample, even if it was filtered out.
+    pub fn max_sample(&self) -> Duration {
+        self.max_sample
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.sample_count
+    }
+
+    pub fn outlier_count(&self) -> usize {
+        self.outlier_count
+    }
+
+    pub fn warmup_iterations(&self) -> usize {
+        self.warmup_iterations
+    }
+
+    /// The bytes processed per second, from the mean time.
+    pub fn throughput(&self) -> Option<f64> {
+        let bytes = self.input_bytes?;
+        Some(bytes as f64 / self.mean.as_secs_f64().max(f64::MIN_POSITIVE))
+    }
+
+    /// How many times slower than the mean the slowest sample was, which shows rare slow
+    /// paths the filtered statistics hide.
+    pub(crate) fn worst_factor(&self) -> f64 {
+        self.max_sample.as_secs_f64() / self.mean.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
 }
 
+/// The memory use of a single call of a function.
 #[derive(Default)]
-pub(crate) struct MemoryData {
+pub struct MemoryData {
     pub(crate) end_ts: f32,
     pub(crate) end_ts_duration: Duration,
     pub(crate) graph_points: Vec<(f32, f32)>,
     pub(crate) max_memory: usize,
+    /// Bytes still allocated when the function returned, including its return value.
+    pub(crate) retained_memory: usize,
+    pub(crate) num_allocs: usize,
+    /// The sum of every allocation's size, however short-lived.
+    pub(crate) total_allocated: usize,
+    pub(crate) num_frees: usize,
+    /// Whether the trace buffer filled up, so the graph stops early. The counts are still exact.
+    pub(crate) overflowed: bool,
+    pub(crate) dropped_events: usize,
+    /// How many events were recorded.
+    pub(crate) recorded_events: usize,
+    /// The function's [`TracingAlloc::region`]s, in the order they started.
+    pub(crate) regions: Vec<(&'static str, RegionStats)>,
+}
+
+/// The memory use within one of a function's [`TracingAlloc::region`]s.
+///
+/// Worked out from the recorded events, so with a memory sample rate the counts are of the
+/// sampled events, and a region missing its end is closed when the function returned.
+#[derive(Debug, Clone)]
+pub struct RegionStats {
+    /// How many regions it's nested in.
+    pub(crate) depth: usize,
+    pub(crate) start_ts: f32,
+    pub(crate) end_ts: f32,
+    pub(crate) max_memory: usize,
     pub(crate) num_allocs: usize,
 }
 
+impl RegionStats {
+    /// How many regions it's nested in.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The most bytes allocated at once during the region, including those from before it.
+    pub fn max_memory(&self) -> usize {
+        self.max_memory
+    }
+
+    pub fn num_allocs(&self) -> usize {
+        self.num_allocs
+    }
+}
+
+impl MemoryData {
+    /// The most bytes allocated at once.
+    pub fn max_memory(&self) -> usize {
+        self.max_memory
+    }
+
+    /// Bytes still allocated when the function returned, including its return value.
+    pub fn retained_memory(&self) -> usize {
+        self.retained_memory
+    }
+
+    pub fn num_allocs(&self) -> usize {
+        self.num_allocs
+    }
+
+    pub fn num_frees(&self) -> usize {
+        self.num_frees
+    }
+
+    /// The sum of every allocation's size, however short-lived.
+    pub fn total_allocated(&self) -> usize {
+        self.total_allocated
+    }
+
+    pub fn regions(&self) -> &[(&'static str, RegionStats)] {
+        &self.regions
+    }
+
+    /// Whether the trace buffer filled up, so the graph stops early. The counts are still exact.
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// A warning about the trace filling up, if it did.
+    pub(crate) fn truncation_warning(&self) -> Option<String> {
+        self.overflowed.then(|| {
+            format!(
+                "memory trace truncated after {} events ({} dropped); peak/alloc counts remain exact",
+                render_decimal(self.recorded_events).trim(),
+                render_decimal(self.dropped_events).trim()
+            )
+        })
+    }
+}
+
+// Allocation-heavy functions can generate hundreds of millions of events, which is far more
+// than we can draw. Past this many events we bucket them by time, keeping the extremes of
+// each bucket so the graph keeps its shape.
+const MAX_GRAPH_BUCKETS: usize = 4096;
+
+struct GraphBucket {
+    index: usize,
+    time: f32,
+    entry: usize,
+    min: usize,
+    max: usize,
+    exit: usize,
+}
+
+struct GraphPoints {
+    bucket_width: f32,
+    points: Vec<(f32, f32)>,
+    current: Option<GraphBucket>,
+}
+
+impl GraphPoints {
+    fn new(end_time: f32, num_events: usize) -> Self {
+        let bucket_width = if num_events > MAX_GRAPH_BUCKETS * 2 {
+            end_time / MAX_GRAPH_BUCKETS as f32
+        } else {
+            0.0
+        };
+
+        Self {
+            bucket_width,
+            points: Vec::new(),
+            current: None,
+        }
+    }
+
+    fn push(&mut self, time: f32, prev_bytes: usize, cur_bytes: usize) {
+        if self.bucket_width <= 0.0 {
+            self.points.push((time, prev_bytes as f32));
+            self.points.push((time, cur_bytes as f32));
+            return;
+        }
+
+        let index = (time / self.bucket_width) as usize;
+        match &mut self.current {
+            Some(bucket) if bucket.index == index => {
+                bucket.min = bucket.min.min(cur_bytes);
+                bucket.max = bucket.max.max(cur_bytes);
+                bucket.exit = cur_bytes;
+            }
+            _ => {
+                self.flush();
+                self.current = Some(GraphBucket {
+                    index,
+                    time,
+                    entry: prev_bytes,
+                    min: prev_bytes.min(cur_bytes),
+                    max: prev_bytes.max(cur_bytes),
+                    exit: cur_bytes,
+                });
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(bucket) = self.current.take() {
+            // Drawing entry -> max -> min -> exit covers the whole range of the bucket.
+            for bytes in [bucket.entry, bucket.max, bucket.min, bucket.exit] {
+                self.points.push((bucket.time, bytes as f32));
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<(f32, f32)> {
+        self.flush();
+        self.points
+    }
+}
+
 fn read_memory_data(alloc: &TracingAlloc) -> MemoryData {
-    let mut points = Vec::new();
-    let mut cur_bytes = 0;
+    let mut num_events = 0;
+    let mut last_time = 0.0;
+    alloc.iter_with(|event| {
+        num_events += 1;
+        last_time = event.time.as_secs_f32();
+    });
+
+    let mut points = GraphPoints::new(last_time, num_events);
+    let mut cur_bytes: usize = 0;
     let mut prev_bytes = 0;
     let mut end_ts_duration = Duration::ZERO;
     let mut end_ts = 0.0;
-    let mut max_memory = 0;
-    let mut num_allocs = 0;
+    let mut regions: Vec<(&'static str, RegionStats)> = Vec::new();
+    let mut open_regions: Vec<usize> = Vec::new();
 
     alloc.iter_with(|event| {
+        let time = event.time.as_secs_f32();
         match event.kind {
             EventKind::Alloc { size } => {
-                num_allocs += 1;
-                cur_bytes += size;
+                cur_bytes = cur_bytes.saturating_add(size);
             }
+            // Another thread can free a block allocated before the trace started, so these
+            // saturate like the counters do.
             EventKind::Free { size } => {
-                cur_bytes -= size;
+                cur_bytes = cur_bytes.saturating_sub(size);
+            }
+            EventKind::Realloc { old_size, new_size } => {
+                cur_bytes = cur_bytes.saturating_sub(old_size).saturating_add(new_size);
             }
             EventKind::Start => {}
             EventKind::End => {
                 end_ts_duration = event.time;
-                end_ts = event.time.as_secs_f32();
+                end_ts = time;
+                for i in open_regions.drain(..) {
+                    regions[i].1.end_ts = time;
+                }
+            }
+            EventKind::RegionStart(name) => {
+                let region = RegionStats {
+                    depth: open_regions.len(),
+                    start_ts: time,
+                    end_ts: time,
+                    max_memory: cur_bytes,
+                    num_allocs: 0,
+                };
+                open_regions.push(regions.len());
+                regions.push((name, region));
+            }
+            // An end without a start is ignored.
+            EventKind::RegionEnd => {
+                if let Some(i) = open_regions.pop() {
+                    regions[i].1.end_ts = time;
+                }
             }
         };
 
-        max_memory = max_memory.max(cur_bytes);
-
-        points.push((event.time.as_secs_f32(), prev_bytes as f32));
-        points.push((event.time.as_secs_f32(), cur_bytes as f32));
+        let is_alloc = matches!(
+            event.kind,
+            EventKind::Alloc { .. } | EventKind::Realloc { .. }
+        );
+        for &i in &open_regions {
+            let region = &mut regions[i].1;
+            region.max_memory = region.max_memory.max(cur_bytes);
+            region.num_allocs += is_alloc as usize;
+        }
 
+        points.push(time, prev_bytes, cur_bytes);
         prev_bytes = cur_bytes;
     });
+    for i in open_regions {
+        regions[i].1.end_ts = last_time;
+    }
 
+    // The events may have been cut short, but the counters are always complete.
+    let counters = alloc.counters();
     MemoryData {
         end_ts,
         end_ts_duration,
-        graph_points: points,
-        max_memory,
-        num_allocs,
+        graph_points: points.finish(),
+        max_memory: counters.peak_bytes,
+        retained_memory: counters.current_bytes,
+        num_allocs: counters.num_allocs,
+        total_allocated: counters.total_allocated,
+        num_frees: counters.num_frees,
+        overflowed: counters.overflowed,
+        dropped_events: counters.dropped_events,
+        recorded_events: num_events,
+        regions,
     }
 }
 
 // Not that this function expects the samples to be *SORTED* before being passed in.
-fn generate_runtime_stats(samples: &[Duration]) -> RuntimeData {
+fn generate_runtime_stats(samples: &[Duration], sample_mode: SampleMode) -> RuntimeData {
     // I don't see any runtime going beyond 10 seconds, which would only result
     // in 10,000,000,000 ^ 2, or 10^20. A u128 has 10^38 precision.
 
@@ -112,70 +469,252 @@ fn generate_runtime_stats(samples: &[Duration]) -> RuntimeData {
         std_dev,
         sample_count: samples.len(),
         outlier_count: 0,
+        max_sample: samples[samples.len() - 1],
+        outlier_filter: OutlierFilter::None,
         first_quartile,
         third_quartile,
+        min: samples[0],
+        max: samples[samples.len() - 1],
+        answer_history: None,
+        sample_mode,
+        warmup_iterations: 0,
+        input_bytes: None,
+    }
+}
+
+// A function that consumes its own state, such as a cache taken out of a `RefCell`, gives the
+// right answer once, then times a different path. Checking every iteration would cost too much,
+// but the first one catches the usual case.
+fn check_idempotent(result: &impl Display, answer: &str) -> Result<Option<String>, String> {
+    if result.to_string() == answer {
+        Ok(None)
+    } else if ARGS.strict {
+        Err(format!(
+            "Error during timing phase: {}",
+            NON_IDEMPOTENT_WARNING
+        ))
+    } else {
+        Ok(Some(NON_IDEMPOTENT_WARNING.to_owned()))
     }
 }
 
-fn bench_function_runtime<Output, OutputErr>(
-    bench_time: u64,
+// The warning is returned alongside the data, if the function's first result after answering
+// differed from its answer.
+fn bench_function_runtime<Output: Display, OutputErr: Display>(
+    sample_mode: SampleMode,
+    mut answer_history: Option<AnswerHistory>,
+    answer: &str,
+    samples_path: Option<&Path>,
     func: impl Fn() -> Result<Output, OutputErr>,
-) -> RuntimeData {
-    let bench_start = Instant::now();
-    let mut samples = Vec::with_capacity(MAX_SAMPLES);
+) -> Result<(RuntimeData, Option<String>), String> {
+    // Waiting for our turn isn't part of the bench time.
+    let _timing_guard = timing_guard();
+    // A huge --iterations mustn't reserve gigabytes before the first sample.
+    let mut samples = Vec::with_capacity(match sample_mode {
+        SampleMode::Timed(_) => MAX_SAMPLES,
+        SampleMode::Iterations(count) => count.min(MAX_SAMPLES),
+    });
+    let mut warning = None;
+    let mut warmup_iterations = 0;
 
-    loop {
-        let start = Instant::now();
-        let res = func();
-        let elapsed = start.elapsed();
-        samples.push(elapsed);
+    let sampled = catch_unwind(AssertUnwindSafe(|| {
+        // Warms the caches and lazy statics, and faults in the pages, so the first samples
+        // aren't outliers. Nothing is recorded, and it isn't part of the bench time.
+        let warmup_start = Instant::now();
+        while warmup_start.elapsed() < ARGS.warmup {
+            let res = func();
+            capture::trim_discarded();
+            match &res {
+                Ok(result) if warmup_iterations == 0 => {
+                    warning = check_idempotent(result, answer)?;
+                }
+                Ok(_) => {}
+                Err(e) => return Err(format!("Error during timing phase: {}", e)),
+            }
+            drop(black_box(res));
+            warmup_iterations += 1;
+        }
+
+        let bench_start = Instant::now();
+        loop {
+            let start = Instant::now();
+            let res = func();
+            let elapsed = start.elapsed();
+            samples.push(elapsed);
+            capture::trim_discarded();
+
+            // The function already succeeded when producing the answer, so an error here means
+            // it isn't pure. Recording happens outside the timed region, so it only costs wall time.
+            match &res {
+                Ok(result) => {
+                    if let Some(history) = &mut answer_history {
+                        history.record(result);
+                    }
+                }
+                Err(e) => return Err(format!("Error during timing phase: {}", e)),
+            }
+
+            if samples.len() == 1 && warmup_iterations == 0 {
+                if let Ok(result) = &res {
+                    warning = check_idempotent(result, answer)?;
+                }
+            }
+
+            // Don't drop while measuring, in case the user returns a non-trivial type.
+            drop(black_box(res));
 
-        // Don't drop while measuring, in case the user returns a non-trivial type.
-        // Also don't handle errors, as the function is assumed to be pure, and has already
-        // had its return value checked in our caller.
-        drop(black_box(res));
+            let is_done = match sample_mode {
+                SampleMode::Timed(bench_time) => {
+                    (bench_start.elapsed().as_secs() >= bench_time
+                        && samples.len() >= ARGS.min_samples)
+                        || samples.len() > MAX_SAMPLES
+                }
+                // An explicit count is taken as-is, so runs can be compared sample for sample.
+                SampleMode::Iterations(count) => samples.len() >= count,
+            };
 
-        if (bench_start.elapsed().as_secs() >= bench_time && samples.len() >= 10)
-            || samples.len() > MAX_SAMPLES
-        {
-            break;
+            if is_done {
+                break Ok(());
+            }
+        }
+    }));
+
+    match sampled {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return Err(e),
+        Err(payload) => {
+            return Err(format!(
+                "Panic during timing phase: {}",
+                panic_message(&*payload)
+            ))
         }
     }
 
+    // Written here, so the samples don't have to be kept once the statistics are worked out.
+    if let Some(path) = samples_path {
+        dump_samples(path, &samples, sample_mode)?;
+    }
+
     samples.sort_unstable();
-    let unfiltered_stats = generate_runtime_stats(&samples);
-
-    // The raw samples have some pretty extreme outliers. We'll filter out those more than 2 standard
-    // deviations from the unfiltered mean and recalculate the mean and std. dev.
-    samples.retain(|&sample| {
-        let (smaller, larger) = (
-            sample.min(unfiltered_stats.mean),
-            sample.max(unfiltered_stats.mean),
-        );
-        (larger - smaller) <= unfiltered_stats.std_dev * 2
-    });
+    let mut stats = filter_outliers(samples, sample_mode, ARGS.outlier);
+    stats.answer_history = answer_history.map(Box::new);
+    stats.warmup_iterations = warmup_iterations;
 
-    let mut filtered_stats = generate_runtime_stats(&samples);
-    filtered_stats.outlier_count = unfiltered_stats.sample_count - filtered_stats.sample_count;
+    Ok((stats, warning))
+}
 
+// Writes the samples in the order they were taken, one nanosecond count per line, after a
+// line describing the run.
+fn dump_samples(path: &Path, samples: &[Duration], sample_mode: SampleMode) -> Result<(), String> {
+    let mode = match sample_mode {
+        SampleMode::Timed(bench_time) => format!("bench_time: {}s", bench_time),
+        SampleMode::Iterations(count) => format!("iterations: {}", count),
+    };
+
+    let mut csv = format!("# {}, samples: {}\n", mode, samples.len());
+    for sample in samples {
+        writeln!(csv, "{}", sample.as_nanos()).unwrap();
+    }
+
+    let write = || {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, csv)
+    };
+    write().map_err(|e| format!("Error writing samples to '{}': {}", path.display(), e))
+}
+
+// Expects the samples to be sorted.
+fn filter_outliers(
+    mut samples: Vec<Duration>,
+    sample_mode: SampleMode,
+    filter: OutlierFilter,
+) -> RuntimeData {
+    let unfiltered_stats = generate_runtime_stats(&samples, sample_mode);
+    if filter == OutlierFilter::None {
+        return unfiltered_stats;
+    }
+
+    // The raw samples have some pretty extreme outliers. We'll filter them out and recalculate
+    // the mean and std. dev.
+    match filter {
+        // The quartiles aren't moved by the outliers, unlike the std. dev., so this still
+        // filters heavily skewed samples.
+        OutlierFilter::Iqr => {
+            let fence = (unfiltered_stats.third_quartile - unfiltered_stats.first_quartile) * 3 / 2;
+            let lower = unfiltered_stats.first_quartile.saturating_sub(fence);
+            let upper = unfiltered_stats.third_quartile + fence;
+            samples.retain(|&sample| (lower..=upper).contains(&sample));
+        }
+        OutlierFilter::TwoSigma => samples.retain(|&sample| {
+            let (smaller, larger) = (
+                sample.min(unfiltered_stats.mean),
+                sample.max(unfiltered_stats.mean),
+            );
+            (larger - smaller) <= unfiltered_stats.std_dev * 2
+        }),
+        OutlierFilter::None => unreachable!(),
+    }
+
+    let mut filtered_stats = generate_runtime_stats(&samples, sample_mode);
+    filtered_stats.outlier_count = unfiltered_stats.sample_count - filtered_stats.sample_count;
+    filtered_stats.max_sample = unfiltered_stats.max_sample;
+    filtered_stats.outlier_filter = filter;
     filtered_stats
 }
 
-fn bench_function_memory<Output, OutputErr>(
+// The buffer is kept between traces, so this only allocates for a thread's first trace, or
+// one that outgrew it.
+fn reserve_trace(alloc: &TracingAlloc) {
+    alloc.reserve_events(ARGS.trace_reserve / mem::size_of::<Event>());
+}
+
+fn bench_function_memory<Output, OutputErr: Display>(
     alloc: &TracingAlloc,
     func: impl Fn() -> Result<Output, OutputErr>,
-) -> Result<MemoryData, MemoryBenchError> {
-    alloc.clear_buffer();
-
-    // No need to handle an error here, we did it earlier.
+) -> Result<MemoryData, String> {
+    reserve_trace(alloc);
     alloc.enable_tracing();
     // Don't discard here, or dropping the return value will be caught
-    // by the tracer.
-    let res = func();
+    // by the tracer. A panic still needs to stop the tracing.
+    let res = catch_unwind(AssertUnwindSafe(&func));
     alloc.disable_tracing();
-    let _ = res;
 
-    Ok(read_memory_data(alloc))
+    match res {
+        Ok(Ok(_)) => Ok(read_memory_data(alloc)),
+        Ok(Err(e)) => Err(format!("Error during memory phase: {}", e)),
+        Err(payload) => Err(format!(
+            "Panic during memory phase: {}",
+            panic_message(&*payload)
+        )),
+    }
+}
+
+/// Runs the function once, measuring both its run time and memory use.
+fn measure_single_execution<Output, OutputErr>(
+    alloc: &TracingAlloc,
+    func: impl Fn() -> Result<Output, OutputErr>,
+) -> (Result<Output, OutputErr>, Duration, MemoryData) {
+    reserve_trace(alloc);
+    alloc.enable_tracing();
+    let start = Instant::now();
+    let res = catch_unwind(AssertUnwindSafe(&func));
+    let elapsed = start.elapsed();
+    alloc.disable_tracing();
+
+    // The caller is expected to handle the panic, but other threads can't trace until the
+    // tracing is stopped.
+    let res = res.unwrap_or_else(|payload| resume_unwind(payload));
+    (res, elapsed, read_memory_data(alloc))
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Unknown reason")
 }
 
 pub(crate) enum BenchEvent {
@@ -183,6 +722,7 @@ pub(crate) enum BenchEvent {
         answer: String,
         id: usize,
         is_alt: bool,
+        input: Option<Arc<InputProvenance>>,
     },
     Memory {
         data: MemoryData,
@@ -192,19 +732,109 @@ pub(crate) enum BenchEvent {
         data: RuntimeData,
         id: usize,
     },
+    /// The function was only run for its answer, see [`Bench::run_once`].
+    NotBenched {
+        id: usize,
+    },
+    /// How long the call that produced the answer took, when nothing else is measured.
+    AnswerTime {
+        elapsed: Duration,
+        id: usize,
+    },
     Error {
         err: String,
         id: usize,
     },
+    /// Something about the function's measurements looks wrong, but they were still taken.
+    Warning {
+        warning: String,
+        id: usize,
+    },
+    /// The function failed after its answer was reported, while being measured.
+    LateError {
+        err: String,
+        id: usize,
+    },
+    /// The function couldn't be run because its input file couldn't be opened.
+    InputError {
+        err: String,
+        id: usize,
+    },
+    Metrics {
+        metrics: Metrics,
+        id: usize,
+    },
+    StackUsage {
+        bytes: usize,
+        id: usize,
+    },
+    /// What the function printed while running, if it was captured.
+    Output {
+        output: String,
+        id: usize,
+    },
+    /// The function is written in another language, so not all of its allocations are traced.
+    #[cfg_attr(not(feature = "ffi"), allow(dead_code))]
+    Foreign {
+        id: usize,
+    },
     Finish {
         id: usize,
     },
 }
 
+/// The outcome of benching a single function, kept after its row has been rendered.
+pub(crate) struct FunctionSummary {
+    pub(crate) day: u8,
+    pub(crate) name: &'static str,
+    /// Which part of the puzzle this function solves, if any.
+    pub(crate) part: Option<u8>,
+    pub(crate) label: String,
+    pub(crate) mean: Option<Duration>,
+    /// The slowest sample, even if it was filtered out.
+    pub(crate) max_sample: Option<Duration>,
+    pub(crate) is_error: bool,
+    pub(crate) is_missing_input: bool,
+    pub(crate) input: Option<Arc<InputProvenance>>,
+}
+
 pub(crate) struct AlternateAnswer {
     pub(crate) answer: String,
     pub(crate) day: u8,
-    pub(crate) day_function_id: u8,
+    /// The puzzle's name, from its [`Day`](crate::Day).
+    pub(crate) name: &'static str,
+    /// Which part of the puzzle the function solves, or `None` for other functions.
+    pub(crate) part: Option<u8>,
+    /// The part number, or the other function's name.
+    pub(crate) label: String,
+}
+
+impl AlternateAnswer {
+    /// `label` is the part number, or the other function's name.
+    pub(crate) fn for_function(
+        answer: String,
+        day: u8,
+        name: &'static str,
+        part: Option<u8>,
+        label: &str,
+    ) -> Self {
+        Self {
+            answer,
+            day,
+            name,
+            part,
+            label: label.to_owned(),
+        }
+    }
+
+    pub(crate) fn heading(&self) -> String {
+        format!("Day {} ({}), Part: {}", self.day, self.name, self.label)
+    }
+
+    /// Orders by day, with the parts before the other functions.
+    pub(crate) fn sort_key(&self) -> (u8, bool, Option<u8>, &str) {
+        (self.day, self.part.is_none(), self.part, &self.label)
+    }
 }
 
 pub struct Bench {
@@ -212,18 +842,70 @@ pub struct Bench {
     pub(crate) id: usize,
     pub(crate) chan: Sender<BenchEvent>,
     pub(crate) run_only: bool,
-    pub(crate) bench_time: u64,
+    pub(crate) sample_mode: SampleMode,
+    /// If set, records the answer of every timing iteration, storing at most this many distinct values.
+    pub(crate) answer_history: Option<usize>,
+    pub(crate) measure_memory: bool,
+    /// Time and trace the single call that produces the answer. Only used with `run_only`.
+    pub(crate) measure_single: bool,
+    /// Where the input came from, reported with the answer.
+    pub(crate) input: Option<Arc<InputProvenance>>,
+    /// The answer the function should give, which is an error if it doesn't.
+    pub(crate) expected: Option<&'static str>,
+    /// Where to write the raw timing samples, with `--dump-samples`.
+    pub(crate) samples_path: Option<PathBuf>,
+    /// How many bytes the function processes, for its throughput. The input's length, unless
+    /// given to [`bench_with_bytes`](Self::bench_with_bytes).
+    pub(crate) input_bytes: Option<usize>,
 }
 
 impl Bench {
-    pub fn bench_alt<T, E>(self, f: impl Fn() -> Result<T, E> + Copy) -> Result<(), BenchError>
+    /// Creates a bench for calling a day's function outside of [`run`](crate::run), such as
+    /// in a `#[test]`. The function is only run for its answer, nothing is measured.
+    ///
+    /// ```rust
+    /// # use aoc_lib::{Bench, BenchResult};
+    /// fn part_1(input: &str, bench: Bench) -> BenchResult {
+    ///     bench.bench(|| Ok::<_, String>(input.lines().count()))
+    /// }
+    ///
+    /// let (bench, sink) = Bench::test_harness();
+    /// part_1("1\n2\n3\n", bench)?;
+    /// assert_eq!(sink.answer(), "3");
+    /// # Ok::<(), aoc_lib::BenchError>(())
+    /// ```
+    pub fn test_harness() -> (Bench, TestSink) {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let bench = Bench {
+            alloc: &TEST_ALLOC,
+            id: 0,
+            chan: sender,
+            run_only: true,
+            sample_mode: SampleMode::Iterations(1),
+            answer_history: None,
+            measure_memory: false,
+            measure_single: false,
+            input: None,
+            expected: None,
+            samples_path: None,
+            input_bytes: None,
+        };
+
+        let sink = TestSink {
+            receiver,
+            result: Mutex::new(None),
+        };
+        (bench, sink)
+    }
+
+    pub fn bench_alt<T, E>(self, f: impl Fn() -> Result<T, E>) -> Result<(), BenchError>
     where
         T: Display,
         E: Display,
     {
         self.bench_inner(true, f)
     }
-    pub fn bench<T, E>(self, f: impl Fn() -> Result<T, E> + Copy) -> Result<(), BenchError>
+    pub fn bench<T, E>(self, f: impl Fn() -> Result<T, E>) -> Result<(), BenchError>
     where
         T: Display,
         E: Display,
@@ -231,95 +913,603 @@ impl Bench {
         self.bench_inner(false, f)
     }
 
-    fn bench_inner<T, E>(
+    /// Like [`bench`](Self::bench), but the throughput is worked out from `len` bytes, for
+    /// functions that only process part of the input.
+    pub fn bench_with_bytes<T, E>(
         self,
-        is_alt: bool,
-        f: impl Fn() -> Result<T, E> + Copy,
+        len: usize,
+        f: impl Fn() -> Result<T, E>,
     ) -> Result<(), BenchError>
     where
         T: Display,
         E: Display,
     {
-        let answer = f()
-            .map_err(|e| {
-                self.chan.send(BenchEvent::Error {
-                    err: e.to_string(),
+        let bench = Self {
+            input_bytes: Some(len),
+            ..self
+        };
+        bench.bench_inner(false, f)
+    }
+
+    /// Runs the function once for its answer, without timing or tracing it. For functions run
+    /// for their side effects, such as writing out a visualisation, which would otherwise be
+    /// benched for no reason.
+    pub fn run_once<T, E>(self, f: impl FnOnce() -> Result<T, E>) -> Result<(), BenchError>
+    where
+        T: Display,
+        E: Display,
+    {
+        let start = Instant::now();
+        let answer = f();
+        let elapsed = start.elapsed();
+
+        if self.report_answer(false, answer)?.is_none() {
+            return Ok(());
+        }
+        if self.run_only {
+            return self.report_single(elapsed, None);
+        }
+
+        self.chan
+            .send(BenchEvent::NotBenched { id: self.id })
+            .map_err(|_| BenchError::ChannelError(self.id))
+    }
+
+    /// Like [`bench`](Self::bench), but the function also returns metrics to display with the answer.
+    ///
+    /// Only the metrics from the invocation that produced the answer are kept; those returned
+    /// during the timing iterations are ignored.
+    pub fn bench_with_metrics<T, E>(
+        self,
+        f: impl Fn() -> Result<(T, Metrics), E>,
+    ) -> Result<(), BenchError>
+    where
+        T: Display,
+        E: Display,
+    {
+        let (answer, elapsed, memory) = self.call(&f);
+        let (answer, metrics) = match answer {
+            Ok((answer, metrics)) => (Ok::<_, E>(answer), Some(metrics)),
+            Err(e) => (Err(e), None),
+        };
+
+        let Some(answer) = self.report_answer(false, answer)? else {
+            return Ok(());
+        };
+        self.report_single(elapsed, memory)?;
+
+        if let Some(metrics) = metrics {
+            self.chan
+                .send(BenchEvent::Metrics {
+                    metrics,
                     id: self.id,
                 })
-            })
-            .map_err(|_| BenchError::ChannelError(self.id))?;
+                .map_err(|_| BenchError::ChannelError(self.id))?;
+        }
 
-        self.chan
-            .send(BenchEvent::Answer {
-                answer: answer.to_string(),
+        self.measure(&answer, || f().map(|(answer, _)| answer))
+    }
+
+    fn bench_inner<T, E>(self, is_alt: bool, f: impl Fn() -> Result<T, E>) -> Result<(), BenchError>
+    where
+        T: Display,
+        E: Display,
+    {
+        let (answer, elapsed, memory) = self.call(&f);
+        if let Some(answer) = self.report_answer(is_alt, answer)? {
+            self.report_single(elapsed, memory)?;
+            self.measure(&answer, f)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Calls the function to get the answer, tracing its memory too if requested. The call is
+    // always timed, as it's cheap next to any solution.
+    fn call<T, E>(
+        &self,
+        f: impl Fn() -> Result<T, E>,
+    ) -> (Result<T, E>, Duration, Option<MemoryData>) {
+        if self.measure_single {
+            let (answer, elapsed, memory) = measure_single_execution(self.alloc, f);
+            (answer, elapsed, Some(memory))
+        } else {
+            let start = Instant::now();
+            let answer = f();
+            (answer, start.elapsed(), None)
+        }
+    }
+
+    // Without the single call being measured, run mode still gets a rough idea of how long
+    // the answer took.
+    fn report_single(
+        &self,
+        elapsed: Duration,
+        memory: Option<MemoryData>,
+    ) -> Result<(), BenchError> {
+        let Some(memory) = memory else {
+            if !self.run_only {
+                return Ok(());
+            }
+            return self
+                .chan
+                .send(BenchEvent::AnswerTime {
+                    elapsed,
+                    id: self.id,
+                })
+                .map_err(|_| BenchError::ChannelError(self.id));
+        };
+
+        let timing = RuntimeData {
+            sample_count: 1,
+            mean: elapsed,
+            std_dev: Duration::ZERO,
+            first_quartile: elapsed,
+            third_quartile: elapsed,
+            min: elapsed,
+            max: elapsed,
+            outlier_count: 0,
+            max_sample: elapsed,
+            outlier_filter: OutlierFilter::None,
+            answer_history: None,
+            sample_mode: SampleMode::Iterations(1),
+            warmup_iterations: 0,
+            input_bytes: self.input_bytes,
+        };
+
+        [
+            BenchEvent::Memory {
+                data: memory,
                 id: self.id,
-                is_alt,
-            })
+            },
+            BenchEvent::Timing {
+                data: timing,
+                id: self.id,
+            },
+        ]
+        .into_iter()
+        .try_for_each(|event| {
+            self.chan
+                .send(event)
+                .map_err(|_| BenchError::ChannelError(self.id))
+        })
+    }
+
+    #[cfg(feature = "ffi")]
+    pub(crate) fn report_foreign(&self) -> Result<(), BenchError> {
+        self.chan
+            .send(BenchEvent::Foreign { id: self.id })
+            .map_err(|_| BenchError::ChannelError(self.id))
+    }
+
+    // Returns the answer if the function produced one, in which case it can be measured.
+    fn report_answer<T, E>(
+        &self,
+        is_alt: bool,
+        answer: Result<T, E>,
+    ) -> Result<Option<String>, BenchError>
+    where
+        T: Display,
+        E: Display,
+    {
+        let (event, answer) = match answer {
+            Ok(answer) => {
+                let answer = answer.to_string();
+                // An alternate answer isn't the one that gets submitted, so isn't checked.
+                match self
+                    .expected
+                    .filter(|&expected| !is_alt && expected != answer)
+                {
+                    Some(expected) => {
+                        let err = if ARGS.censor {
+                            "not the expected answer".to_owned()
+                        } else {
+                            format!("expected {}, got {}", expected, answer)
+                        };
+                        (BenchEvent::Error { err, id: self.id }, None)
+                    }
+                    None => {
+                        let event = BenchEvent::Answer {
+                            answer: answer.clone(),
+                            id: self.id,
+                            is_alt,
+                            input: self.input.clone(),
+                        };
+                        (event, Some(answer))
+                    }
+                }
+            }
+            Err(e) => {
+                let event = BenchEvent::Error {
+                    err: e.to_string(),
+                    id: self.id,
+                };
+                (event, None)
+            }
+        };
+
+        self.chan
+            .send(event)
             .map_err(|_| BenchError::ChannelError(self.id))?;
 
-        if !self.run_only {
-            let data = bench_function_memory(self.alloc, f)
-                .map_err(|e| BenchError::MemoryBenchError(e, self.id))?;
+        Ok(answer)
+    }
+
+    fn measure<T, E>(&self, answer: &str, f: impl Fn() -> Result<T, E>) -> Result<(), BenchError>
+    where
+        T: Display,
+        E: Display,
+    {
+        if self.run_only {
+            return Ok(());
+        }
 
-            self.chan
-                .send(BenchEvent::Memory { data, id: self.id })
-                .map_err(|_| BenchError::ChannelError(self.id))?;
+        let history = self.answer_history.map(AnswerHistory::new);
+        let measure = || {
+            let memory = if self.measure_memory {
+                bench_function_memory(self.alloc, &f).and_then(|data| {
+                    self.chan
+                        .send(BenchEvent::Memory { data, id: self.id })
+                        .map_err(|_| "Channel closed".to_owned())
+                })
+            } else {
+                Ok(())
+            };
+            memory.and_then(|_| {
+                bench_function_runtime(
+                    self.sample_mode,
+                    history,
+                    answer,
+                    self.samples_path.as_deref(),
+                    &f,
+                )
+            })
+        };
 
-            let data = bench_function_runtime(self.bench_time, f);
+        // The answer's call already printed whatever the function prints, there's no
+        // need to keep thousands of copies of it.
+        let measured = if ARGS.nocapture {
+            measure()
+        } else {
+            capture::discard(measure)
+        };
+
+        // A non-idempotent function can fail after it already gave us an answer, which
+        // we don't want to look like a success.
+        let events = match measured {
+            Ok((mut data, warning)) => {
+                data.input_bytes = self.input_bytes;
+                let warning = warning.map(|warning| BenchEvent::Warning {
+                    warning,
+                    id: self.id,
+                });
+                let timing = BenchEvent::Timing { data, id: self.id };
+                [warning, Some(timing)]
+            }
+            Err(err) => [Some(BenchEvent::LateError { err, id: self.id }), None],
+        };
+
+        events.into_iter().flatten().try_for_each(|event| {
             self.chan
-                .send(BenchEvent::Timing { data, id: self.id })
-                .map_err(|_| BenchError::ChannelError(self.id))?;
+                .send(event)
+                .map_err(|_| BenchError::ChannelError(self.id))
+        })
+    }
+}
+
+/// Receives what the function given a [`Bench::test_harness`] bench reported.
+pub struct TestSink {
+    receiver: Receiver<BenchEvent>,
+    // Reading the result drains the channel, so it's kept for the next call.
+    result: Mutex<Option<Result<String, String>>>,
+}
+
+impl TestSink {
+    /// The answer or error the function reported, or `None` if it reported neither.
+    pub fn result(&self) -> Option<Result<String, String>> {
+        let mut result = self.result.lock().unwrap_or_else(|e| e.into_inner());
+        if result.is_none() {
+            *result = self.receiver.try_iter().find_map(|event| match event {
+                BenchEvent::Answer { answer, .. } => Some(Ok(answer)),
+                BenchEvent::Error { err, .. } | BenchEvent::LateError { err, .. } => Some(Err(err)),
+                _ => None,
+            });
         }
+        result.clone()
+    }
 
-        Ok(())
+    /// The answer the function reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function reported an error, or no answer at all.
+    pub fn answer(&self) -> String {
+        match self.result() {
+            Some(Ok(answer)) => answer,
+            Some(Err(err)) => panic!("Function reported an error: {}", err),
+            None => panic!("Function didn't report an answer"),
+        }
     }
 }
 
-pub(crate) fn bench_worker(day: u8, bench: Bench, func: SetupFunction) {
+// Runs the day's function, reporting any error or panic through the bench's channel.
+fn run_setup(input: &str, bench: Bench, func: SetupFunction) {
     let id = bench.id;
     let sender = bench.chan.clone();
-    match input(day).open() {
-        Ok(input) => {
-            let did_panic = catch_unwind(|| func(&input, bench));
-
-            match did_panic {
-                Ok(Ok(_)) => {}
-                Ok(Err(e)) => {
-                    sender
-                        .send(BenchEvent::Error {
-                            err: e.to_string(),
-                            id,
-                        })
-                        .expect("Unable to send error");
-                }
-                Err(payload) => {
-                    let msg = payload
-                        .downcast_ref::<&str>()
-                        .copied()
-                        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
-                        .unwrap_or("Unknown reason");
-
-                    sender
-                        .send(BenchEvent::Error {
-                            err: format!("Panic: {}", msg),
-                            id,
-                        })
-                        .expect("Unable to send error");
-                }
+    let run = || catch_unwind(AssertUnwindSafe(|| func(input, bench)));
+    let (res, output) = if ARGS.nocapture {
+        (run(), String::new())
+    } else {
+        capture::capture(run)
+    };
+
+    // The sends can only fail if the function timed out and was abandoned, in which case
+    // nobody is listening anymore.
+    if let Some(bytes) = platform::peak_stack_usage() {
+        let _ = sender.send(BenchEvent::StackUsage { bytes, id });
+    }
+
+    if !output.is_empty() {
+        let _ = sender.send(BenchEvent::Output { output, id });
+    }
+
+    let err = match res {
+        Ok(Ok(_)) => return,
+        Ok(Err(e)) => e.to_string(),
+        Err(payload) => format!("Panic: {}", panic_message(&*payload)),
+    };
+    let _ = sender.send(BenchEvent::Error { err, id });
+}
+
+// Threads can't be killed, so if the function doesn't produce its answer within the timeout
+// we stop listening to it and leave its thread running. Its events go through a proxy channel
+// so we can watch for the answer, and so it errors out on its next send once abandoned.
+fn run_setup_with_timeout(timeout: Duration, input: String, mut bench: Bench, func: SetupFunction) {
+    let id = bench.id;
+    let sender = bench.chan.clone();
+    let (proxy_sender, proxy_receiver) = crossbeam_channel::unbounded();
+    bench.chan = proxy_sender;
+    spawn_with_stack(move || run_setup(&input, bench, func));
+
+    let deadline = Instant::now() + timeout;
+    let mut is_answered = false;
+    loop {
+        // Only the answer is time-limited; once we have it, the measurements take as long as they take.
+        let event = if is_answered {
+            proxy_receiver
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            proxy_receiver.recv_deadline(deadline)
+        };
+
+        match event {
+            Ok(event) => {
+                is_answered |=
+                    matches!(event, BenchEvent::Answer { .. } | BenchEvent::Error { .. });
+                sender.send(event).expect("Unable to forward event");
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                sender
+                    .send(BenchEvent::Error {
+                        err: format!("Timed out after {:?}", timeout),
+                        id,
+                    })
+                    .expect("Unable to send error");
+                break;
             }
         }
-        Err(BenchError::InputFileError { inner, name }) => {
+    }
+}
+
+/// Runs the function on the bench's stack, giving up on its answer after `--timeout`. Its
+/// errors and panics are sent as error events.
+pub(crate) fn run_function(input: String, bench: Bench, func: SetupFunction) {
+    match ARGS.timeout {
+        Some(timeout) => run_setup_with_timeout(timeout, input, bench, func),
+        None => {
+            if let Err(payload) = run_with_stack(|| run_setup(&input, bench, func)) {
+                resume_unwind(payload);
+            }
+        }
+    }
+}
+
+pub(crate) fn bench_worker(
+    day: u8,
+    variant: Option<String>,
+    mut bench: Bench,
+    func: SetupFunction,
+) {
+    let id = bench.id;
+    let sender = bench.chan.clone();
+    let input_file = day_input(day, variant.as_deref());
+    let path = input_file.path();
+    let is_default = input_file.is_default();
+    match input_file.open() {
+        Ok(input) => {
+            bench.input = Some(InputProvenance::shared(path, &input, is_default));
+            bench.input_bytes = Some(input.len());
+            run_function(input, bench, func);
+        }
+        Err(e) => {
+            // InputFile::open should only return errors from opening or fetching the input, but
+            // anything else still fails this function rather than the whole run.
+            let err = describe_input_error(&e).unwrap_or_else(|| e.to_string());
             sender
-                .send(BenchEvent::Error {
-                    err: format!("{}: {:?}", name, inner.kind()),
-                    id,
-                })
+                .send(BenchEvent::InputError { err, id })
                 .expect("Unable to send error");
         }
-        Err(_) => unreachable!(), // InputFile::open only returns one error variant.
     }
 
     sender
         .send(BenchEvent::Finish { id })
         .expect("Unable to send finish");
 }
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        alloc::{set_trace_buffer_cap, tests::BUFFER_CAP},
+        TEST_ALLOC,
+    };
+
+    #[test]
+    fn test_sink_keeps_the_answer() {
+        let (bench, sink) = Bench::test_harness();
+        bench.bench(|| Ok::<_, String>(42)).unwrap();
+        assert_eq!(sink.answer(), "42");
+        assert_eq!(sink.answer(), "42");
+        assert_eq!(sink.result(), Some(Ok("42".to_owned())));
+    }
+
+    #[test]
+    fn free_of_block_from_before_trace() {
+        let block = vec![0u8; 1 << 20];
+
+        TEST_ALLOC.enable_tracing();
+        thread::spawn(move || drop(block)).join().unwrap();
+        TEST_ALLOC.disable_tracing();
+
+        // Freeing more than the trace allocated mustn't wrap the live bytes round.
+        let memory = read_memory_data(&TEST_ALLOC);
+        assert!(memory.max_memory < 1 << 20);
+        assert!(memory
+            .graph_points
+            .iter()
+            .all(|&(_, bytes)| bytes < (1 << 20) as f32));
+    }
+
+    #[test]
+    fn allocation_storm_past_the_cap() {
+        const STORM: usize = 50_000;
+        const CAP: usize = 1000;
+
+        let _cap = BUFFER_CAP.lock().unwrap_or_else(|e| e.into_inner());
+        set_trace_buffer_cap(CAP * mem::size_of::<Event>());
+        TEST_ALLOC.enable_tracing();
+        for i in 0..STORM {
+            drop(black_box(Box::new(i)));
+        }
+        TEST_ALLOC.disable_tracing();
+        set_trace_buffer_cap(usize::MAX);
+
+        let memory = read_memory_data(&TEST_ALLOC);
+        assert!(memory.overflowed());
+        assert!(memory.recorded_events <= CAP, "{}", memory.recorded_events);
+        // Each box is an allocation and a free, and nothing is lost from the counters.
+        assert!(memory.recorded_events + memory.dropped_events >= STORM * 2);
+        assert!(memory.num_allocs() >= STORM);
+        assert!(memory.num_frees() >= STORM);
+        assert!(memory.total_allocated() >= STORM * mem::size_of::<usize>());
+
+        let warning = memory.truncation_warning().unwrap();
+        assert!(
+            warning.starts_with(&format!(
+                "memory trace truncated after {} events (",
+                render_decimal(memory.recorded_events).trim()
+            )),
+            "{}",
+            warning
+        );
+        assert!(
+            warning.ends_with(" dropped); peak/alloc counts remain exact"),
+            "{}",
+            warning
+        );
+
+        // A trace that fits has no warning.
+        TEST_ALLOC.enable_tracing();
+        drop(black_box(Box::new(0)));
+        TEST_ALLOC.disable_tracing();
+        assert!(read_memory_data(&TEST_ALLOC).truncation_warning().is_none());
+    }
+
+    #[test]
+    fn panic_while_tracing() {
+        let res = bench_function_memory(&TEST_ALLOC, || -> Result<(), String> {
+            black_box(vec![0u8; 1 << 20]);
+            panic!("boom")
+        });
+        assert_eq!(
+            res.err().as_deref(),
+            Some("Panic during memory phase: boom")
+        );
+
+        // The panic stopped the trace, so the thread can trace the next function, without the
+        // last one's events.
+        let memory = bench_function_memory(&TEST_ALLOC, || -> Result<_, String> {
+            Ok(black_box(Box::new(1u64)))
+        })
+        .unwrap();
+        assert!(memory.max_memory < 1 << 20);
+        assert!(memory.end_ts < 1000.0);
+        assert!(memory.num_allocs >= 1);
+
+        let res = catch_unwind(|| {
+            measure_single_execution(&TEST_ALLOC, || -> Result<(), String> { panic!("boom") })
+        });
+        assert!(res.is_err());
+        let (res, _, memory) = measure_single_execution(&TEST_ALLOC, || -> Result<_, String> {
+            Ok(black_box(vec![0u8; 64]))
+        });
+        assert_eq!(res.unwrap().len(), 64);
+        assert!(memory.max_memory >= 64 && memory.max_memory < 1 << 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tracing enabled while this thread was already tracing")]
+    fn nested_tracing() {
+        TEST_ALLOC.enable_tracing();
+        // Stops the trace as the panic unwinds, so the other tests can still trace.
+        struct Disable;
+        impl Drop for Disable {
+            fn drop(&mut self) {
+                TEST_ALLOC.disable_tracing();
+            }
+        }
+        let _disable = Disable;
+        TEST_ALLOC.enable_tracing();
+    }
+
+    // 199 flat samples of 100µs and one 10x spike, sorted as the bench sorts them.
+    fn spiked_samples() -> Vec<Duration> {
+        let mut samples = vec![Duration::from_micros(100); 199];
+        samples.push(Duration::from_micros(1000));
+        samples
+    }
+
+    #[test]
+    fn filters_report_the_spike() {
+        for filter in [OutlierFilter::Iqr, OutlierFilter::TwoSigma] {
+            let stats = filter_outliers(spiked_samples(), SampleMode::Timed(0), filter);
+
+            assert_eq!(stats.outlier_filter, filter);
+            assert_eq!(stats.outlier_count, 1, "{}", filter);
+            assert_eq!(stats.sample_count, 199, "{}", filter);
+            assert_eq!(stats.mean, Duration::from_micros(100), "{}", filter);
+            assert_eq!(stats.max, Duration::from_micros(100), "{}", filter);
+            // The spike is gone from the statistics, but not from the worst case.
+            assert_eq!(stats.max_sample, Duration::from_micros(1000), "{}", filter);
+            assert!((stats.worst_factor() - 10.0).abs() < 1e-9, "{}", filter);
+        }
+    }
+
+    #[test]
+    fn no_filter_keeps_raw_stats() {
+        let stats = filter_outliers(spiked_samples(), SampleMode::Timed(0), OutlierFilter::None);
+
+        assert_eq!(stats.outlier_filter, OutlierFilter::None);
+        assert_eq!(stats.outlier_count, 0);
+        assert_eq!(stats.sample_count, 200);
+        // (199 * 100µs + 1000µs) / 200
+        assert_eq!(stats.mean, Duration::from_nanos(104_500));
+        assert_eq!(stats.max, Duration::from_micros(1000));
+        assert_eq!(stats.max_sample, Duration::from_micros(1000));
+        assert!((stats.worst_factor() - 1000.0 / 104.5).abs() < 1e-9);
+    }
+}
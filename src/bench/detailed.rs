@@ -1,28 +1,67 @@
-use std::{panic, time::Duration};
+use std::{
+    panic,
+    path::PathBuf,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
 
 use bytesize::ByteSize;
-use console::Term;
+use console::Color;
 use crossbeam_channel::Receiver;
 use drawille::Canvas;
 use indicatif::{ProgressBar, ProgressStyle};
-use rayon::{ThreadPool, ThreadPoolBuilder};
+use rayon::ThreadPool;
 
 use crate::{
-    bench::{bench_worker, BenchEvent, MemoryData, RuntimeData, SetupFunction},
+    baseline::save_baseline,
+    bench::{
+        bench_worker, BenchEvent, FunctionSummary, MemoryData, OutlierFilter, RuntimeData,
+        SampleMode, SetupFunction,
+    },
+    day_label,
     misc::ArrWindows,
-    render_decimal, render_duration, Bench, BenchError, BenchResult, Day, TracingAlloc, ARGS,
+    outcome_result,
+    output::OUTPUT,
+    plan::{
+        apply_wall_budget, build_worker_pool, parallelism, print_parallelism, print_pinning,
+        selected_functions,
+    },
+    print_run_notes, print_summary_lines,
+    provenance::InputProvenance,
+    records::update_records,
+    render_decimal, render_duration,
+    speedup::{report_speedups, Measurement},
+    write_badge, Bench, BenchError, BenchResult, Day, Metrics, TracingAlloc, ARGS, RUNNING_YEAR,
 };
 
 struct BenchedFunction {
     name: &'static str,
     day: u8,
+    part: Option<u8>,
     day_function_id: String,
+    /// The input variant it's given, if it isn't the day's own input.
+    variant: Option<String>,
     function: SetupFunction,
+    expected: Option<&'static str>,
+    samples_path: Option<PathBuf>,
+    sample_mode: SampleMode,
     message: String,
     is_multiline_answer: bool,
     is_error: bool,
+    is_missing_input: bool,
     timing_data: Option<RuntimeData>,
     memory_data: Option<MemoryData>,
+    metrics: Option<Metrics>,
+    stack_usage: Option<usize>,
+    /// Registered through the FFI, so only its Rust-side allocations are traced.
+    is_foreign: bool,
+    /// Only run for its answer, through [`Bench::run_once`].
+    is_not_benched: bool,
+    late_error: Option<String>,
+    /// Why the measurements may not be trustworthy.
+    warning: Option<String>,
+    captured_output: Option<String>,
+    input: Option<Arc<InputProvenance>>,
 }
 
 fn render_function_data(func: BenchedFunction, term_width: u16) {
@@ -38,13 +77,49 @@ fn render_function_data(func: BenchedFunction, term_width: u16) {
     } else {
         println!("{}", func.message);
     }
+    if let Some(input) = &func.input {
+        println!("  Input:  {}", input.render());
+    }
+
+    if let Some(err) = &func.late_error {
+        println!("  -- Failed: {}", err);
+    }
+    if let Some(warning) = &func.warning {
+        println!(
+            "  -- Warning: {}",
+            OUTPUT.style().paint(warning, Color::Yellow)
+        );
+    }
+
+    if let Some(output) = &func.captured_output {
+        println!("  -- Captured Output");
+        for line in output.lines() {
+            println!("    {}", line);
+        }
+    }
 
     if func.is_error {
         return;
     }
 
+    if let Some(metrics) = &func.metrics {
+        let name_width = metrics.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+        println!("  -- Metrics");
+        for (name, value) in metrics {
+            println!("    -- {:<width$}  {}", name, value, width = name_width);
+        }
+    }
+
+    if func.is_not_benched {
+        println!("  -- Not benched");
+        return;
+    }
+
     let timing = func.timing_data.expect("No timing data?");
-    println!("  -- Timing");
+    match timing.sample_mode {
+        SampleMode::Timed(_) => println!("  -- Timing"),
+        SampleMode::Iterations(count) => println!("  -- Timing ({} iterations)", count),
+    }
     println!(
         "    -- Mean:       {}    Std. Dev:   {}",
         render_duration(timing.mean, true),
@@ -55,69 +130,246 @@ fn render_function_data(func: BenchedFunction, term_width: u16) {
         render_duration(timing.first_quartile, false,),
         render_duration(timing.third_quartile, false,)
     );
+    println!(
+        "    -- Min:        {}    Max:        {}",
+        render_duration(timing.min, false),
+        render_duration(timing.max, false)
+    );
+    let outliers = match timing.outlier_filter {
+        OutlierFilter::Iqr | OutlierFilter::TwoSigma => render_decimal(timing.outlier_count),
+        OutlierFilter::None => "not filtered".to_owned(),
+    };
     println!(
         "    -- Samples:    {}     Outliers:   {}",
         render_decimal(timing.sample_count),
-        render_decimal(timing.outlier_count),
+        outliers,
     );
+    println!(
+        "    -- Worst:      {}    ({:.1}× mean)",
+        render_duration(timing.max_sample, false),
+        timing.worst_factor()
+    );
+    if let Some(throughput) = timing.throughput() {
+        println!("    -- Throughput: {}", render_throughput(throughput));
+    }
+    if timing.warmup_iterations > 0 {
+        println!(
+            "    -- Warm-up:    {} iterations",
+            render_decimal(timing.warmup_iterations)
+        );
+    }
+
+    if let Some(history) = timing
+        .answer_history
+        .as_ref()
+        .filter(|h| !h.is_consistent())
+    {
+        println!("  -- Answer History ({})", history.pattern());
+        for line in history.summary_lines(ARGS.censor) {
+            println!("    -- {}", line);
+        }
+    }
 
     let memory = func.memory_data.expect("No memory data?");
     let max_memory = format!("{}", ByteSize(memory.max_memory as u64));
     println!("  -- Memory");
     println!(
-        "    -- N. Allocs:  {}     Max Mem.: {}",
+        "    -- N. Allocs:  {}     Max Mem.: {}    Retained: {}",
         render_decimal(memory.num_allocs),
-        max_memory
+        max_memory,
+        ByteSize(memory.retained_memory as u64)
+    );
+    println!(
+        "    -- Total Alloc: {}    N. Frees: {}",
+        ByteSize(memory.total_allocated as u64),
+        render_decimal(memory.num_frees)
     );
+    if let Some(warning) = memory.truncation_warning() {
+        println!(
+            "    -- Warning:    {}",
+            OUTPUT.style().paint(warning, Color::Yellow)
+        );
+    }
+    if func.is_foreign {
+        println!("    -- Note:       only Rust-side allocations are traced for foreign functions");
+    }
+    if let Some(stack_usage) = func.stack_usage {
+        println!(
+            "    -- Stack:      ~{} peak of {}",
+            ByteSize(stack_usage as u64),
+            ByteSize(ARGS.stack_size as u64)
+        );
+    }
+
+    if !memory.regions.is_empty() {
+        println!("    -- Regions");
+    }
+    for (name, region) in &memory.regions {
+        println!(
+            "      {:indent$}{}: {} peak, {} allocs",
+            "",
+            name,
+            ByteSize(region.max_memory as u64),
+            render_decimal(region.num_allocs).trim(),
+            indent = region.depth * 2
+        );
+    }
 
     if memory.num_allocs != 0 {
-        const CHART_HEIGHT: f32 = 10.0 * 4.0;
-        let chart_width = (term_width as u32 - max_memory.len() as u32 - 3) * 2;
-        let x_per_pixel = memory.end_ts / chart_width as f32;
-        let y_per_pixel = memory.max_memory as f32 / CHART_HEIGHT;
-
-        let rendered_max_ts = render_duration(memory.end_ts_duration, false);
-        let (_, ts_unit) = rendered_max_ts.rsplit_once(' ').unwrap();
-
-        let mut canvas = Canvas::new(chart_width, 10);
-        for &[(sx, sy), (ex, ey)] in ArrWindows::new(&memory.graph_points) {
-            let sx = (sx / x_per_pixel) as u32;
-            let sy = (CHART_HEIGHT - (sy / y_per_pixel)) as u32;
-            let ex = (ex / x_per_pixel) as u32;
-            let ey = (CHART_HEIGHT - (ey / y_per_pixel)) as u32;
-            canvas.line(sx, sy, ex, ey);
+        for line in render_memory_chart(&memory, &max_memory, term_width) {
+            println!("{}", line);
         }
-        let rows = canvas.rows();
-        match &*rows {
-            [first, middle @ .., end] => {
-                println!(" {} {}", first, max_memory);
-                middle.iter().for_each(|r| println!(" {}", r));
-                println!(" {} {}", end, ByteSize(0));
+    }
+}
+
+// Renders bytes per second in binary units, as memory bandwidth usually is.
+fn render_throughput(bytes_per_sec: f64) -> String {
+    const UNITS: [&str; 4] = ["B/s", "KiB/s", "MiB/s", "GiB/s"];
+
+    let mut value = bytes_per_sec;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+// The chart is returned as lines, so the caller decides where it goes.
+fn render_memory_chart(memory: &MemoryData, max_memory: &str, term_width: u16) -> Vec<String> {
+    const CHART_HEIGHT: f32 = 10.0 * 4.0;
+    let chart_width = (term_width as u32)
+        .saturating_sub(max_memory.len() as u32 + 3)
+        .saturating_mul(2);
+
+    // A zero scale would give us infinite or NaN coordinates, which just draws garbage.
+    if memory.max_memory == 0
+        || memory.end_ts <= 0.0
+        || memory.graph_points.len() < 2
+        || chart_width == 0
+    {
+        return vec!["    -- Memory profile too small to chart".to_owned()];
+    }
+
+    let x_per_pixel = memory.end_ts / chart_width as f32;
+    let y_per_pixel = memory.max_memory as f32 / CHART_HEIGHT;
+    let max_x = (chart_width - 1) as f32;
+    let max_y = CHART_HEIGHT - 1.0;
+
+    let rendered_max_ts = render_duration(memory.end_ts_duration, false);
+    let (_, ts_unit) = rendered_max_ts.rsplit_once(' ').unwrap();
+
+    let mut canvas = Canvas::new(chart_width, 10);
+    for &[(sx, sy), (ex, ey)] in ArrWindows::new(&memory.graph_points) {
+        let sx = (sx / x_per_pixel).clamp(0.0, max_x) as u32;
+        let sy = (CHART_HEIGHT - (sy / y_per_pixel)).clamp(0.0, max_y) as u32;
+        let ex = (ex / x_per_pixel).clamp(0.0, max_x) as u32;
+        let ey = (CHART_HEIGHT - (ey / y_per_pixel)).clamp(0.0, max_y) as u32;
+        canvas.line(sx, sy, ex, ey);
+    }
+    // The region boundaries are dotted, to tell them apart from the memory use.
+    for (_, region) in &memory.regions {
+        for ts in [region.start_ts, region.end_ts] {
+            let x = (ts / x_per_pixel).clamp(0.0, max_x) as u32;
+            for y in (0..CHART_HEIGHT as u32).step_by(3) {
+                canvas.set(x, y);
             }
-            _ => unreachable!(),
         }
-        println!(
-            " 0 {}{:>width$}",
-            ts_unit,
-            rendered_max_ts,
-            width = (term_width as usize) - 5 - max_memory.len() - 2
-        );
     }
+    let rows = canvas.rows();
+    let mut lines = Vec::new();
+    let mid_row = rows.len() / 2;
+    // Each row of the canvas is 4 pixels high.
+    let mid_bytes = memory.max_memory as f32 * (1.0 - (mid_row * 4) as f32 / CHART_HEIGHT);
+    for (i, row) in rows.iter().enumerate() {
+        if i == 0 {
+            lines.push(format!(" {} {}", row, max_memory));
+        } else if i == rows.len() - 1 {
+            lines.push(format!(" {} {}", row, ByteSize(0)));
+        } else if i == mid_row {
+            lines.push(format!(" {} {}", row, ByteSize(mid_bytes as u64)));
+        } else {
+            lines.push(format!(" {}", row));
+        }
+    }
+
+    let chart_chars = rows[0].chars().count();
+    lines.push(format!(" {}", render_x_axis_ticks(chart_chars)));
+    lines.push(format!(
+        " {}",
+        render_x_axis_labels(
+            chart_chars,
+            &format!("0 {}", ts_unit.trim()),
+            render_duration(memory.end_ts_duration / 2, false).trim(),
+            rendered_max_ts.trim(),
+        )
+    ));
+    if memory.overflowed {
+        lines.push(format!(
+            " {}",
+            OUTPUT
+                .style()
+                .paint("(truncated: the trace buffer filled up)", Color::Yellow)
+        ));
+    }
+
+    lines
+}
+
+fn render_x_axis_ticks(width: usize) -> String {
+    let mut ticks = vec![' '; width];
+    for quarter in 0..=4 {
+        let pos = (width * quarter / 4).min(width.saturating_sub(1));
+        if let Some(tick) = ticks.get_mut(pos) {
+            *tick = '|';
+        }
+    }
+    ticks.into_iter().collect()
 }
 
-fn ui_update_worker(funcs: &mut Vec<BenchedFunction>, bench_events: Receiver<BenchEvent>) {
-    let progress_bar = ProgressBar::new(funcs.len() as u64);
+// Places the labels at the start, middle, and end of the axis, dropping the middle
+// one if there isn't room for it.
+fn render_x_axis_labels(width: usize, start: &str, mid: &str, end: &str) -> String {
+    let mut line = start.to_owned();
+    let mid_start = (width / 2).saturating_sub(mid.chars().count() / 2);
+    let end_start = width.saturating_sub(end.chars().count());
+
+    if mid_start > line.chars().count() && mid_start + mid.chars().count() < end_start {
+        line.push_str(&" ".repeat(mid_start - line.chars().count()));
+        line.push_str(mid);
+    }
+
+    let padding = end_start.saturating_sub(line.chars().count()).max(1);
+    line.push_str(&" ".repeat(padding));
+    line.push_str(end);
+    line
+}
+
+fn ui_update_worker(funcs: &mut [BenchedFunction], bench_events: Receiver<BenchEvent>) {
+    let progress_bar =
+        ProgressBar::with_draw_target(Some(funcs.len() as u64), OUTPUT.draw_target());
+    let template = OUTPUT.style().template(
+        " [{elapsed_precise}] [{wide_bar:.cyan/blue}] {percent}% ({eta})",
+        " [{elapsed_precise}] [{wide_bar}] {percent}% ({eta})",
+    );
     progress_bar.set_style(
         ProgressStyle::default_bar()
-            .template(" [{elapsed_precise}] [{wide_bar:.cyan/blue}] {percent}% ({eta})")
+            .template(template)
             .unwrap()
             .progress_chars("#>-"),
     );
 
     bench_events.iter().for_each(|event| match event {
-        BenchEvent::Answer { answer, id, is_alt } => {
+        BenchEvent::Answer {
+            answer,
+            id,
+            is_alt,
+            input,
+        } => {
             funcs[id].message = answer;
             funcs[id].is_multiline_answer = is_alt;
+            funcs[id].input = input;
         }
         BenchEvent::Memory { data, id } => {
             funcs[id].memory_data = Some(data);
@@ -130,6 +382,36 @@ fn ui_update_worker(funcs: &mut Vec<BenchedFunction>, bench_events: Receiver<Ben
             funcs[id].is_error = true;
             progress_bar.inc(1);
         }
+        BenchEvent::InputError { err, id } => {
+            funcs[id].message = err;
+            funcs[id].is_error = true;
+            funcs[id].is_missing_input = true;
+            progress_bar.inc(1);
+        }
+        BenchEvent::Warning { warning, id } => {
+            funcs[id].warning = Some(warning);
+        }
+        BenchEvent::LateError { err, id } => {
+            funcs[id].late_error = Some(err);
+            funcs[id].is_error = true;
+        }
+        BenchEvent::Metrics { metrics, id } => {
+            funcs[id].metrics = Some(metrics);
+        }
+        BenchEvent::StackUsage { bytes, id } => {
+            funcs[id].stack_usage = Some(bytes);
+        }
+        BenchEvent::Output { output, id } => {
+            funcs[id].captured_output = Some(output);
+        }
+        BenchEvent::Foreign { id } => {
+            funcs[id].is_foreign = true;
+        }
+        BenchEvent::NotBenched { id } => {
+            funcs[id].is_not_benched = true;
+        }
+        // The detailed bench never runs in run mode.
+        BenchEvent::AnswerTime { .. } => {}
         BenchEvent::Finish { .. } => {
             progress_bar.inc(1);
         }
@@ -143,6 +425,8 @@ fn bench_days(
     pool: &ThreadPool,
     mut funcs: Vec<BenchedFunction>,
     term_width: u16,
+    results: &mut Vec<FunctionSummary>,
+    measurements: &mut Vec<Measurement>,
 ) -> Result<Duration, BenchError> {
     let (sender, receiver) = crossbeam_channel::unbounded();
 
@@ -159,11 +443,19 @@ fn bench_days(
             id,
             chan: sender.clone(),
             run_only: false,
-            bench_time: ARGS.bench_time,
+            sample_mode: func.sample_mode,
+            answer_history: ARGS.answer_history(),
+            measure_memory: true,
+            measure_single: false,
+            input: None,
+            expected: func.expected,
+            samples_path: func.samples_path.clone(),
+            input_bytes: None,
         };
         let day = func.day;
+        let variant = func.variant.clone();
         let f = func.function;
-        pool.spawn(move || bench_worker(day, bench, f));
+        pool.spawn(move || bench_worker(day, variant, bench, f));
     }
 
     // If we don't drop this thread's sender the handler thread will never stop.
@@ -178,6 +470,36 @@ fn bench_days(
     let mut day_id = 99;
 
     for func in funcs {
+        // Only the day's own input is comparable with the saved measurements.
+        let timing = func
+            .timing_data
+            .as_ref()
+            .filter(|_| !func.is_error && func.variant.is_none());
+        if let Some(timing) = timing {
+            measurements.push(Measurement {
+                day: func.day,
+                part: func.part,
+                name: func.day_function_id.clone(),
+                mean: timing.mean,
+                std_dev: timing.std_dev,
+                max_memory: func.memory_data.as_ref().map(|m| m.max_memory),
+                num_allocs: func.memory_data.as_ref().map(|m| m.num_allocs),
+                input: func.input.clone(),
+            });
+        }
+
+        results.push(FunctionSummary {
+            day: func.day,
+            name: func.name,
+            part: func.part,
+            label: format!("{} {}", day_label(func.day), func.day_function_id),
+            mean: func.timing_data.as_ref().map(|t| t.mean),
+            max_sample: func.timing_data.as_ref().map(|t| t.max_sample),
+            is_error: func.is_error || func.late_error.is_some(),
+            is_missing_input: func.is_missing_input,
+            input: func.input.clone(),
+        });
+
         if func.day != day_id {
             day_id = func.day;
             println!("{:#<width$}", "", width = term_width as usize);
@@ -198,67 +520,171 @@ fn bench_days(
 }
 
 pub fn run_detailed_bench(alloc: &'static TracingAlloc, days: &[&Day]) -> BenchResult {
-    // We should limit the number of threads in the pool. Having too many
-    // results in them basically fighting for priority with the two update threads
-    // negatively effecting the benchmark.
-    let num_threads = ARGS
-        .num_threads
-        .unwrap_or_else(|| num_cpus::get_physical().saturating_sub(2))
-        .max(1);
-
-    let pool = ThreadPoolBuilder::new()
-        .num_threads(num_threads)
-        .build()
-        .expect("Failed to build threadpool");
-
     // We'll be rendering a graph the size of the terminal, so we need the width.
-    let stdout = Term::stdout();
-    let (_, term_width) = stdout.size();
+    let (_, term_width) = OUTPUT.term_size();
 
-    let mut benched_functions = Vec::new();
-    for day in days {
-        benched_functions.push(BenchedFunction {
+    let functions = selected_functions(days);
+    let parallelism = parallelism(functions.len());
+    let (functions, is_partial) = apply_wall_budget(functions, parallelism);
+    let benched_functions: Vec<_> = functions
+        .into_iter()
+        .map(|(day, f)| BenchedFunction {
             name: day.name,
             day: day.day,
-            day_function_id: "Part 1".to_owned(),
-            function: day.part_1,
+            part: f.part,
+            day_function_id: f.title(),
+            variant: f.variant.clone(),
+            function: f.function,
+            expected: f.expected,
+            samples_path: f.samples_path(day.day),
+            sample_mode: ARGS.day_sample_mode(day),
             message: String::new(),
             is_multiline_answer: false,
             is_error: false,
+            is_missing_input: false,
             timing_data: None,
             memory_data: None,
-        });
-        if let Some(p2) = day.part_2 {
-            benched_functions.push(BenchedFunction {
-                name: day.name,
-                day: day.day,
-                day_function_id: "Part 2".to_owned(),
-                function: p2,
-                message: String::new(),
-                is_multiline_answer: false,
-                is_error: false,
-                timing_data: None,
-                memory_data: None,
-            });
+            metrics: None,
+            late_error: None,
+            stack_usage: None,
+            is_foreign: false,
+            is_not_benched: false,
+            warning: None,
+            captured_output: None,
+            input: None,
+        })
+        .collect();
+    print_parallelism(parallelism, benched_functions.len());
+    let pool = build_worker_pool(parallelism);
+
+    let mut results = Vec::new();
+    let mut measurements = Vec::new();
+    let total_time = bench_days(
+        alloc,
+        &pool,
+        benched_functions,
+        term_width,
+        &mut results,
+        &mut measurements,
+    )?;
+    let label = if is_partial {
+        "Partial Time"
+    } else {
+        "Total Time"
+    };
+    println!("{}: {}", label, render_duration(total_time, false));
+    write_badge(total_time)?;
+    report_speedups(
+        RUNNING_YEAR.load(Ordering::Relaxed),
+        &measurements,
+        ARGS.speedup_file.as_deref(),
+    )?;
+    update_records(
+        RUNNING_YEAR.load(Ordering::Relaxed),
+        &measurements,
+        ARGS.records_file.as_deref(),
+    )?;
+    if let Some(name) = &ARGS.save_baseline {
+        save_baseline(name, RUNNING_YEAR.load(Ordering::Relaxed), &measurements)?;
+    }
+    print_run_notes(&results);
+    print_pinning();
+
+    if ARGS.summary_line {
+        print_summary_lines(&results);
+    }
+
+    outcome_result(&results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOO_SMALL: &str = "    -- Memory profile too small to chart";
+
+    fn memory(max_memory: usize, end_ts: f32, graph_points: Vec<(f32, f32)>) -> MemoryData {
+        MemoryData {
+            end_ts,
+            end_ts_duration: Duration::from_secs_f32(end_ts / 1000.0),
+            graph_points,
+            max_memory,
+            num_allocs: 1,
+            ..MemoryData::default()
         }
+    }
 
-        for &(name, extra) in day.other {
-            benched_functions.push(BenchedFunction {
-                name: day.name,
-                day: day.day,
-                day_function_id: name.to_owned(),
-                function: extra,
-                message: String::new(),
-                is_multiline_answer: false,
-                is_error: false,
-                timing_data: None,
-                memory_data: None,
-            });
+    #[test]
+    fn chart_of_nothing() {
+        let cases = [
+            // No peak, though something was allocated.
+            memory(0, 10.0, vec![(0.0, 0.0), (10.0, 0.0)]),
+            // Everything happened at once.
+            memory(1024, 0.0, vec![(0.0, 0.0), (0.0, 1024.0)]),
+            // Only a single point, so no line to draw.
+            memory(1024, 10.0, vec![(5.0, 1024.0)]),
+            memory(1024, 10.0, Vec::new()),
+        ];
+        for memory in cases {
+            assert_eq!(render_memory_chart(&memory, "1.0 KiB", 80), [TOO_SMALL]);
+        }
+
+        // No room left for the chart beside the label.
+        let memory = memory(1024, 10.0, vec![(0.0, 0.0), (10.0, 1024.0)]);
+        assert_eq!(render_memory_chart(&memory, "1.0 KiB", 5), [TOO_SMALL]);
+    }
+
+    #[test]
+    fn chart_fits_the_terminal() {
+        // Points past the end and above the peak are clamped to the edges of the chart.
+        let memory = memory(
+            1024,
+            10.0,
+            vec![
+                (0.0, 0.0),
+                (5.0, 1024.0),
+                (10.0, 512.0),
+                (20.0, 4096.0),
+                (-1.0, -5.0),
+            ],
+        );
+
+        for term_width in [30, 40, 80, 200] {
+            let lines = render_memory_chart(&memory, "1.0 KiB", term_width);
+            // The canvas' rows, then the ticks and labels.
+            assert_eq!(lines.len(), 12, "{:#?}", lines);
+            assert!(lines[0].ends_with(" 1.0 KiB"), "{:#?}", lines);
+            assert!(lines[9].ends_with(" 0 B"), "{:#?}", lines);
+            for line in &lines {
+                assert!(
+                    line.chars().count() <= term_width as usize,
+                    "{:?} is wider than {}",
+                    line,
+                    term_width
+                );
+            }
         }
     }
 
-    let total_time = bench_days(alloc, &pool, benched_functions, term_width)?;
-    println!("Total Time: {}", render_duration(total_time, false));
+    #[test]
+    fn axis_labels() {
+        assert_eq!(render_x_axis_ticks(9), "| | | | |");
+        assert_eq!(render_x_axis_ticks(17), "|   |   |   |   |");
+        assert_eq!(render_x_axis_ticks(1), "|");
+        assert_eq!(render_x_axis_ticks(0), "");
 
-    Ok(())
+        assert_eq!(
+            render_x_axis_labels(20, "0 ms", "5 ms", "10 ms"),
+            "0 ms    5 ms   10 ms"
+        );
+        // The middle label is dropped when there isn't room.
+        assert_eq!(
+            render_x_axis_labels(12, "0 ms", "5 ms", "10 ms"),
+            "0 ms   10 ms"
+        );
+        assert_eq!(
+            render_x_axis_labels(4, "0 ms", "5 ms", "10 ms"),
+            "0 ms 10 ms"
+        );
+    }
 }
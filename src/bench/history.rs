@@ -0,0 +1,291 @@
+use std::fmt::Display;
+
+// Bounds the number of runs we store, in case the answer oscillates every iteration.
+const MAX_RUNS: usize = 1000;
+const MAX_DISPLAYED_RUNS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AnswerPattern {
+    Consistent,
+    /// The answer changed once, and never changed back.
+    SingleChange,
+    /// The answer changed for a single run, then returned to the original.
+    SingleFlip,
+    /// The answer is numeric, and moved in one direction every time it changed.
+    MonotonicDrift,
+    /// The answer changed between a small set of values.
+    Oscillation,
+    Erratic,
+}
+
+impl Display for AnswerPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            AnswerPattern::Consistent => "consistent",
+            AnswerPattern::SingleChange => "single change",
+            AnswerPattern::SingleFlip => "single flip",
+            AnswerPattern::MonotonicDrift => "monotonic drift",
+            AnswerPattern::Oscillation => "oscillation",
+            AnswerPattern::Erratic => "erratic",
+        };
+
+        f.write_str(output)
+    }
+}
+
+struct AnswerRun {
+    // Index into `values`, or None if the value wasn't stored due to the cap.
+    value: Option<usize>,
+    first_iteration: usize,
+    count: usize,
+}
+
+/// Run-length compressed record of the answers returned by each timing iteration.
+pub(crate) struct AnswerHistory {
+    max_distinct: usize,
+    values: Vec<String>,
+    runs: Vec<AnswerRun>,
+    iterations: usize,
+    dropped_values: bool,
+    truncated_iterations: usize,
+}
+
+impl AnswerHistory {
+    pub(crate) fn new(max_distinct: usize) -> Self {
+        Self {
+            max_distinct: max_distinct.max(1),
+            values: Vec::new(),
+            runs: Vec::new(),
+            iterations: 0,
+            dropped_values: false,
+            truncated_iterations: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, answer: impl Display) {
+        let answer = answer.to_string();
+        self.iterations += 1;
+
+        let value = match self.values.iter().position(|v| *v == answer) {
+            Some(idx) => Some(idx),
+            None if self.values.len() < self.max_distinct => {
+                self.values.push(answer);
+                Some(self.values.len() - 1)
+            }
+            None => {
+                self.dropped_values = true;
+                None
+            }
+        };
+
+        let num_runs = self.runs.len();
+        match self.runs.last_mut() {
+            Some(run) if run.value == value && self.truncated_iterations == 0 => run.count += 1,
+            _ if num_runs == MAX_RUNS => self.truncated_iterations += 1,
+            _ => self.runs.push(AnswerRun {
+                value,
+                first_iteration: self.iterations,
+                count: 1,
+            }),
+        }
+    }
+
+    pub(crate) fn is_consistent(&self) -> bool {
+        self.runs.len() <= 1
+    }
+
+    pub(crate) fn pattern(&self) -> AnswerPattern {
+        let num_runs = self.runs.len() + (self.truncated_iterations > 0) as usize;
+        match num_runs {
+            0 | 1 => return AnswerPattern::Consistent,
+            2 => return AnswerPattern::SingleChange,
+            3 if self.runs[0].value == self.runs[2].value && self.runs[1].count == 1 => {
+                return AnswerPattern::SingleFlip
+            }
+            _ => {}
+        }
+
+        let numeric: Option<Vec<f64>> = self
+            .runs
+            .iter()
+            .map(|r| r.value.and_then(|v| self.values[v].trim().parse().ok()))
+            .collect();
+
+        if let Some(numeric) = numeric {
+            let increasing = numeric.windows(2).all(|w| w[0] < w[1]);
+            let decreasing = numeric.windows(2).all(|w| w[0] > w[1]);
+            if increasing || decreasing {
+                return AnswerPattern::MonotonicDrift;
+            }
+        }
+
+        if !self.dropped_values && self.values.len() <= 3 {
+            AnswerPattern::Oscillation
+        } else {
+            AnswerPattern::Erratic
+        }
+    }
+
+    /// Returns lines like "iterations 1–340: 1234".
+    pub(crate) fn summary_lines(&self, censor: bool) -> Vec<String> {
+        let mut lines: Vec<_> = self
+            .runs
+            .iter()
+            .take(MAX_DISPLAYED_RUNS)
+            .map(|run| {
+                let last = run.first_iteration + run.count - 1;
+                let range = if run.count == 1 {
+                    format!("iteration {}", run.first_iteration)
+                } else {
+                    format!("iterations {}–{}", run.first_iteration, last)
+                };
+
+                let value = match run.value {
+                    _ if censor => "**CENSORED**",
+                    Some(v) => &self.values[v],
+                    None => "<other>",
+                };
+
+                format!("{}: {}", range, value)
+            })
+            .collect();
+
+        if self.runs.len() > MAX_DISPLAYED_RUNS {
+            lines.push(format!(
+                "... {} further runs",
+                self.runs.len() - MAX_DISPLAYED_RUNS
+            ));
+        }
+
+        if self.truncated_iterations > 0 {
+            lines.push(format!(
+                "... {} further iterations not recorded",
+                self.truncated_iterations
+            ));
+        }
+
+        if self.dropped_values {
+            lines.push(format!(
+                "more than {} distinct answers, extra values shown as <other>",
+                self.max_distinct
+            ));
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use super::*;
+
+    fn history_of<T: Display>(
+        answers: impl IntoIterator<Item = T>,
+        max_distinct: usize,
+    ) -> AnswerHistory {
+        let mut history = AnswerHistory::new(max_distinct);
+        for answer in answers {
+            history.record(answer);
+        }
+        history
+    }
+
+    fn pattern_of(answers: &[u32]) -> AnswerPattern {
+        history_of(answers, 10).pattern()
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(pattern_of(&[]), AnswerPattern::Consistent);
+        assert_eq!(pattern_of(&[7; 50]), AnswerPattern::Consistent);
+        assert_eq!(pattern_of(&[7, 7, 7, 8, 8]), AnswerPattern::SingleChange);
+        assert_eq!(pattern_of(&[7, 7, 8, 7, 7]), AnswerPattern::SingleFlip);
+        // Changing back after more than one run isn't a flip.
+        assert_eq!(pattern_of(&[7, 8, 8, 7]), AnswerPattern::Oscillation);
+        assert_eq!(
+            pattern_of(&[1, 1, 2, 3, 3, 4]),
+            AnswerPattern::MonotonicDrift
+        );
+        assert_eq!(pattern_of(&[9, 5, 5, 2]), AnswerPattern::MonotonicDrift);
+        assert_eq!(pattern_of(&[1, 2, 1, 2, 1, 2]), AnswerPattern::Oscillation);
+        assert_eq!(pattern_of(&[1, 5, 2, 9, 3]), AnswerPattern::Erratic);
+    }
+
+    #[test]
+    fn non_numeric_answers() {
+        let history = history_of(["a", "b", "c", "a"], 10);
+        assert_eq!(history.pattern(), AnswerPattern::Oscillation);
+
+        let history = history_of(["a", "b", "c", "d", "e"], 10);
+        assert_eq!(history.pattern(), AnswerPattern::Erratic);
+    }
+
+    #[test]
+    fn run_length_summary() {
+        let answers = iter::repeat_n(1234, 340)
+            .chain([1198])
+            .chain(iter::repeat_n(1234, 1658));
+        let history = history_of(answers, 10);
+
+        assert!(!history.is_consistent());
+        assert_eq!(history.pattern(), AnswerPattern::SingleFlip);
+        assert_eq!(
+            history.summary_lines(false),
+            [
+                "iterations 1–340: 1234",
+                "iteration 341: 1198",
+                "iterations 342–1999: 1234"
+            ]
+        );
+        assert_eq!(
+            history.summary_lines(true),
+            [
+                "iterations 1–340: **CENSORED**",
+                "iteration 341: **CENSORED**",
+                "iterations 342–1999: **CENSORED**"
+            ]
+        );
+    }
+
+    #[test]
+    fn distinct_values_capped() {
+        let history = history_of([1, 2, 3, 4, 5, 1], 2);
+
+        // Past the cap, the values are lumped together, but still mark where they changed.
+        assert_eq!(history.values.len(), 2);
+        assert_eq!(history.pattern(), AnswerPattern::Erratic);
+        assert_eq!(
+            history.summary_lines(false),
+            [
+                "iteration 1: 1",
+                "iteration 2: 2",
+                "iterations 3–5: <other>",
+                "iteration 6: 1",
+                "more than 2 distinct answers, extra values shown as <other>"
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_capped() {
+        let answers = (0..MAX_RUNS + 500).map(|i| i % 2);
+        let history = history_of(answers, 10);
+
+        assert_eq!(history.runs.len(), MAX_RUNS);
+        assert_eq!(history.iterations, MAX_RUNS + 500);
+        assert_eq!(history.pattern(), AnswerPattern::Oscillation);
+
+        let lines = history.summary_lines(false);
+        assert_eq!(lines.len(), MAX_DISPLAYED_RUNS + 2);
+        assert_eq!(
+            lines[MAX_DISPLAYED_RUNS],
+            format!("... {} further runs", MAX_RUNS - MAX_DISPLAYED_RUNS)
+        );
+        assert_eq!(
+            lines[MAX_DISPLAYED_RUNS + 1],
+            "... 500 further iterations not recorded"
+        );
+    }
+}
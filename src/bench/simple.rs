@@ -1,36 +1,87 @@
 use std::{
-    iter,
+    fmt::Write,
     panic::{self},
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Arc},
     thread,
     time::Duration,
 };
 
 use bytesize::ByteSize;
-use console::{style, Term};
-use crossbeam_channel::{Receiver, Sender};
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use rayon::{ThreadPool, ThreadPoolBuilder};
+use console::{measure_text_width, pad_str, truncate_str, Alignment, Color};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rayon::ThreadPool;
 
 use crate::{
+    alloc,
+    baseline::{render_delta, save_baseline, Baseline, BaselineEntry},
     bench::{
-        bench_worker, AlternateAnswer, Bench, BenchEvent, MemoryData, RuntimeData, SetupFunction,
+        bench_worker, AlternateAnswer, Bench, BenchEvent, FunctionSummary, MemoryData, RuntimeData,
+        SampleMode, SetupFunction,
     },
-    print_alt_answers, print_footer, print_header, render_decimal, render_duration, BenchError,
-    BenchResult, Day, TracingAlloc, ARGS, TABLE_DETAILED_COLS_WIDTH, TABLE_PRE_COL_WIDTH,
+    blank_pre_col,
+    clock::Timestamp,
+    collect::DayResult,
+    day_col_rule, day_col_width, day_label, fit_day_col, function_row_label, is_background_bench,
+    markdown::{write_markdown, MarkdownRow},
+    message_col_width, outcome_result,
+    output::{write_atomic, OUTPUT},
+    pad_day_col,
+    plan::{
+        apply_wall_budget, build_worker_pool, is_sequential_pinned, parallelism, print_parallelism,
+        selected_functions,
+    },
+    print_alt_answers, print_footer, print_header, print_run_notes, print_summary_lines,
+    provenance::InputProvenance,
+    records::update_records,
+    render_answer_time, render_day_title, render_decimal, render_duration, render_header,
+    render_row_label,
+    speedup::Measurement,
+    table_pre_col_width, write_badge, BenchError, BenchResult, Day, RowStatus, TracingAlloc, ARGS,
+    RUNNING_YEAR, SPARKLINE_WIDTH, TABLE_RUN_TIME_COL_WIDTH,
 };
 
+// How often the table checks whether the terminal was resized.
+const RESIZE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Stands in for line breaks in the table, the full answer being printed after it.
+pub(crate) const LINE_BREAK_MARKER: &str = "⏎";
+
 struct BenchedFunction {
     day: u8,
-    day_function_id: u8,
+    name: &'static str,
+    /// Which part of the puzzle this function solves, or `None` for other functions.
+    part: Option<u8>,
+    /// The part number, or the other function's name, followed by the input variant.
+    day_function_id: String,
+    /// The input variant it's given, if it isn't the day's own input.
+    variant: Option<String>,
     function: SetupFunction,
+    expected: Option<&'static str>,
+    samples_path: Option<PathBuf>,
+    sample_mode: SampleMode,
     message: String,
     is_error: bool,
+    is_missing_input: bool,
     timing_data: Option<RuntimeData>,
+    /// The single call that produced the answer, only timed in run mode.
+    answer_time: Option<Duration>,
+    /// Only run for its answer, through [`Bench::run_once`].
+    is_not_benched: bool,
+    /// Why the measurements may not be trustworthy.
+    warning: Option<String>,
     memory_data: Option<MemoryData>,
+    /// The function's entry in the `--baseline`, if it's in it.
+    baseline: Option<BaselineEntry>,
+    captured_output: Option<String>,
+    input: Option<Arc<InputProvenance>>,
+    /// Whether its results are all in, so its row can be printed.
+    is_finished: bool,
     finished_spinner: ProgressStyle,
+    warning_spinner: ProgressStyle,
     error_spinner: ProgressStyle,
     bar: Option<ProgressBar>,
-    term_width: usize,
 }
 
 impl BenchedFunction {
@@ -38,24 +89,52 @@ impl BenchedFunction {
         self.message = ans;
         if let Some(bar) = &self.bar {
             bar.set_style(self.finished_spinner.clone());
-            let msg = self.render();
-            bar.set_message(msg);
+            bar.set_message(self.render(OUTPUT.term_width()));
         }
     }
 
     fn memory(&mut self, data: MemoryData) {
         self.memory_data = Some(data);
         if let Some(bar) = &self.bar {
-            let msg = self.render();
-            bar.set_message(msg);
+            bar.set_message(self.render(OUTPUT.term_width()));
         }
     }
 
     fn timing(&mut self, data: RuntimeData) {
         self.timing_data = Some(data);
         if let Some(bar) = &self.bar {
-            let msg = self.render();
-            bar.set_message(msg);
+            bar.set_message(self.render(OUTPUT.term_width()));
+        }
+    }
+
+    fn not_benched(&mut self) {
+        self.is_not_benched = true;
+        if let Some(bar) = &self.bar {
+            bar.set_message(self.render(OUTPUT.term_width()));
+        }
+    }
+
+    fn answer_time(&mut self, elapsed: Duration) {
+        self.answer_time = Some(elapsed);
+        if let Some(bar) = &self.bar {
+            bar.set_message(self.render(OUTPUT.term_width()));
+        }
+    }
+
+    fn warning(&mut self, warning: String) {
+        self.warning = Some(warning);
+        if let Some(bar) = &self.bar {
+            bar.set_style(self.warning_spinner.clone());
+        }
+    }
+
+    fn status(&self) -> RowStatus {
+        if self.is_error {
+            RowStatus::Error
+        } else if self.warning.is_some() {
+            RowStatus::Warning
+        } else {
+            RowStatus::Ok
         }
     }
 
@@ -64,54 +143,85 @@ impl BenchedFunction {
         self.is_error = true;
         if let Some(bar) = &self.bar {
             bar.set_style(self.error_spinner.clone());
-            let msg = self.render();
-            bar.set_message(msg);
+            bar.set_message(self.render(OUTPUT.term_width()));
         }
     }
 
     fn finish(&mut self) {
+        self.is_finished = true;
         if let Some(bar) = &self.bar {
             bar.finish()
         }
     }
 
-    fn render(&self) -> String {
-        if self.is_error {
-            // Keep the error within the width of the terminal.
-            self.message
-                .char_indices()
-                .nth(self.term_width - TABLE_PRE_COL_WIDTH)
-                .map(|(i, _)| &self.message[..i])
-                .unwrap_or(&self.message)
-                .to_owned()
-        } else if ARGS.run_type.is_run_only() {
-            if ARGS.censor {
-                "**CENSORED**".to_owned()
-            } else {
-                // Keep the error within the width of the terminal.
-                self.message
-                    .char_indices()
-                    .nth(self.term_width - TABLE_PRE_COL_WIDTH)
-                    .map(|(i, _)| &self.message[..i])
-                    .unwrap_or(&self.message)
-                    .to_owned()
-            }
+    // How many characters of the message fit in its column.
+    fn message_width(&self, term_width: usize) -> usize {
+        if ARGS.run_type().is_run_only() {
+            term_width.saturating_sub(table_pre_col_width() + TABLE_RUN_TIME_COL_WIDTH)
+        } else if self.is_error {
+            // Keep the message within the width of the terminal.
+            term_width.saturating_sub(table_pre_col_width())
         } else {
-            let msg_max_width = self
-                .term_width
-                .saturating_sub(TABLE_DETAILED_COLS_WIDTH)
-                .clamp(12, 30);
+            message_col_width(term_width)
+        }
+    }
 
-            let msg = if ARGS.censor {
-                "**CENSORED**"
-            } else {
-                self.message
-                    .char_indices()
-                    .nth(msg_max_width)
-                    .map(|(i, _)| &self.message[..i])
-                    .unwrap_or(&self.message)
-            };
+    // Whether the answer was cut short in the table, and needs printing in full after it.
+    fn is_truncated_answer(&self, term_width: usize) -> bool {
+        let message = self.message.trim_end();
+        !self.is_error
+            && !ARGS.censor
+            && (message.contains('\n')
+                || measure_text_width(message) > self.message_width(term_width))
+    }
 
+    // The finished row, with its warnings, as printed in the table.
+    fn render_final(&self, term_width: usize) -> Vec<String> {
+        let day = function_row_label(self.day, &self.day_function_id);
+        let mut lines = vec![format!(
+            "{} | {}",
+            render_row_label(&day, self.status()),
+            self.render(term_width)
+        )];
+
+        let truncation = self
+            .memory_data
+            .as_ref()
+            .and_then(|m| m.truncation_warning());
+        for warning in self
+            .warning
+            .iter()
+            .map(String::as_str)
+            .chain(truncation.as_deref())
+        {
+            lines.push(format!(
+                "{} {}",
+                blank_pre_col(),
+                OUTPUT.style().paint(warning, Color::Yellow)
+            ));
+        }
+        lines
+    }
+
+    fn render(&self, term_width: usize) -> String {
+        let msg_max_width = self.message_width(term_width);
+        let msg = if ARGS.censor && !self.is_error {
+            "**CENSORED**".to_owned()
+        } else {
+            truncate_message(&self.message, msg_max_width)
+        };
+
+        if ARGS.run_type().is_run_only() {
+            format!("{} | {}", render_answer_time(self.answer_time), msg)
+        } else if self.is_error {
+            msg
+        } else if self.is_not_benched {
+            format!(
+                "{} | {:<22} |",
+                pad_str(&msg, msg_max_width, Alignment::Left, None),
+                "not benched",
+            )
+        } else {
             let (mean_time, std_dev) = self
                 .timing_data
                 .as_ref()
@@ -123,30 +233,100 @@ impl BenchedFunction {
                 })
                 .unwrap_or_default();
 
-            let (allocs, mem) = self
+            let (allocs, mem, total) = self
                 .memory_data
                 .as_ref()
                 .map(|md| {
                     (
                         render_decimal(md.num_allocs),
                         format!("{}", ByteSize(md.max_memory as u64)),
+                        format!("{}", ByteSize(md.total_allocated as u64)),
                     )
                 })
                 .unwrap_or_default();
 
-            format!(
-                "{:<msg_width$} | {:<8} (σ {:<8}) | {:<7} | {}",
-                msg,
+            let mut row = format!(
+                "{} | {:<9} (σ {:<8}) | {:<7} | ",
+                pad_str(&msg, msg_max_width, Alignment::Left, None),
                 mean_time,
                 std_dev,
                 allocs,
-                mem,
-                msg_width = msg_max_width
-            )
+            );
+            if ARGS.wide {
+                let worst = self
+                    .timing_data
+                    .as_ref()
+                    .map(|td| format!("{:.1}×", td.worst_factor()))
+                    .unwrap_or_default();
+                write!(row, "{:<8} | {:<11} | {:<6}", mem, total, worst).unwrap();
+            } else {
+                write!(row, "{:<8}", mem).unwrap();
+            }
+            if ARGS.sparkline {
+                let sparkline = self
+                    .memory_data
+                    .as_ref()
+                    .map(|md| render_sparkline(md, SPARKLINE_WIDTH))
+                    .unwrap_or_default();
+                write!(row, " | {}", sparkline).unwrap();
+            }
+            if ARGS.baseline.is_some() {
+                let delta = render_delta(
+                    self.baseline.as_ref(),
+                    self.timing_data.as_ref().map(|td| td.mean),
+                    self.memory_data.as_ref().map(|md| md.num_allocs),
+                );
+                write!(row, " | {}", delta).unwrap();
+            }
+            row.truncate(row.trim_end().len());
+            row
         }
     }
 }
 
+// Each column is the most memory in use during its slice of the run, so short-lived spikes
+// still show. Slices without events carry on the previous column's, as nothing changed.
+fn render_sparkline(memory: &MemoryData, width: usize) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    if memory.max_memory == 0 || memory.end_ts <= 0.0 || width == 0 {
+        return String::new();
+    }
+
+    let mut columns: Vec<Option<f32>> = vec![None; width];
+    for &(time, bytes) in &memory.graph_points {
+        let col = ((time / memory.end_ts) * width as f32) as usize;
+        let col = &mut columns[col.min(width - 1)];
+        *col = Some(col.map_or(bytes, |c| c.max(bytes)));
+    }
+
+    let mut prev = 0.0;
+    columns
+        .into_iter()
+        .map(|col| {
+            let bytes = col.unwrap_or(prev);
+            prev = bytes;
+            let level = (bytes / memory.max_memory as f32 * (LEVELS.len() - 1) as f32).round();
+            LEVELS[(level as usize).min(LEVELS.len() - 1)]
+        })
+        .collect()
+}
+
+// Cuts the message down to `width` terminal columns, ending with an ellipsis if anything was
+// removed. Wide characters take two columns, and line breaks are marked, as they'd break the
+// table.
+fn truncate_message(msg: &str, width: usize) -> String {
+    if width == 0 {
+        return String::new();
+    }
+
+    let msg = msg
+        .trim_end()
+        .replace("\r\n", LINE_BREAK_MARKER)
+        .replace('\n', LINE_BREAK_MARKER);
+    truncate_str(&msg, width, "…").into_owned()
+}
+
 fn tick_bars_worker(bars: Vec<ProgressBar>) {
     loop {
         let all_finished = bars.iter().fold(true, |all_finished, bar| {
@@ -164,54 +344,202 @@ fn tick_bars_worker(bars: Vec<ProgressBar>) {
     }
 }
 
+// Redraws the bars for the new width, and the header too if the columns moved.
+fn handle_resize(
+    funcs: &[BenchedFunction],
+    multi_bars: &MultiProgress,
+    old_width: usize,
+    new_width: usize,
+) {
+    for func in funcs {
+        if let Some(bar) = &func.bar {
+            bar.set_message(func.render(new_width));
+        }
+    }
+
+    if message_col_width(old_width) != message_col_width(new_width) {
+        // Only decoration, so a failure isn't worth stopping for.
+        let _ = multi_bars.println(render_header(new_width));
+    }
+}
+
+// Prints the lines above the progress bars. Suspending the bars panics if the terminal can't be
+// written, taking the results with the UI thread, so they're cleared first to check it's still
+// there. If it isn't, the bars are hidden for the rest of the run.
+fn print_above_bars(multi_bars: &MultiProgress, lines: &[String]) {
+    if let Err(e) = multi_bars.clear() {
+        OUTPUT.disable_broken_progress(e);
+        multi_bars.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    multi_bars.suspend(|| lines.iter().for_each(|line| println!("{}", line)));
+}
+
+// Prints the rows of the functions that have finished, in order, above the progress bars. A row
+// waits for those before it, so the table comes out as if it was printed at the end, but what
+// was already printed survives the run being interrupted.
+fn print_finished_rows(
+    funcs: &[BenchedFunction],
+    next_row: &mut usize,
+    prev_day: &mut Option<u8>,
+    multi_bars: &MultiProgress,
+    term_width: usize,
+    print_unfinished: bool,
+) {
+    while let Some(func) = funcs
+        .get(*next_row)
+        .filter(|f| f.is_finished || print_unfinished)
+    {
+        let mut lines = Vec::new();
+        if *prev_day != Some(func.day) {
+            lines.push(render_day_title(func.day, func.name, term_width));
+        }
+        lines.extend(func.render_final(term_width));
+
+        if let Some(bar) = &func.bar {
+            multi_bars.remove(bar);
+        }
+        print_above_bars(multi_bars, &lines);
+
+        *prev_day = Some(func.day);
+        *next_row += 1;
+    }
+}
+
 fn ui_update_worker(
     mut funcs: Vec<BenchedFunction>,
     receiver: Receiver<BenchEvent>,
+    multi_bars: MultiProgress,
     alt_answers: Sender<AlternateAnswer>,
     time_sender: Sender<Duration>,
+    mut prev_day: Option<u8>,
 ) -> Vec<BenchedFunction> {
-    for event in receiver.iter() {
+    let mut term_width = OUTPUT.term_width();
+    let mut next_row = 0;
+    loop {
+        // Wake up now and then even without events, to notice the terminal being resized.
+        let event = if is_sequential_pinned() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(RESIZE_POLL_INTERVAL)
+        };
+        let event = match event {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let new_width = OUTPUT.term_width();
+        if new_width != term_width {
+            handle_resize(&funcs, &multi_bars, term_width, new_width);
+            term_width = new_width;
+        }
+
+        let Some(event) = event else {
+            continue;
+        };
         match event {
             BenchEvent::Answer {
                 answer,
                 id,
                 is_alt: false,
-            } => funcs[id].answer(answer),
-            BenchEvent::Answer { answer, id, .. } => {
+                input,
+            } => {
+                funcs[id].input = input;
+                funcs[id].answer(answer);
+            }
+            BenchEvent::Answer {
+                answer, id, input, ..
+            } => {
                 let func = &mut funcs[id];
+                func.input = input;
                 alt_answers
-                    .send(AlternateAnswer {
+                    .send(AlternateAnswer::for_function(
                         answer,
-                        day: func.day,
-                        day_function_id: func.day_function_id,
-                    })
+                        func.day,
+                        func.name,
+                        func.part,
+                        &func.day_function_id,
+                    ))
                     .expect("Failed to send alternate answer from UI thread");
                 func.answer("Check alternate answers".to_owned());
             }
             BenchEvent::Memory { data, id } => funcs[id].memory(data),
+            BenchEvent::NotBenched { id } => funcs[id].not_benched(),
+            BenchEvent::AnswerTime { elapsed, id } => {
+                time_sender
+                    .send(elapsed)
+                    .expect("Failed to send timing from UI thread");
+                funcs[id].answer_time(elapsed);
+            }
             BenchEvent::Timing { data, id } => {
                 time_sender
                     .send(data.mean)
                     .expect("Failed to send timing from UI thread");
                 funcs[id].timing(data);
             }
-            BenchEvent::Error { err, id } => funcs[id].error(err),
-            BenchEvent::Finish { id } => funcs[id].finish(),
+            BenchEvent::Warning { warning, id } => funcs[id].warning(warning),
+            BenchEvent::Error { err, id } | BenchEvent::LateError { err, id } => {
+                funcs[id].error(err)
+            }
+            BenchEvent::InputError { err, id } => {
+                funcs[id].is_missing_input = true;
+                funcs[id].error(err);
+            }
+            BenchEvent::Output { output, id } => funcs[id].captured_output = Some(output),
+            BenchEvent::Metrics { .. }
+            | BenchEvent::StackUsage { .. }
+            | BenchEvent::Foreign { .. } => {}
+            BenchEvent::Finish { id } => {
+                funcs[id].finish();
+                print_finished_rows(
+                    &funcs,
+                    &mut next_row,
+                    &mut prev_day,
+                    &multi_bars,
+                    term_width,
+                    false,
+                );
+            }
         }
     }
 
+    // Every worker has stopped, so any function that didn't finish never will.
+    print_finished_rows(
+        &funcs,
+        &mut next_row,
+        &mut prev_day,
+        &multi_bars,
+        term_width,
+        true,
+    );
     funcs
 }
 
+/// What a chunk produced besides its rows.
+#[derive(Default)]
+struct ChunkOutput {
+    total_time: Duration,
+    truncated_answers: Vec<AlternateAnswer>,
+    captured_output: Vec<AlternateAnswer>,
+    measurements: Vec<Measurement>,
+    markdown_rows: Vec<MarkdownRow>,
+    collected: Vec<DayResult>,
+}
+
+#[allow(clippy::too_many_arguments)]
 fn bench_days_chunk(
     alloc: &'static TracingAlloc,
     mut funcs: Vec<BenchedFunction>,
     alt_answer_sender: Sender<AlternateAnswer>,
     spinner_style: &ProgressStyle,
     pool: &ThreadPool,
-) -> Result<Duration, BenchError> {
+    measure_memory: bool,
+    draw_target: ProgressDrawTarget,
+    results: &mut Vec<FunctionSummary>,
+) -> Result<ChunkOutput, BenchError> {
     let (sender, receiver) = crossbeam_channel::unbounded();
-    let multi_bars = MultiProgress::new();
+    let multi_bars = MultiProgress::with_draw_target(draw_target);
     multi_bars.set_move_cursor(true);
 
     let mut bars = Vec::new();
@@ -225,7 +553,10 @@ fn bench_days_chunk(
 
     for (id, func) in funcs.iter_mut().enumerate() {
         let bar = multi_bars.add(ProgressBar::new_spinner());
-        bar.set_prefix(format!("{:2}.{}", func.day, func.day_function_id));
+        bar.set_prefix(pad_day_col(&function_row_label(
+            func.day,
+            &func.day_function_id,
+        )));
         bar.set_style(spinner_style.clone());
 
         bars.push(bar.clone());
@@ -235,18 +566,33 @@ fn bench_days_chunk(
             alloc,
             id,
             chan: sender.clone(),
-            run_only: ARGS.run_type.is_run_only(),
-            bench_time: ARGS.bench_time,
+            run_only: ARGS.run_type().is_run_only(),
+            sample_mode: func.sample_mode,
+            answer_history: ARGS.answer_history(),
+            measure_memory,
+            measure_single: false,
+            input: None,
+            expected: func.expected,
+            samples_path: func.samples_path.clone(),
+            input_bytes: None,
         };
         let day = func.day;
+        let variant = func.variant.clone();
         let f = func.function;
 
-        pool.spawn(move || bench_worker(day, bench, f));
+        pool.spawn(move || bench_worker(day, variant, bench, f));
     }
 
     // Using the built-in steady tick spawns a thread for each bar. We could have up to 50.
-    // Seems wasteful. Let's just spawn a single thread to tick them all instead.
-    let tick_thread = thread::spawn(move || tick_bars_worker(bars));
+    // Seems wasteful. Let's just spawn a single thread to tick them all instead. Benching
+    // sequentially, the bars are only redrawn when a function's results arrive, to keep the
+    // machine quiet while it's timed.
+    let tick_thread = (!is_sequential_pinned()).then(|| {
+        thread::spawn(move || {
+            alloc::exclude_current_thread();
+            tick_bars_worker(bars)
+        })
+    });
 
     // If we don't drop this thread's sender the handler thread will never stop.
     drop(sender);
@@ -255,121 +601,667 @@ fn bench_days_chunk(
     // We don't want to spawn the handler thread in the worker pool, because the benchmarking will
     // hog the pool's threads, meaning the UI updates won't happen in a timely manner.
     // Rayon's scope function seems to end up in the pool, so we need to make sure we get a new thread.
-    let ui_update_thread =
-        thread::spawn(move || ui_update_worker(funcs, receiver, alt_answer_sender, time_sender));
+    let ui_multi_bars = multi_bars.clone();
+    // A day's functions can be split across chunks, so its title may already be printed.
+    let prev_day = results.last().map(|r| r.day);
+    let ui_update_thread = thread::spawn(move || {
+        alloc::exclude_current_thread();
+        ui_update_worker(
+            funcs,
+            receiver,
+            ui_multi_bars,
+            alt_answer_sender,
+            time_sender,
+            prev_day,
+        )
+    });
 
     let ui_thread_res = ui_update_thread.join();
-    let tick_res = tick_thread.join();
+    let tick_res = tick_thread.map_or(Ok(()), |thread| thread.join());
     let mb_clear_res = multi_bars.clear();
 
     panic::set_hook(old_panic_hook);
 
-    mb_clear_res.expect("Failed to join progress bars");
-    tick_res.expect("Failed to join tick thread");
-    let funcs = ui_thread_res.expect("Failed to join handler thread");
+    // The bars are only decoration, the UI worker already has the results, so losing them
+    // shouldn't lose the rest of the run.
+    if let Err(e) = mb_clear_res {
+        OUTPUT.disable_broken_progress(e);
+    }
+    if tick_res.is_err() {
+        OUTPUT.disable_broken_progress("the tick thread panicked");
+    }
+    // A broken terminal only hides the bars, so the handler panicking means the results
+    // themselves couldn't be handled, which leaves nothing to report.
+    let funcs = ui_thread_res.map_err(|_| BenchError::ResultsLost)?;
 
-    for func in funcs {
-        let day = format!("{:>2}.{}", func.day, func.day_function_id);
-        let day = if func.is_error {
-            style(day).red()
-        } else {
-            style(day).green()
-        };
-        println!("  {} | {}", day, func.render());
+    let mut output = ChunkOutput::default();
+    let term_width = OUTPUT.term_width();
+    for mut func in funcs {
+        // Only the day's own input is comparable with the saved measurements.
+        let timing = func
+            .timing_data
+            .as_ref()
+            .filter(|_| !func.is_error && func.variant.is_none());
+        if let Some(timing) = timing {
+            output.measurements.push(Measurement {
+                day: func.day,
+                part: func.part,
+                // The parts are saved without a name.
+                name: match func.part {
+                    Some(_) => String::new(),
+                    None => func.day_function_id.clone(),
+                },
+                mean: timing.mean,
+                std_dev: timing.std_dev,
+                max_memory: func.memory_data.as_ref().map(|m| m.max_memory),
+                num_allocs: func.memory_data.as_ref().map(|m| m.num_allocs),
+                input: func.input.clone(),
+            });
+        }
+
+        if ARGS.markdown.is_some() {
+            output.markdown_rows.push(MarkdownRow {
+                day: day_label(func.day),
+                function: func.day_function_id.clone(),
+                message: if ARGS.censor && !func.is_error {
+                    "**CENSORED**".to_owned()
+                } else {
+                    func.message.clone()
+                },
+                is_error: func.is_error,
+                mean: func.timing_data.as_ref().map(|t| t.mean),
+                std_dev: func.timing_data.as_ref().map(|t| t.std_dev),
+                num_allocs: func.memory_data.as_ref().map(|m| m.num_allocs),
+                max_memory: func.memory_data.as_ref().map(|m| m.max_memory),
+                input: func.input.clone(),
+            });
+        }
+
+        results.push(FunctionSummary {
+            day: func.day,
+            name: func.name,
+            part: func.part,
+            label: format!("{}.{}", day_label(func.day), func.day_function_id),
+            mean: func.timing_data.as_ref().map(|t| t.mean),
+            max_sample: func.timing_data.as_ref().map(|t| t.max_sample),
+            is_error: func.is_error,
+            is_missing_input: func.is_missing_input,
+            input: func.input.clone(),
+        });
+
+        if let Some(captured) = func.captured_output.take() {
+            output.captured_output.push(AlternateAnswer::for_function(
+                captured,
+                func.day,
+                func.name,
+                func.part,
+                &func.day_function_id,
+            ));
+        }
+
+        if measure_memory {
+            let mut result = DayResult::new(
+                func.day,
+                func.part,
+                func.day_function_id.clone(),
+                func.variant.clone(),
+            );
+            if func.is_error {
+                result.error = Some(func.message.clone());
+            } else {
+                result.answer = Some(func.message.clone());
+            }
+            result.timing = func.timing_data.take();
+            result.memory = func.memory_data.take();
+            result.input = func.input.clone();
+            output.collected.push(result);
+        }
+
+        if func.is_truncated_answer(term_width) {
+            output.truncated_answers.push(AlternateAnswer::for_function(
+                func.message,
+                func.day,
+                func.name,
+                func.part,
+                &func.day_function_id,
+            ));
+        }
     }
 
-    Ok(time_receiver.iter().sum())
+    output.total_time = time_receiver.iter().sum();
+    Ok(output)
 }
 
 pub fn run_simple_bench(alloc: &'static TracingAlloc, days: &[&Day]) -> BenchResult {
-    // We should limit the number of threads in the pool. Having too many
-    // results in them basically fighting for priority with the two update threads
-    // negatively effecting the benchmark.
-    let num_threads = ARGS
-        .num_threads
-        .unwrap_or_else(|| num_cpus::get_physical().saturating_sub(2))
-        .max(1);
-
-    let pool = ThreadPoolBuilder::new()
-        .num_threads(num_threads)
-        .build()
-        .expect("Failed to build threadpool");
+    let (runs, _) = bench_simple(alloc, days)?;
+    outcome_result(&runs[0])
+}
 
+/// Benches the days, printing the table, and returns the results from each run, along with
+/// the full results of the first run.
+pub(crate) fn bench_simple(
+    alloc: &'static TracingAlloc,
+    days: &[&Day],
+) -> Result<(Vec<Vec<FunctionSummary>>, Vec<DayResult>), BenchError> {
     // MultiProgress goes a bit nuts if the terminal isn't tall enough to display all the bars
     // at once. So we need to chunk the functions to bench based on how tall the terminal is.
-    let stdout = Term::stdout();
-    let (rows, cols) = stdout.size();
+    let (rows, _) = OUTPUT.term_size();
     // Add room for header and trailing line.
-    let rows = rows.saturating_sub(5) as usize;
-
-    print_header(cols as _);
+    let rows = if OUTPUT.has_progress() {
+        rows.saturating_sub(5) as usize
+    } else {
+        usize::MAX
+    };
 
+    let style = OUTPUT.style();
     let spinner_style = ProgressStyle::default_spinner()
         .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-        .template("{spinner} {prefix:.dim} | {msg}")
+        .template(style.template(
+            "{spinner} {prefix:.dim} | {msg}",
+            "{spinner} {prefix} | {msg}",
+        ))
         .unwrap();
     let finished_spinner = spinner_style
         .clone()
-        .template("{spinner} {prefix:.green} | {msg}")
+        .template(style.template(
+            "{spinner} {prefix:.green} | {msg}",
+            "{spinner} {prefix} | {msg}",
+        ))
+        .unwrap();
+    let warning_spinner = spinner_style
+        .clone()
+        .template(style.template("{spinner} {prefix:.yellow} | {msg}", "[W]{prefix} | {msg}"))
         .unwrap();
     let error_spinner = spinner_style
         .clone()
-        .template("{spinner} {prefix:.red} | {msg}")
+        .template(style.template("{spinner} {prefix:.red} | {msg}", "[E]{prefix} | {msg}"))
         .unwrap();
 
-    let mut benched_functions = Vec::new();
-    let mut cur_chunk = Vec::new();
+    let functions = selected_functions(days);
+    let parallelism = parallelism(functions.len());
+    let (functions, is_partial) = if ARGS.run_type().is_run_only() {
+        (functions, false)
+    } else {
+        apply_wall_budget(functions, parallelism)
+    };
+    let benched_functions: Vec<_> = functions
+        .into_iter()
+        .map(|(day, f)| {
+            let id = f.row_id();
+            (day, f, id)
+        })
+        .collect();
+    fit_day_col(
+        benched_functions
+            .iter()
+            .map(|(day, _, id)| function_row_label(day.day, id)),
+    );
+    print_parallelism(parallelism, benched_functions.len());
+    let pool = build_worker_pool(parallelism);
 
-    for day in days {
-        let parts = iter::once(day.part_1).chain(day.part_2).zip(1..);
+    // Loaded before benching, so a missing or corrupt baseline doesn't waste a run.
+    let baseline = ARGS.baseline.as_deref().map(Baseline::load).transpose()?;
 
-        for (f, i) in parts {
-            if cur_chunk.len() == rows {
-                benched_functions.push(cur_chunk);
-                cur_chunk = Vec::new();
-            }
+    let repeat_runs = if ARGS.run_type().is_run_only() {
+        1
+    } else {
+        ARGS.repeat_runs.max(1)
+    };
 
-            let p1f = BenchedFunction {
+    let mut runs = Vec::with_capacity(repeat_runs);
+    let mut first_alt_answers = None;
+    let mut first_truncated_answers = None;
+    let mut first_captured_output = None;
+    let mut first_collected = None;
+    let mut run_measurements = Vec::new();
+
+    for run in 1..=repeat_runs {
+        if repeat_runs > 1 {
+            println!("\n -- Run {} of {} --", run, repeat_runs);
+        }
+
+        print_header(OUTPUT.term_width());
+
+        let mut chunks = Vec::new();
+        let mut run_functions = benched_functions
+            .iter()
+            .map(|(day, f, day_function_id)| BenchedFunction {
                 day: day.day,
-                // name: day.name,
-                day_function_id: i,
-                function: f,
+                name: day.name,
+                part: f.part,
+                day_function_id: day_function_id.clone(),
+                variant: f.variant.clone(),
+                function: f.function,
+                expected: f.expected,
+                samples_path: f.samples_path(day.day),
+                sample_mode: ARGS.day_sample_mode(day),
                 message: String::new(),
                 is_error: false,
+                is_missing_input: false,
                 timing_data: None,
+                answer_time: None,
+                is_not_benched: false,
+                warning: None,
                 memory_data: None,
+                baseline: baseline
+                    .as_ref()
+                    .filter(|_| f.variant.is_none())
+                    .and_then(|b| {
+                        b.find(
+                            RUNNING_YEAR.load(Ordering::Relaxed),
+                            day.day,
+                            f.part,
+                            day_function_id,
+                        )
+                    }),
+                captured_output: None,
+                input: None,
+                is_finished: false,
                 finished_spinner: finished_spinner.clone(),
+                warning_spinner: warning_spinner.clone(),
                 error_spinner: error_spinner.clone(),
                 bar: None,
-                term_width: cols as usize,
-            };
-
-            cur_chunk.push(p1f);
+            })
+            .peekable();
+        while run_functions.peek().is_some() {
+            chunks.push(run_functions.by_ref().take(rows.max(1)).collect::<Vec<_>>());
         }
-    }
-    if !cur_chunk.is_empty() {
-        benched_functions.push(cur_chunk);
-    }
 
-    let (alt_answer_sender, alt_answer_receiver) = crossbeam_channel::unbounded();
+        let (alt_answer_sender, alt_answer_receiver) = crossbeam_channel::unbounded();
+        let mut results = Vec::new();
+        let mut truncated_answers = Vec::new();
+        let mut captured_output = Vec::new();
+        let mut measurements = Vec::new();
+        let mut markdown_rows = Vec::new();
+        let mut collected = Vec::new();
 
-    let total_time = benched_functions
-        .into_iter()
-        .map(|days_chunk| {
-            bench_days_chunk(
+        // Memory use is close enough to deterministic, and expensive to trace, so
+        // we only measure it on the first run.
+        let mut total_time = Duration::ZERO;
+        for days_chunk in chunks {
+            let chunk = bench_days_chunk(
                 alloc,
                 days_chunk,
                 alt_answer_sender.clone(),
                 &spinner_style,
                 &pool,
-            )
+                run == 1,
+                OUTPUT.draw_target(),
+                &mut results,
+            )?;
+            total_time += chunk.total_time;
+            truncated_answers.extend(chunk.truncated_answers);
+            captured_output.extend(chunk.captured_output);
+            measurements.extend(chunk.measurements);
+            markdown_rows.extend(chunk.markdown_rows);
+            collected.extend(chunk.collected);
+        }
+
+        print_footer(total_time, is_partial, OUTPUT.term_width());
+        if run == 1 {
+            write_badge(total_time)?;
+            if let Some(path) = ARGS.markdown.as_deref() {
+                write_markdown(
+                    path,
+                    &markdown_rows,
+                    total_time,
+                    ARGS.show_answers,
+                    is_background_bench(),
+                )?;
+            }
+        }
+        run_measurements.push(measurements);
+
+        // Every run sends the same alternate answers, we only need one set.
+        drop(alt_answer_sender);
+        first_alt_answers.get_or_insert(alt_answer_receiver);
+        first_truncated_answers.get_or_insert(truncated_answers);
+        first_captured_output.get_or_insert(captured_output);
+        first_collected.get_or_insert(collected);
+        runs.push(results);
+    }
+
+    let measurements = median_measurements(run_measurements);
+    update_records(
+        RUNNING_YEAR.load(Ordering::Relaxed),
+        &measurements,
+        ARGS.records_file.as_deref(),
+    )?;
+    if let Some(name) = &ARGS.save_baseline {
+        save_baseline(name, RUNNING_YEAR.load(Ordering::Relaxed), &measurements)?;
+    }
+
+    print_run_notes(&runs[0]);
+    if let Some(baseline) = &baseline {
+        baseline.print_note();
+    }
+
+    if ARGS.summary_line {
+        print_summary_lines(&runs[0]);
+    }
+
+    if repeat_runs > 1 {
+        print_run_variance(&runs);
+    }
+
+    if let Some(path) = &ARGS.runs_export {
+        write_runs_export(path, &runs)?;
+    }
+
+    if let Some(truncated_answers) = first_truncated_answers {
+        print_full_answers(&truncated_answers);
+    }
+
+    if let Some(captured_output) = first_captured_output {
+        print_captured_output(&captured_output);
+    }
+
+    if let Some(alt_answers) = first_alt_answers {
+        print_alt_answers(alt_answers);
+    }
+
+    Ok((runs, first_collected.unwrap_or_default()))
+}
+
+fn print_full_answers(answers: &[AlternateAnswer]) {
+    if !answers.is_empty() {
+        println!("\n -- Full Answers --");
+        for answer in answers {
+            println!("{}", answer.heading());
+            println!("{}\n", answer.answer);
+        }
+    }
+}
+
+pub(crate) fn print_captured_output(outputs: &[AlternateAnswer]) {
+    if !outputs.is_empty() {
+        println!("\n -- Captured Output --");
+        for output in outputs {
+            println!("{}", output.heading());
+            println!("{}", output.answer);
+        }
+    }
+}
+
+// Each function's measurement from the run with its median time, so a single slow or fast
+// run doesn't end up in the baseline or records. The memory is only measured on the first run.
+fn median_measurements(runs: Vec<Vec<Measurement>>) -> Vec<Measurement> {
+    let mut runs = runs.into_iter();
+    let Some(first) = runs.next() else {
+        return Vec::new();
+    };
+    let rest: Vec<_> = runs.collect();
+
+    first
+        .into_iter()
+        .map(|first| {
+            let mut timings: Vec<_> = rest
+                .iter()
+                .filter_map(|run| {
+                    run.iter()
+                        .find(|m| (m.day, m.part, &m.name) == (first.day, first.part, &first.name))
+                })
+                .map(|m| (m.mean, m.std_dev))
+                .chain([(first.mean, first.std_dev)])
+                .collect();
+            timings.sort_unstable();
+            let (mean, std_dev) = timings[timings.len() / 2];
+            Measurement {
+                mean,
+                std_dev,
+                ..first
+            }
         })
-        .try_fold(Duration::ZERO, |acc, a| a.map(|a| a + acc))?;
+        .collect()
+}
 
-    print_footer(total_time, cols as _);
+fn print_run_variance(runs: &[Vec<FunctionSummary>]) {
+    println!("\n -- Across {} Runs --", runs.len());
+    println!(
+        "{:>width$} | Min      | Median   | Max      | CV",
+        "Day",
+        width = day_col_width() + 2
+    );
+    println!(
+        "{}|__________|__________|__________|________",
+        day_col_rule()
+    );
 
-    drop(alt_answer_sender);
-    print_alt_answers(alt_answer_receiver);
+    for (i, first) in runs[0].iter().enumerate() {
+        // Labelled like the table's rows, rather than with the year.
+        let label = match first.label.split_once('.') {
+            Some((_, id)) => function_row_label(first.day, id),
+            None => first.label.clone(),
+        };
+        let label = pad_day_col(&label);
+        let mut means: Vec<_> = runs
+            .iter()
+            .filter_map(|run| run.get(i).and_then(|r| r.mean))
+            .collect();
+
+        if means.is_empty() {
+            println!("  {} | {:<8} | {:<8} | {:<8} |", label, "", "", "");
+            continue;
+        }
+
+        means.sort_unstable();
+        let min = means[0];
+        let max = means[means.len() - 1];
+        let median = means[means.len() / 2];
+
+        let mean_secs = means.iter().map(Duration::as_secs_f64).sum::<f64>() / means.len() as f64;
+        let variance = means
+            .iter()
+            .map(|m| (m.as_secs_f64() - mean_secs).powi(2))
+            .sum::<f64>()
+            / means.len() as f64;
+        let cv = if mean_secs > 0.0 {
+            variance.sqrt() / mean_secs * 100.0
+        } else {
+            0.0
+        };
+
+        println!(
+            "  {} | {} | {} | {} | {:>5.1}%",
+            label,
+            render_duration(min, false),
+            render_duration(median, false),
+            render_duration(max, false),
+            cv
+        );
+    }
+}
+
+fn write_runs_export(path: &Path, runs: &[Vec<FunctionSummary>]) -> BenchResult {
+    // Every row has the time, so exports from different machines can be concatenated.
+    let exported = Timestamp::now();
+    let mut output = String::from(concat!(
+        "run,function,name,mean_ns,max_ns,outlier_filter,",
+        "input,input_sha256,input_bytes,background,exported_at\n"
+    ));
+    for (run, results) in runs.iter().enumerate() {
+        for result in results {
+            let mean = result
+                .mean
+                .map(|m| m.as_nanos().to_string())
+                .unwrap_or_default();
+            let max = result
+                .max_sample
+                .map(|m| m.as_nanos().to_string())
+                .unwrap_or_default();
+            // Quote the name and path, as they could contain a comma.
+            let name = result.name.replace('"', "\"\"");
+            let (input, sha256, len) = match &result.input {
+                Some(input) => (
+                    input.source.replace('"', "\"\""),
+                    input.sha256.as_str(),
+                    input.len.to_string(),
+                ),
+                None => Default::default(),
+            };
+            writeln!(
+                output,
+                "{},{},\"{}\",{},{},{},\"{}\",{},{},{},{}",
+                run + 1,
+                result.label,
+                name,
+                mean,
+                max,
+                ARGS.outlier,
+                input,
+                sha256,
+                len,
+                is_background_bench(),
+                exported.rfc3339()
+            )
+            .unwrap();
+        }
+    }
+
+    write_atomic(&exported.fill_path(path), output.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use indicatif::TermLike;
+
+    use super::*;
+    use crate::{Bench, BenchResult, TEST_ALLOC};
+
+    fn measurement(mean_ms: u64, max_memory: Option<usize>) -> Measurement {
+        Measurement {
+            day: 1,
+            part: Some(2),
+            name: String::new(),
+            mean: Duration::from_millis(mean_ms),
+            std_dev: Duration::from_millis(mean_ms / 10),
+            max_memory,
+            num_allocs: max_memory.map(|_| 1),
+            input: None,
+        }
+    }
+
+    #[test]
+    fn median_of_runs() {
+        let runs = vec![
+            vec![measurement(30, Some(64))],
+            vec![measurement(10, None)],
+            vec![measurement(20, None)],
+        ];
+        let medians = median_measurements(runs);
+
+        assert_eq!(medians.len(), 1);
+        assert_eq!(medians[0].mean, Duration::from_millis(20));
+        assert_eq!(medians[0].std_dev, Duration::from_millis(2));
+        // Only the first run is traced.
+        assert_eq!(medians[0].max_memory, Some(64));
+    }
 
-    Ok(())
+    // A terminal that's gone away, failing every write.
+    #[derive(Debug)]
+    struct BrokenTerm;
+
+    impl TermLike for BrokenTerm {
+        fn width(&self) -> u16 {
+            80
+        }
+
+        fn move_cursor_up(&self, _: usize) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn move_cursor_down(&self, _: usize) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn move_cursor_right(&self, _: usize) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn move_cursor_left(&self, _: usize) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn write_line(&self, _: &str) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn write_str(&self, _: &str) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn clear_line(&self) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&self) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    fn solution(_: &str, bench: Bench) -> BenchResult {
+        bench.bench(|| Ok::<_, String>(42))
+    }
+
+    fn benched_function(day: u8, style: &ProgressStyle) -> BenchedFunction {
+        BenchedFunction {
+            day,
+            name: "Mock",
+            part: Some(1),
+            day_function_id: "1".to_owned(),
+            variant: None,
+            function: solution,
+            expected: None,
+            samples_path: None,
+            sample_mode: SampleMode::Timed(0),
+            message: String::new(),
+            is_error: false,
+            is_missing_input: false,
+            timing_data: None,
+            answer_time: None,
+            is_not_benched: false,
+            warning: None,
+            memory_data: None,
+            baseline: None,
+            captured_output: None,
+            input: None,
+            is_finished: false,
+            finished_spinner: style.clone(),
+            warning_spinner: style.clone(),
+            error_spinner: style.clone(),
+            bar: None,
+        }
+    }
+
+    #[test]
+    fn broken_draw_target_keeps_results() {
+        let style = ProgressStyle::default_spinner();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let (alt_answer_sender, _alt_answer_receiver) = crossbeam_channel::unbounded();
+        let mut results = Vec::new();
+
+        let funcs = vec![benched_function(3, &style), benched_function(4, &style)];
+        let chunk = bench_days_chunk(
+            &TEST_ALLOC,
+            funcs,
+            alt_answer_sender,
+            &style,
+            &pool,
+            false,
+            ProgressDrawTarget::term_like(Box::new(BrokenTerm)),
+            &mut results,
+        );
+
+        // Every row is still there, and later chunks won't draw the progress.
+        assert!(chunk.is_ok());
+        let days: Vec<_> = results.iter().map(|r| r.day).collect();
+        assert_eq!(days, [3, 4]);
+        assert!(OUTPUT.is_progress_broken());
+        assert!(OUTPUT.draw_target().is_hidden());
+    }
 }
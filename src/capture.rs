@@ -0,0 +1,69 @@
+// Captures what solutions print, so it doesn't garble the progress bars and table.
+//
+// This uses the same per-thread hook as libtest, which is only available on nightly, so
+// without the `capture-output` feature nothing is captured.
+
+#[cfg(feature = "capture-output")]
+mod imp {
+    use std::{
+        cell::RefCell,
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    type Buffer = Arc<Mutex<Vec<u8>>>;
+
+    thread_local! {
+        static DISCARDING: RefCell<Option<Buffer>> = const { RefCell::new(None) };
+    }
+
+    fn with_capture<T>(buffer: Buffer, f: impl FnOnce() -> T) -> T {
+        let old = io::set_output_capture(Some(buffer));
+        let res = f();
+        io::set_output_capture(old);
+        res
+    }
+
+    /// Runs the function, returning everything the thread printed with `print!`, `eprint!`,
+    /// `dbg!` and friends. Output from threads the function spawns is also captured.
+    pub(crate) fn capture<T>(f: impl FnOnce() -> T) -> (T, String) {
+        let buffer = Buffer::default();
+        let res = with_capture(buffer.clone(), f);
+
+        let output = std::mem::take(&mut *buffer.lock().unwrap_or_else(|e| e.into_inner()));
+        (res, String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// Runs the function, throwing away anything it prints. Call [`trim_discarded`]
+    /// regularly so a noisy function doesn't fill memory.
+    pub(crate) fn discard<T>(f: impl FnOnce() -> T) -> T {
+        let buffer = Buffer::default();
+        let old = DISCARDING.with(|d| d.replace(Some(buffer.clone())));
+        let res = with_capture(buffer, f);
+        DISCARDING.with(|d| d.replace(old));
+        res
+    }
+
+    pub(crate) fn trim_discarded() {
+        DISCARDING.with(|d| {
+            if let Some(buffer) = &*d.borrow() {
+                buffer.lock().unwrap_or_else(|e| e.into_inner()).clear();
+            }
+        });
+    }
+}
+
+#[cfg(not(feature = "capture-output"))]
+mod imp {
+    pub(crate) fn capture<T>(f: impl FnOnce() -> T) -> (T, String) {
+        (f(), String::new())
+    }
+
+    pub(crate) fn discard<T>(f: impl FnOnce() -> T) -> T {
+        f()
+    }
+
+    pub(crate) fn trim_discarded() {}
+}
+
+pub(crate) use imp::*;
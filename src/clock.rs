@@ -0,0 +1,312 @@
+//! Every wall-clock date the crate saves or shows comes from here, so they're all formatted
+//! the same way.
+//!
+//! Saved timestamps are RFC 3339 in UTC, e.g. `2023-12-16T09:30:00Z`, so files are the same
+//! whichever machine wrote them. Setting `SOURCE_DATE_EPOCH` to a number of seconds since the
+//! Unix epoch fixes the clock, so the output can be compared between runs.
+
+use std::{
+    env,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use once_cell::sync::Lazy;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ARGS;
+
+const SECS_PER_DAY: u64 = 60 * 60 * 24;
+
+/// Replaced by the time of the run in output paths, e.g. `runs-{timestamp}.csv`.
+pub(crate) const PATH_PLACEHOLDER: &str = "{timestamp}";
+
+static FIXED: Lazy<Option<Timestamp>> = Lazy::new(|| {
+    let value = env::var("SOURCE_DATE_EPOCH").ok()?;
+    let fixed = parse_fixed_clock(&value);
+    if fixed.is_none() {
+        eprintln!("Ignoring SOURCE_DATE_EPOCH '{}', it isn't a number", value);
+    }
+    fixed
+});
+
+// Reads the time the clock is fixed at from `SOURCE_DATE_EPOCH`.
+fn parse_fixed_clock(value: &str) -> Option<Timestamp> {
+    value.trim().parse().ok().map(|secs| Timestamp { secs })
+}
+
+/// A moment in time, to the second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Timestamp {
+    /// Seconds since the Unix epoch.
+    secs: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Civil {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+// Howard Hinnant's civil_from_days.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_offset = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_offset + 2) / 5 + 1;
+    let month = if month_offset < 10 {
+        month_offset + 3
+    } else {
+        month_offset - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month as u32, day as u32)
+}
+
+// And its inverse, days_from_civil.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+impl Timestamp {
+    /// The current time, or `SOURCE_DATE_EPOCH` if it's set.
+    pub(crate) fn now() -> Self {
+        FIXED.unwrap_or_else(|| Self {
+            secs: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        })
+    }
+
+    pub(crate) fn days_since(self, earlier: Timestamp) -> u64 {
+        self.secs.saturating_sub(earlier.secs) / SECS_PER_DAY
+    }
+
+    fn civil(self, offset_secs: i64) -> Civil {
+        let secs = self.secs as i64 + offset_secs;
+        let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY as i64));
+        let time = secs.rem_euclid(SECS_PER_DAY as i64) as u32;
+        Civil {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+        }
+    }
+
+    /// The form it's saved in, e.g. `2023-12-16T09:30:00Z`.
+    pub(crate) fn rfc3339(self) -> String {
+        let c = self.civil(0);
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            c.year, c.month, c.day, c.hour, c.minute, c.second
+        )
+    }
+
+    /// A form that sorts by time, for file names, e.g. `20231216T093000Z`.
+    pub(crate) fn compact(self) -> String {
+        let c = self.civil(0);
+        format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            c.year, c.month, c.day, c.hour, c.minute, c.second
+        )
+    }
+
+    /// Reads the form written by [`rfc3339`](Self::rfc3339). Offsets other than `Z` are
+    /// accepted too, as the file could have been edited by hand.
+    pub(crate) fn parse_rfc3339(s: &str) -> Option<Self> {
+        let (date, time) = s.split_once(['T', 't', ' '])?;
+
+        let mut date = date.splitn(3, '-');
+        let year: i64 = date.next()?.parse().ok()?;
+        let month: u32 = date.next()?.parse().ok()?;
+        let day: u32 = date.next()?.parse().ok()?;
+
+        let (time, offset_secs) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+            (time, 0)
+        } else {
+            let split = time.rfind(['+', '-'])?;
+            let (time, offset) = time.split_at(split);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let hours: i64 = hours.parse().ok()?;
+            let minutes: i64 = minutes.parse().ok()?;
+            (time, sign * (hours * 3600 + minutes * 60))
+        };
+
+        let mut time = time.splitn(3, ':');
+        let hour: i64 = time.next()?.parse().ok()?;
+        let minute: i64 = time.next()?.parse().ok()?;
+        // Fractions of a second are dropped.
+        let second = time.next()?;
+        let second: i64 = second.split('.').next()?.parse().ok()?;
+
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+            return None;
+        }
+
+        let secs = days_from_civil(year, month, day) * SECS_PER_DAY as i64
+            + hour * 3600
+            + minute * 60
+            + second
+            - offset_secs;
+        Some(Self {
+            secs: u64::try_from(secs).ok()?,
+        })
+    }
+
+    /// Puts the time in place of the placeholder in the path, if there is one.
+    pub(crate) fn fill_path(self, path: &Path) -> PathBuf {
+        match path.to_str() {
+            Some(s) if s.contains(PATH_PLACEHOLDER) => {
+                PathBuf::from(s.replace(PATH_PLACEHOLDER, &self.compact()))
+            }
+            _ => path.to_owned(),
+        }
+    }
+
+    #[cfg(unix)]
+    fn local_offset(self) -> i64 {
+        let time = self.secs as libc::time_t;
+        // SAFETY: An all-zero tm is valid, and localtime_r only writes to it.
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        // SAFETY: Both pointers are to valid values for the duration of the call.
+        let converted = unsafe { !libc::localtime_r(&time, &mut tm).is_null() };
+        if converted {
+            tm.tm_gmtoff as i64
+        } else {
+            0
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn local_offset(self) -> i64 {
+        0
+    }
+}
+
+/// Dates are shown in UTC, unless `--local-time` is given, in which case the time and offset
+/// are shown too, as the offset can change between dates.
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !ARGS.local_time {
+            let c = self.civil(0);
+            return write!(f, "{:04}-{:02}-{:02}", c.year, c.month, c.day);
+        }
+
+        let offset = self.local_offset();
+        let c = self.civil(offset);
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02} {}{:02}:{:02}",
+            c.year,
+            c.month,
+            c.day,
+            c.hour,
+            c.minute,
+            if offset < 0 { '-' } else { '+' },
+            offset.abs() / 3600,
+            offset.abs() / 60 % 60
+        )
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.rfc3339())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Older files saved the seconds since the epoch.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Saved {
+            Secs(u64),
+            Rfc3339(String),
+        }
+
+        match Saved::deserialize(deserializer)? {
+            Saved::Secs(secs) => Ok(Self { secs }),
+            Saved::Rfc3339(s) => Self::parse_rfc3339(&s).ok_or_else(|| {
+                de::Error::invalid_value(de::Unexpected::Str(&s), &"an RFC 3339 timestamp")
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2023-12-16T09:30:00Z, as SOURCE_DATE_EPOCH would give it.
+    fn fixed() -> Timestamp {
+        parse_fixed_clock("1702719000\n").unwrap()
+    }
+
+    #[test]
+    fn fixed_clock() {
+        assert_eq!(
+            fixed(),
+            Timestamp {
+                secs: 1_702_719_000
+            }
+        );
+        assert_eq!(parse_fixed_clock("soon"), None);
+        assert_eq!(parse_fixed_clock("-1"), None);
+    }
+
+    #[test]
+    fn rfc3339() {
+        assert_eq!(fixed().rfc3339(), "2023-12-16T09:30:00Z");
+        assert_eq!(fixed().compact(), "20231216T093000Z");
+        // Shown as just the date without --local-time.
+        assert_eq!(fixed().to_string(), "2023-12-16");
+        assert_eq!(Timestamp { secs: 0 }.rfc3339(), "1970-01-01T00:00:00Z");
+
+        let leap = Timestamp::parse_rfc3339("2024-02-29T23:59:59Z").unwrap();
+        assert_eq!(leap.rfc3339(), "2024-02-29T23:59:59Z");
+        assert_eq!(Timestamp::parse_rfc3339(&fixed().rfc3339()), Some(fixed()));
+    }
+
+    #[test]
+    fn parse_offsets() {
+        let parse = |s| Timestamp::parse_rfc3339(s);
+        assert_eq!(parse("2023-12-16T04:30:00-05:00"), Some(fixed()));
+        assert_eq!(parse("2023-12-16 15:00:00.999+05:30"), Some(fixed()));
+        assert_eq!(parse("2023-13-16T09:30:00Z"), None);
+        assert_eq!(parse("2023-12-16"), None);
+    }
+
+    #[test]
+    fn fill_path() {
+        assert_eq!(
+            fixed().fill_path(Path::new("exports/runs-{timestamp}.csv")),
+            Path::new("exports/runs-20231216T093000Z.csv")
+        );
+        assert_eq!(
+            fixed().fill_path(Path::new("runs.csv")),
+            Path::new("runs.csv")
+        );
+    }
+}
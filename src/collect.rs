@@ -0,0 +1,182 @@
+//! Benches a year's days and returns the results, for binaries that do their own reporting,
+//! such as one that aggregates several years.
+//!
+//! The bench is still configured by the command line arguments, and the inputs are read from
+//! the same place, like [`run`](crate::run).
+
+use std::{
+    panic,
+    sync::{atomic::Ordering, Arc},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    alloc,
+    bench::{bench_worker, simple::bench_simple, Bench, BenchEvent, MemoryData, RuntimeData},
+    days_filter, get_days, is_background_bench,
+    output::OUTPUT,
+    plan::{build_worker_pool, parallelism, selected_functions},
+    BenchError, Day, InputProvenance, TracingAlloc, ARGS, RUNNING_YEAR,
+};
+
+/// The results of one of a day's functions.
+pub struct DayResult {
+    pub day: u8,
+    /// The part number, or `None` for other functions.
+    pub part: Option<u8>,
+    /// The part number, or the other function's name, as shown in the table. The parse
+    /// function is `p`, and a variant's name follows a `/`.
+    pub function: String,
+    /// The input variant the function was given, or `None` for the day's own input.
+    pub variant: Option<String>,
+    pub answer: Option<String>,
+    /// Why the function failed, if it did. It may have given an answer first.
+    pub error: Option<String>,
+    pub timing: Option<RuntimeData>,
+    pub memory: Option<MemoryData>,
+    /// The input the answer came from.
+    pub input: Option<Arc<InputProvenance>>,
+    /// Whether it was timed with `--background`, so the timing isn't comparable with other
+    /// runs'.
+    pub background: bool,
+}
+
+impl DayResult {
+    pub(crate) fn new(
+        day: u8,
+        part: Option<u8>,
+        function: String,
+        variant: Option<String>,
+    ) -> Self {
+        Self {
+            day,
+            part,
+            function,
+            variant,
+            answer: None,
+            error: None,
+            timing: None,
+            memory: None,
+            input: None,
+            background: is_background_bench(),
+        }
+    }
+}
+
+/// Benches a year's days, returning the results. Nothing is printed unless
+/// [`render`](Self::render) is turned on.
+pub struct Collector<'a> {
+    alloc: &'static TracingAlloc,
+    year: u16,
+    days: &'a [Day],
+    render: bool,
+}
+
+impl<'a> Collector<'a> {
+    pub fn new(alloc: &'static TracingAlloc, year: u16, days: &'a [Day]) -> Self {
+        Self {
+            alloc,
+            year,
+            days,
+            render: false,
+        }
+    }
+
+    /// Prints the simple table and its progress while benching, as `bench` does.
+    pub fn render(mut self, render: bool) -> Self {
+        self.render = render;
+        self
+    }
+
+    /// Benches the days picked on the command line, or all of them. A function failing is
+    /// only an error in its result.
+    pub fn run(self) -> Result<Vec<DayResult>, BenchError> {
+        let days = get_days(self.year, self.days, days_filter())?;
+        RUNNING_YEAR.store(self.year, Ordering::Relaxed);
+        alloc::exclude_current_thread();
+        alloc::set_trace_buffer_cap(ARGS.trace_buffer_cap);
+        alloc::set_sample_rate(ARGS.mem_sample_rate);
+
+        if self.render {
+            Lazy::force(&OUTPUT);
+            println!("Advent of Code {}", self.year);
+            let (_, results) = bench_simple(self.alloc, &days)?;
+            return Ok(results);
+        }
+
+        Ok(bench_quietly(self.alloc, &days))
+    }
+}
+
+/// Benches the year's days without printing anything. See [`Collector`] to print the table
+/// while benching.
+pub fn run_collect(
+    alloc: &'static TracingAlloc,
+    year: u16,
+    days: &[Day],
+) -> Result<Vec<DayResult>, BenchError> {
+    Collector::new(alloc, year, days).run()
+}
+
+fn bench_quietly(alloc: &'static TracingAlloc, days: &[&Day]) -> Vec<DayResult> {
+    let functions = selected_functions(days);
+    let pool = build_worker_pool(parallelism(functions.len()));
+    let (sender, receiver) = crossbeam_channel::unbounded();
+
+    // The errors are in the results, so we don't want the panic messages as well.
+    let old_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut results = Vec::with_capacity(functions.len());
+    for (id, (day, f)) in functions.iter().enumerate() {
+        results.push(DayResult::new(
+            day.day,
+            f.part,
+            f.row_id(),
+            f.variant.clone(),
+        ));
+
+        let bench = Bench {
+            alloc,
+            id,
+            chan: sender.clone(),
+            run_only: ARGS.run_type().is_run_only(),
+            sample_mode: ARGS.day_sample_mode(day),
+            answer_history: None,
+            measure_memory: true,
+            measure_single: false,
+            input: None,
+            expected: f.expected,
+            samples_path: f.samples_path(day.day),
+            input_bytes: None,
+        };
+        let (day, variant, function) = (day.day, f.variant.clone(), f.function);
+        pool.spawn(move || bench_worker(day, variant, bench, function));
+    }
+
+    // The workers hold the only other senders, so this ends once they're all done.
+    drop(sender);
+    for event in receiver {
+        match event {
+            BenchEvent::Answer {
+                answer,
+                id,
+                is_alt: false,
+                input,
+            } => {
+                results[id].answer = Some(answer);
+                results[id].input = input;
+            }
+            BenchEvent::Memory { data, id } => results[id].memory = Some(data),
+            BenchEvent::Timing { data, id } => results[id].timing = Some(data),
+            BenchEvent::Error { err, id }
+            | BenchEvent::LateError { err, id }
+            | BenchEvent::InputError { err, id } => results[id].error = Some(err),
+            _ => {}
+        }
+    }
+
+    panic::set_hook(old_panic_hook);
+    results
+}
@@ -0,0 +1,281 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{bench::FunctionSummary, render_duration, BenchError};
+
+const CRITERION_ESTIMATES: &str = "new/estimates.json";
+
+// Only the parts of criterion's `estimates.json` that we use.
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+    confidence_interval: ConfidenceInterval,
+}
+
+#[derive(Deserialize)]
+struct ConfidenceInterval {
+    confidence_level: f64,
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+/// A previously measured time for a function, to compare against.
+pub(crate) struct OldTiming {
+    pub(crate) day: u8,
+    pub(crate) part: u8,
+    pub(crate) mean: Duration,
+    /// Half the width of the confidence interval, as a fraction of the mean.
+    pub(crate) uncertainty: Option<f64>,
+}
+
+// Parses numbers for the `{day}` and `{part}` placeholders, matching everything else literally.
+fn match_benchmark_name(pattern: &str, name: &str) -> Option<(u8, u8)> {
+    // Returns the (day, part) captured in the rest of the name.
+    fn matches(pattern: &str, name: &str) -> Option<(Option<u8>, Option<u8>)> {
+        let (rest, is_day) = if let Some(rest) = pattern.strip_prefix("{day}") {
+            (rest, true)
+        } else if let Some(rest) = pattern.strip_prefix("{part}") {
+            (rest, false)
+        } else {
+            return match (pattern.chars().next(), name.chars().next()) {
+                (None, None) => Some((None, None)),
+                (Some(p), Some(n)) if p == n => {
+                    matches(&pattern[p.len_utf8()..], &name[n.len_utf8()..])
+                }
+                _ => None,
+            };
+        };
+
+        let digits = name.bytes().take_while(u8::is_ascii_digit).count();
+        // Try the longest number first, so day10 isn't read as day1 followed by a 0.
+        (1..=digits).rev().find_map(|len| {
+            let value = name[..len].parse().ok()?;
+            let (day, part) = matches(rest, &name[len..])?;
+            Some(if is_day {
+                (Some(value), part)
+            } else {
+                (day, Some(value))
+            })
+        })
+    }
+
+    let (day, part) = matches(pattern, name)?;
+    day.zip(part)
+}
+
+// Criterion stores each benchmark in its own directory, nested by group, with the
+// estimates of the latest run in `new/estimates.json`.
+fn find_criterion_benchmarks(dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), BenchError> {
+    let entries = fs::read_dir(dir).map_err(|e| BenchError::InputFileError {
+        inner: e,
+        name: dir.display().to_string(),
+    })?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() || entry.file_name() == "report" {
+            continue;
+        }
+
+        if path.join(CRITERION_ESTIMATES).is_file() {
+            found.push(path.clone());
+        }
+        find_criterion_benchmarks(&path, found)?;
+    }
+
+    Ok(())
+}
+
+/// Reads criterion's results, mapping the benchmark names to days and parts using the pattern.
+///
+/// Returns the mapped timings, and the names of any benchmarks that couldn't be mapped.
+pub(crate) fn read_criterion_dir(
+    dir: &Path,
+    pattern: &str,
+) -> Result<(Vec<OldTiming>, Vec<String>), BenchError> {
+    let mut benchmarks = Vec::new();
+    find_criterion_benchmarks(dir, &mut benchmarks)?;
+    benchmarks.sort();
+
+    let mut timings = Vec::new();
+    let mut unmapped = Vec::new();
+
+    for bench_dir in benchmarks {
+        let name = bench_dir
+            .strip_prefix(dir)
+            .unwrap_or(&bench_dir)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let Some((day, part)) = match_benchmark_name(pattern, &name) else {
+            unmapped.push(name);
+            continue;
+        };
+
+        let estimates_path = bench_dir.join(CRITERION_ESTIMATES);
+        let contents =
+            fs::read_to_string(&estimates_path).map_err(|e| BenchError::InputFileError {
+                inner: e,
+                name: estimates_path.display().to_string(),
+            })?;
+        let estimates: Estimates =
+            serde_json::from_str(&contents).map_err(|e| BenchError::ImportError {
+                inner: e,
+                name: estimates_path.display().to_string(),
+            })?;
+
+        let mean = estimates.mean;
+        let interval = mean.confidence_interval;
+        let uncertainty = (mean.point_estimate > 0.0 && interval.confidence_level > 0.0)
+            .then(|| (interval.upper_bound - interval.lower_bound) / 2.0 / mean.point_estimate);
+
+        timings.push(OldTiming {
+            day,
+            part,
+            mean: Duration::from_secs_f64(mean.point_estimate.max(0.0) / 1e9),
+            uncertainty,
+        });
+    }
+
+    Ok((timings, unmapped))
+}
+
+/// Prints a table comparing the benched functions against the old timings.
+pub(crate) fn print_comparison(
+    tool: &str,
+    location: &str,
+    old: &[OldTiming],
+    results: &[FunctionSummary],
+) {
+    println!("\n -- Compared with {} ({}) --", tool, location);
+    println!("   Day | Old      | ±      | New      | Delta");
+    println!("_______|__________|________|__________|_________");
+
+    for result in results {
+        let Some(part) = result.part else {
+            continue;
+        };
+        let old_timing = old.iter().find(|o| o.day == result.day && o.part == part);
+
+        let old_mean =
+            old_timing.map_or_else(|| format!("{:<8}", "-"), |o| render_duration(o.mean, false));
+        let uncertainty = old_timing
+            .and_then(|o| o.uncertainty)
+            .map_or_else(|| "-".to_owned(), |u| format!("{:.1}%", u * 100.0));
+        let new_mean = result
+            .mean
+            .map_or_else(|| format!("{:<8}", "-"), |m| render_duration(m, false));
+        let delta = match (old_timing, result.mean) {
+            (Some(o), Some(new)) if !o.mean.is_zero() => {
+                let change = (new.as_secs_f64() / o.mean.as_secs_f64() - 1.0) * 100.0;
+                format!("{:>+7.1}%", change)
+            }
+            _ => "-".to_owned(),
+        };
+
+        println!(
+            " {:>5} | {} | {:>6} | {} | {}",
+            result.label, old_mean, uncertainty, new_mean, delta
+        );
+    }
+
+    println!(
+        "\nOld times are the mean estimated by {}, with ± being half its confidence interval.\n\
+         The tools sample and filter outliers differently, so small deltas may just be noise.",
+        tool
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed from a real run, which also has the median, slope and deviations.
+    const ESTIMATES: &str = r#"{
+        "mean": {
+            "confidence_interval": {
+                "confidence_level": 0.95,
+                "lower_bound": 1480.5,
+                "upper_bound": 1519.5
+            },
+            "point_estimate": 1500.0,
+            "standard_error": 9.9
+        },
+        "median": {
+            "confidence_interval": {
+                "confidence_level": 0.95,
+                "lower_bound": 1470.0,
+                "upper_bound": 1490.0
+            },
+            "point_estimate": 1481.0,
+            "standard_error": 5.1
+        }
+    }"#;
+
+    fn write_estimates(dir: &Path, name: &str, contents: &str) {
+        let path = dir.join(name).join(CRITERION_ESTIMATES);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn reads_estimates() {
+        let dir = tempfile::tempdir().unwrap();
+        write_estimates(dir.path(), "day12/part2", ESTIMATES);
+        write_estimates(dir.path(), "day12/parse", ESTIMATES);
+        // Criterion's own report directory isn't a benchmark.
+        write_estimates(dir.path(), "report", ESTIMATES);
+
+        let (timings, unmapped) = read_criterion_dir(dir.path(), "day{day}/part{part}").unwrap();
+
+        assert_eq!(unmapped, ["day12/parse"]);
+        assert_eq!(timings.len(), 1);
+        assert_eq!((timings[0].day, timings[0].part), (12, 2));
+        assert_eq!(timings[0].mean, Duration::from_nanos(1500));
+        // The interval is 39ns wide, so ±19.5ns around the mean of 1500ns.
+        let uncertainty = timings[0].uncertainty.unwrap();
+        assert!((uncertainty - 0.013).abs() < 1e-9, "{}", uncertainty);
+    }
+
+    #[test]
+    fn malformed_estimates() {
+        let dir = tempfile::tempdir().unwrap();
+        write_estimates(dir.path(), "day1/part1", r#"{"median": {}}"#);
+
+        let err = read_criterion_dir(dir.path(), "day{day}/part{part}")
+            .err()
+            .unwrap();
+        assert!(
+            matches!(&err, BenchError::ImportError { name, .. } if name.ends_with("estimates.json")),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn missing_criterion_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("criterion");
+
+        let err = read_criterion_dir(&missing, "day{day}/part{part}")
+            .err()
+            .unwrap();
+        assert!(
+            matches!(&err, BenchError::InputFileError { name, .. } if *name == missing.display().to_string()),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::{collections::BTreeSet, fmt::Write, fs, path::Path};
+
+use crate::{plan::day_functions, BenchError, Day};
+
+const HEADER: &str = "# aoc_lib registration digest";
+
+/// One registered function, e.g. `2021 day05 part2` or `2021 day05 other visualise`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    year: u16,
+    day: u8,
+    /// The registered name of an other function, or `None` for a part.
+    other: Option<String>,
+    part: Option<u8>,
+}
+
+impl Entry {
+    fn render(&self) -> String {
+        match (&self.other, self.part) {
+            (Some(name), _) => format!("{} day{:02} other {}", self.year, self.day, name),
+            (None, Some(part)) => format!("{} day{:02} part{}", self.year, self.day, part),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, ' ');
+        let year = fields.next()?.parse().ok()?;
+        let day = fields.next()?.strip_prefix("day")?.parse().ok()?;
+        let kind = fields.next()?;
+
+        let (other, part) = match kind.strip_prefix("other ") {
+            Some(name) => (Some(name.to_owned()), None),
+            None => (None, Some(kind.strip_prefix("part")?.parse().ok()?)),
+        };
+
+        Some(Self {
+            year,
+            day,
+            other,
+            part,
+        })
+    }
+}
+
+fn registered_entries(years: &[(u16, &[Day])]) -> BTreeSet<Entry> {
+    let mut entries = BTreeSet::new();
+    for &(year, days) in years {
+        for day in days {
+            entries.extend(day_functions(day).map(|f| Entry {
+                year,
+                day: day.day,
+                other: f.part.is_none().then(|| f.name.to_owned()),
+                part: f.part,
+            }));
+        }
+    }
+
+    entries
+}
+
+// FNV-1a, as the standard library doesn't promise its hashes are stable between versions.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Renders the sorted list of every registered function, one per line, headed by a hash
+/// of the list so two digests can be compared at a glance.
+pub(crate) fn registration_digest(years: &[(u16, &[Day])]) -> String {
+    let lines: Vec<_> = registered_entries(years)
+        .iter()
+        .map(Entry::render)
+        .collect();
+    let listing = lines.join("\n");
+
+    let mut digest = String::new();
+    writeln!(digest, "{}", HEADER).unwrap();
+    writeln!(digest, "# hash: {:016x}", fnv1a(listing.as_bytes())).unwrap();
+    for line in lines {
+        writeln!(digest, "{}", line).unwrap();
+    }
+
+    digest
+}
+
+enum Change {
+    Added(Entry),
+    Removed(Entry),
+    Renamed { old: Entry, new: Entry },
+}
+
+// An other function that disappeared from a day that gained one was most likely renamed,
+// so those are paired up, in name order.
+fn diff_entries(old: &BTreeSet<Entry>, new: &BTreeSet<Entry>) -> Vec<Change> {
+    let mut removed: Vec<_> = old.difference(new).cloned().collect();
+    let mut added: Vec<_> = new.difference(old).cloned().collect();
+    let mut changes = Vec::new();
+
+    let mut i = 0;
+    while i < removed.len() {
+        let old_entry = &removed[i];
+        let rename = added.iter().position(|a| {
+            a.year == old_entry.year
+                && a.day == old_entry.day
+                && a.other.is_some()
+                && old_entry.other.is_some()
+        });
+
+        match rename {
+            Some(j) => changes.push(Change::Renamed {
+                old: removed.remove(i),
+                new: added.remove(j),
+            }),
+            None => i += 1,
+        }
+    }
+
+    changes.extend(removed.into_iter().map(Change::Removed));
+    changes.extend(added.into_iter().map(Change::Added));
+    changes
+}
+
+/// Compares the registered functions against a digest saved by `--registration-digest`,
+/// printing what was added, removed, or renamed.
+pub(crate) fn diff_registrations(
+    years: &[(u16, &[Day])],
+    old_digest: &Path,
+) -> Result<(), BenchError> {
+    let contents = fs::read_to_string(old_digest).map_err(|e| BenchError::InputFileError {
+        inner: e,
+        name: old_digest.display().to_string(),
+    })?;
+
+    let old: BTreeSet<_> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(Entry::parse)
+        .collect();
+    let new = registered_entries(years);
+
+    let changes = diff_entries(&old, &new);
+    if changes.is_empty() {
+        println!("No changes since {}", old_digest.display());
+        return Ok(());
+    }
+
+    println!("Changes since {}:", old_digest.display());
+    for change in &changes {
+        match change {
+            Change::Added(entry) => println!("  + {}", entry.render()),
+            Change::Removed(entry) => println!("  - {}", entry.render()),
+            Change::Renamed { old, new } => println!(
+                "  ~ {} -> {}",
+                old.render(),
+                new.other.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+
+    Err(BenchError::RegistrationMismatch(changes.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bench, BenchResult};
+
+    fn solution(_: &str, bench: Bench) -> BenchResult {
+        bench.bench(|| Ok::<_, String>(42))
+    }
+
+    // Reads the entries back from a digest, as the diff does.
+    fn parse_digest(digest: &str) -> BTreeSet<Entry> {
+        digest
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(Entry::parse)
+            .collect()
+    }
+
+    #[test]
+    fn digest_round_trips() {
+        let days = [Day::new(5, "Vents", solution)
+            .part_2(solution)
+            .others(&[("brute force", solution)])];
+        let digest = registration_digest(&[(2021, &days)]);
+
+        let lines: Vec<_> = digest.lines().collect();
+        assert_eq!(lines[0], HEADER);
+        assert_eq!(
+            lines[2..],
+            [
+                "2021 day05 part1",
+                "2021 day05 part2",
+                "2021 day05 other brute force"
+            ]
+        );
+        assert_eq!(parse_digest(&digest), registered_entries(&[(2021, &days)]));
+    }
+
+    #[test]
+    fn renamed_function() {
+        // The same function, registered under a new name.
+        let old_days = [Day::new(5, "Vents", solution).others(&[("brute", solution)])];
+        let new_days = [Day::new(5, "Vents", solution).others(&[("brute_force", solution)])];
+        let old = parse_digest(&registration_digest(&[(2021, &old_days)]));
+        let new = registered_entries(&[(2021, &new_days)]);
+
+        let changes = diff_entries(&old, &new);
+        assert_eq!(changes.len(), 1);
+        match &changes[0] {
+            Change::Renamed { old, new } => {
+                assert_eq!(old.render(), "2021 day05 other brute");
+                assert_eq!(new.render(), "2021 day05 other brute_force");
+            }
+            Change::Added(_) | Change::Removed(_) => panic!("not reported as a rename"),
+        }
+    }
+
+    #[test]
+    fn moved_function_isnt_renamed() {
+        // Another day's function, or a part, is a removal and an addition.
+        let old_days = [
+            Day::new(5, "Vents", solution).others(&[("brute", solution)]),
+            Day::new(6, "Lanternfish", solution),
+        ];
+        let new_days = [
+            Day::new(5, "Vents", solution),
+            Day::new(6, "Lanternfish", solution)
+                .part_2(solution)
+                .others(&[("brute", solution)]),
+        ];
+        let old = registered_entries(&[(2021, &old_days)]);
+        let new = registered_entries(&[(2021, &new_days)]);
+
+        let changes = diff_entries(&old, &new);
+        let rendered: Vec<_> = changes
+            .iter()
+            .map(|change| match change {
+                Change::Added(entry) => format!("+ {}", entry.render()),
+                Change::Removed(entry) => format!("- {}", entry.render()),
+                Change::Renamed { .. } => panic!("reported as a rename"),
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                "- 2021 day05 other brute",
+                "+ 2021 day06 part2",
+                "+ 2021 day06 other brute"
+            ]
+        );
+    }
+}
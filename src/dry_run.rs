@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use bytesize::ByteSize;
+
+use crate::{
+    clock::Timestamp,
+    day_input,
+    plan::{
+        apply_wall_budget, estimate_run_time, parallelism, print_parallelism, selected_functions,
+    },
+    render_duration, BenchError, Day, ARGS,
+};
+
+// Checks the file could be written, by creating a temporary file next to it the same way
+// `write_atomic` does.
+fn check_writable(option: &str, path: &Path) -> Result<(), String> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    tempfile::NamedTempFile::new_in(dir)
+        .map(drop)
+        .map_err(|e| format!("{} '{}' can't be written: {}", option, path.display(), e))
+}
+
+/// Goes through everything a bench would do up to spawning the workers, printing the plan and
+/// reporting every problem found, without running any of the solutions.
+pub(crate) fn dry_run(days: &[&Day], detailed: bool) -> Result<(), BenchError> {
+    let outputs = [
+        ("--runs-export", ARGS.runs_export.as_deref()),
+        ("--badge", ARGS.badge.as_deref()),
+        ("--speedup-file", ARGS.speedup_file.as_deref()),
+        ("--records-file", ARGS.records_file.as_deref()),
+    ];
+    let problems = check_plan(days, detailed, &outputs);
+
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+
+    println!("\nProblems:");
+    for problem in &problems {
+        println!("  - {}", problem);
+    }
+
+    Err(BenchError::DryRunFailed(problems.len()))
+}
+
+// Prints the plan, returning the problems with it. The outputs are the options giving the
+// files the bench would write, and their paths if given.
+fn check_plan(days: &[&Day], detailed: bool, outputs: &[(&str, Option<&Path>)]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    println!(" -- Dry Run --");
+    println!(
+        "Each function: {}, {} stack, timeout {}, memory traced{}",
+        match ARGS.iterations {
+            Some(count) => format!("{} iterations", count),
+            None => format!("bench time {}s", ARGS.bench_time),
+        },
+        ByteSize(ARGS.stack_size as u64),
+        ARGS.timeout
+            .map_or_else(|| "none".to_owned(), |t| format!("{:?}", t)),
+        if detailed { "" } else { " on the first run" },
+    );
+
+    let functions = selected_functions(days);
+    for day in days {
+        let input_file = day_input(day.day, ARGS.input_suffix.as_deref());
+        let path = input_file.path();
+        let input = match input_file.open() {
+            Ok(input) => format!("{}, {}", path, ByteSize(input.len() as u64)),
+            Err(e) => {
+                problems.push(format!("Day {}: {}", day.day, e));
+                format!("{}, missing", path)
+            }
+        };
+
+        println!("Day {} ({}): {}", day.day, day.name, input);
+        for (_, f) in functions.iter().filter(|(d, _)| d.day == day.day) {
+            println!("  {}", f.title());
+        }
+    }
+
+    let parallelism = parallelism(functions.len());
+    let (functions, _) = apply_wall_budget(functions, parallelism);
+    print_parallelism(parallelism, functions.len());
+
+    let repeat_runs = if detailed { 1 } else { ARGS.repeat_runs.max(1) };
+    let estimate = estimate_run_time(functions.len(), parallelism) * repeat_runs as u32;
+    println!(
+        "{} functions on {} worker threads, {} run(s), estimated {}",
+        functions.len(),
+        parallelism.worker_threads,
+        repeat_runs,
+        render_duration(estimate, false).trim()
+    );
+
+    for &(option, path) in outputs {
+        if let Some(path) = path {
+            if let Err(problem) = check_writable(option, &Timestamp::now().fill_path(path)) {
+                problems.push(problem);
+            }
+        }
+    }
+
+    if detailed && ARGS.repeat_runs > 1 {
+        problems.push("--repeat-runs is ignored by the detailed bench".to_owned());
+    }
+    if detailed && ARGS.runs_export.is_some() {
+        problems.push("--runs-export is ignored by the detailed bench".to_owned());
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::{Bench, BenchResult};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn solution(_: &str, _: Bench) -> BenchResult {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    #[test]
+    fn finds_every_problem_without_running() {
+        // The crate has no inputs directory, so the day's input is missing.
+        const DAY: Day = Day::new(7, "Mock", solution)
+            .part_2(solution)
+            .parse(solution);
+
+        // Permissions don't stop root, so the export directory is a file instead.
+        let dir = tempfile::tempdir().unwrap();
+        let not_a_dir = dir.path().join("exports");
+        fs::write(&not_a_dir, "").unwrap();
+        let export = not_a_dir.join("runs.csv");
+
+        let problems = check_plan(&[&DAY], false, &[("--runs-export", Some(&export))]);
+
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].starts_with("Day 7: "), "{}", problems[0]);
+        assert!(
+            problems[1].starts_with(&format!("--runs-export '{}'", export.display())),
+            "{}",
+            problems[1]
+        );
+    }
+}
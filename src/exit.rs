@@ -0,0 +1,168 @@
+use std::process;
+
+use crate::{BenchError, BenchResult};
+
+/// The broad kind of failure, used to pick the process exit code.
+///
+/// The codes are part of the public interface, so scripts can rely on them:
+///
+/// | Code | Category         | Meaning                                                  |
+/// |------|------------------|----------------------------------------------------------|
+/// | 0    | `Success`        | Everything ran.                                          |
+/// | 1    | `SolutionError`  | A solution returned an error or panicked.                |
+/// | 2    | `Infrastructure` | The harness couldn't do its job, e.g. a missing input.   |
+/// | 3    | `Verification`   | A check on the results failed, e.g. a regression gate.   |
+/// | 130  | `Interrupted`    | The run was interrupted by the user.                     |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCategory {
+    Success,
+    SolutionError,
+    Infrastructure,
+    Verification,
+    Interrupted,
+}
+
+impl ExitCategory {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ExitCategory::Success => 0,
+            ExitCategory::SolutionError => 1,
+            ExitCategory::Infrastructure => 2,
+            ExitCategory::Verification => 3,
+            ExitCategory::Interrupted => 130,
+        }
+    }
+}
+
+impl BenchError {
+    pub fn category(&self) -> ExitCategory {
+        match self {
+            BenchError::UserError(_) => ExitCategory::SolutionError,
+            BenchError::RegistrationMismatch(_) | BenchError::FailedExamples(_) => {
+                ExitCategory::Verification
+            }
+            BenchError::FailedFunctions { errored, .. } if !errored.is_empty() => {
+                ExitCategory::SolutionError
+            }
+            BenchError::FailedFunctions { .. } => ExitCategory::Verification,
+
+            BenchError::MemoryBenchError(..)
+            | BenchError::ChannelError(_)
+            | BenchError::ResultsLost
+            | BenchError::InputFileError { .. }
+            | BenchError::InputFetchError { .. }
+            | BenchError::InputDirError { .. }
+            | BenchError::OutputFileError { .. }
+            | BenchError::SavedResultsError { .. }
+            | BenchError::ImportError { .. }
+            | BenchError::MissingBaseline(_)
+            | BenchError::CorruptBaseline { .. }
+            | BenchError::DaysFilterError(..)
+            | BenchError::PartFilterError(..)
+            | BenchError::YearFilterError(_)
+            | BenchError::NoParseFunction(_)
+            | BenchError::DryRunFailed(_)
+            | BenchError::NotInteractive
+            | BenchError::ScaffoldTargetNotEmpty(_)
+            | BenchError::PartialFailure { .. } => ExitCategory::Infrastructure,
+        }
+    }
+}
+
+pub trait IntoExitCode {
+    fn into_exit_code(self) -> i32;
+}
+
+impl IntoExitCode for BenchResult {
+    fn into_exit_code(self) -> i32 {
+        match self {
+            Ok(()) => ExitCategory::Success.exit_code(),
+            Err(e) => e.category().exit_code(),
+        }
+    }
+}
+
+/// Prints the error, if any, and exits with the code for its [`ExitCategory`].
+///
+/// ```rust,no_run
+/// # use aoc_lib::{Day, TracingAlloc};
+/// # const DAYS: &[Day] = &[];
+/// #[global_allocator]
+/// static ALLOC: TracingAlloc = TracingAlloc;
+///
+/// fn main() {
+///     aoc_lib::main_helper(aoc_lib::run(&ALLOC, 2021, DAYS));
+/// }
+/// ```
+pub fn main_helper(result: BenchResult) -> ! {
+    if let Err(e) = &result {
+        eprintln!("Error: {}", e);
+    }
+
+    process::exit(result.into_exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{bench::FunctionSummary, check_outcome};
+
+    fn summary(part: u8, mean_ms: u64) -> FunctionSummary {
+        FunctionSummary {
+            day: 3,
+            name: "Mock",
+            part: Some(part),
+            label: format!("3.{}", part),
+            mean: Some(Duration::from_millis(mean_ms)),
+            max_sample: None,
+            is_error: false,
+            is_missing_input: false,
+            input: None,
+        }
+    }
+
+    fn exit_code(results: &[FunctionSummary]) -> i32 {
+        check_outcome(results, Some(Duration::from_millis(250))).into_exit_code()
+    }
+
+    #[test]
+    fn all_ok() {
+        assert_eq!(exit_code(&[summary(1, 10), summary(2, 250)]), 0);
+    }
+
+    #[test]
+    fn part_error() {
+        let mut failed = summary(2, 10);
+        failed.is_error = true;
+        assert_eq!(exit_code(&[summary(1, 10), failed]), 1);
+    }
+
+    #[test]
+    fn missing_input() {
+        // The other functions failing too doesn't hide that the results are incomplete.
+        let mut missing = summary(1, 10);
+        missing.is_error = true;
+        missing.is_missing_input = true;
+        let mut failed = summary(2, 10);
+        failed.is_error = true;
+        assert_eq!(exit_code(&[missing, failed]), 2);
+    }
+
+    #[test]
+    fn too_slow() {
+        assert_eq!(exit_code(&[summary(1, 10), summary(2, 251)]), 3);
+
+        // Without --fail-above it's fine.
+        let results = [summary(1, 10), summary(2, 251)];
+        assert_eq!(check_outcome(&results, None).into_exit_code(), 0);
+    }
+
+    #[test]
+    fn internal_error() {
+        let result: BenchResult = Err(BenchError::ChannelError(0));
+        assert_eq!(result.into_exit_code(), 2);
+        assert_eq!(ExitCategory::Interrupted.exit_code(), 130);
+    }
+}
@@ -0,0 +1,184 @@
+//! Downloads missing inputs from adventofcode.com, with `--fetch-inputs`.
+//!
+//! The session token is read from the `AOC_SESSION` environment variable, or the `.session`
+//! file in the working directory. The request is made by `curl`, so it must be installed.
+//! Example inputs are never fetched, as they aren't published as files.
+
+use std::{
+    env, fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+use crate::{clock::Timestamp, output::write_atomic, BenchError};
+
+const SESSION_VAR: &str = "AOC_SESSION";
+const SESSION_FILE: &str = ".session";
+const USER_AGENT: &str = "github.com/Measter/aoc_lib";
+
+// Every function of a day opens its input, so a failed download is remembered to avoid asking
+// the server again for each one. Holding the lock also stops the functions downloading the
+// same input at once.
+static FAILED_FETCHES: Mutex<Vec<(u16, u8, String)>> = Mutex::new(Vec::new());
+
+fn read_session() -> Result<String, String> {
+    let (session, source) = match env::var(SESSION_VAR) {
+        Ok(session) => (session, SESSION_VAR),
+        Err(_) => {
+            let session = fs::read_to_string(SESSION_FILE).map_err(|_| {
+                format!(
+                    "no session token, set {} or save it in {}",
+                    SESSION_VAR, SESSION_FILE
+                )
+            })?;
+            (session, SESSION_FILE)
+        }
+    };
+
+    parse_session(&session)
+        .map(str::to_owned)
+        .map_err(|reason| format!("the session token in {} {}", source, reason))
+}
+
+// The token goes into curl's config unescaped, so anything but the hex digits the site hands
+// out is refused rather than risk it being read as more options.
+fn parse_session(session: &str) -> Result<&str, &'static str> {
+    let session = session.trim();
+    match session.strip_prefix("session=").unwrap_or(session) {
+        "" => Err("is empty"),
+        session
+            if !session
+                .bytes()
+                .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) =>
+        {
+            Err("isn't valid, it should only be the digits 0-9 and a-f")
+        }
+        session => Ok(session),
+    }
+}
+
+// Puzzles unlock at midnight EST, which is UTC-5 all December.
+fn release_time(year: u16, day: u8) -> Option<Timestamp> {
+    Timestamp::parse_rfc3339(&format!("{:04}-12-{:02}T00:00:00-05:00", year, day))
+}
+
+fn download(year: u16, day: u8) -> Result<String, String> {
+    // There's no need to ask the server about a puzzle that can't be out yet.
+    match release_time(year, day) {
+        Some(release) if Timestamp::now() >= release => {}
+        Some(release) => {
+            return Err(format!(
+                "day {} of {} isn't released until {}",
+                day, year, release
+            ))
+        }
+        None => return Err(format!("day {} of {} isn't a puzzle", day, year)),
+    }
+
+    let session = read_session()?;
+    let url = format!("https://adventofcode.com/{}/day/{}/input", year, day);
+
+    // The token is passed as curl's config on stdin so it isn't in the process list.
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--config", "-"])
+        .args(["--user-agent", USER_AGENT, "--write-out", "\n%{http_code}"])
+        .arg(&url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("unable to run curl: {}", e))?;
+
+    let config = format!("header = \"Cookie: session={}\"\n", session);
+    child
+        .stdin
+        .take()
+        .expect("curl stdin not piped")
+        .write_all(config.as_bytes())
+        .map_err(|e| format!("unable to run curl: {}", e))?;
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("unable to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+
+    let output = String::from_utf8(output.stdout).map_err(|_| "input isn't UTF-8".to_owned())?;
+    let (body, status) = output.rsplit_once('\n').unwrap_or(("", &output));
+    match status {
+        "200" => Ok(body.to_owned()),
+        "404" => Err(format!("day {} of {} isn't released yet", day, year)),
+        // A bad or expired token gets a 400, with a message asking to log in.
+        _ => Err(format!("HTTP {}: {}", status, body.trim())),
+    }
+}
+
+/// Downloads the year's input for the day to the path, unless it's already there.
+pub(crate) fn fetch_input(year: u16, day: u8, path: &str) -> Result<(), BenchError> {
+    let to_error = |reason: &str| BenchError::InputFetchError {
+        name: path.to_owned(),
+        reason: reason.to_owned(),
+    };
+
+    let mut failed = FAILED_FETCHES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((.., reason)) = failed.iter().find(|f| (f.0, f.1) == (year, day)) {
+        return Err(to_error(reason));
+    }
+
+    // Another function of the day may have downloaded it while we waited for the lock.
+    let path = Path::new(path);
+    if path.exists() {
+        return Ok(());
+    }
+
+    let input = match download(year, day) {
+        Ok(input) => input,
+        Err(reason) => {
+            let err = to_error(&reason);
+            failed.push((year, day, reason));
+            return Err(err);
+        }
+    };
+
+    // Reported as a fetch error, as it's still the input that's missing.
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| to_error(&format!("unable to save it: {}", e)))?;
+    }
+    write_atomic(path, input.as_bytes()).map_err(|e| to_error(&format!("unable to save it: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_tokens() {
+        assert_eq!(parse_session("53616c7465645f5f\n"), Ok("53616c7465645f5f"));
+        assert_eq!(parse_session("session=0123abcdef"), Ok("0123abcdef"));
+        assert_eq!(parse_session("  \n"), Err("is empty"));
+        assert_eq!(parse_session("session="), Err("is empty"));
+
+        // Anything that could escape curl's quoted header is refused.
+        for token in [
+            "abc\"def", "abc\\def", "abc\ndef", "ABCDEF", "abc def", "xyz",
+        ] {
+            assert!(parse_session(token).is_err(), "{:?} was accepted", token);
+        }
+    }
+
+    #[test]
+    fn release_times() {
+        assert_eq!(
+            release_time(2023, 1).unwrap().rfc3339(),
+            "2023-12-01T05:00:00Z"
+        );
+        assert_eq!(
+            release_time(2023, 25).unwrap().rfc3339(),
+            "2023-12-25T05:00:00Z"
+        );
+        assert!(release_time(2023, 32).is_none());
+    }
+}
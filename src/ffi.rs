@@ -0,0 +1,308 @@
+//! Registering solutions written in other languages, through a C-compatible signature.
+//!
+//! A solution in C looks like this:
+//!
+//! ```c
+//! #include <stddef.h>
+//! #include <stdint.h>
+//! #include <stdio.h>
+//!
+//! int32_t day01_part1(const uint8_t *input, size_t input_len,
+//!                     uint8_t *answer, size_t answer_capacity, size_t *answer_len) {
+//!     size_t lines = 0;
+//!     for (size_t i = 0; i < input_len; i++) {
+//!         lines += input[i] == '\n';
+//!     }
+//!
+//!     // snprintf returns the full length even if it doesn't fit, so truncation is reported.
+//!     int written = snprintf((char *)answer, answer_capacity, "%zu", lines);
+//!     if (written < 0) {
+//!         return 1;
+//!     }
+//!     *answer_len = (size_t)written;
+//!     return 0;
+//! }
+//! ```
+//!
+//! And is registered with [`foreign_part!`](crate::foreign_part):
+//!
+//! ```rust
+//! # use aoc_lib::{foreign_part, Bench, Day};
+//! # // Stands in for the C, so the example links.
+//! # mod c {
+//! #     #[no_mangle]
+//! #     unsafe extern "C" fn day01_part1(
+//! #         _: *const u8, _: usize, answer: *mut u8, _: usize, answer_len: *mut usize,
+//! #     ) -> i32 {
+//! #         *answer = b'3';
+//! #         *answer_len = 1;
+//! #         0
+//! #     }
+//! # }
+//! extern "C" {
+//!     fn day01_part1(
+//!         input: *const u8,
+//!         input_len: usize,
+//!         answer: *mut u8,
+//!         answer_capacity: usize,
+//!         answer_len: *mut usize,
+//!     ) -> i32;
+//! }
+//!
+//! pub const DAY: Day = Day::new(1, "Sonar Sweep", foreign_part!(unsafe day01_part1));
+//! # let (bench, sink) = Bench::test_harness();
+//! # (DAY.part_1)("1\n2\n3\n", bench).unwrap();
+//! # assert_eq!(sink.answer(), "3");
+//! ```
+//!
+//! Only allocations made from Rust are traced, so the memory use of foreign solutions is
+//! usually under-reported.
+
+use std::{fmt::Display, str};
+
+use crate::{Bench, BenchResult};
+
+/// The most bytes of an answer that are kept.
+pub const ANSWER_CAPACITY: usize = 1024;
+
+/// A solution with a C-compatible signature.
+///
+/// It's given the input, and must write the answer as UTF-8 into the buffer, storing its
+/// full length in `answer_len`, even if it didn't fit. A status of zero means the solution
+/// succeeded, anything else is reported as an error.
+///
+/// The function must not unwind, and must only write within the answer buffer's capacity.
+pub type ForeignSolution = unsafe extern "C" fn(
+    input: *const u8,
+    input_len: usize,
+    answer: *mut u8,
+    answer_capacity: usize,
+    answer_len: *mut usize,
+) -> i32;
+
+#[derive(Debug, Clone, Copy)]
+enum ForeignError {
+    Status(i32),
+    InvalidUtf8,
+}
+
+impl Display for ForeignError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForeignError::Status(code) => write!(f, "Foreign solution failed with status {}", code),
+            ForeignError::InvalidUtf8 => f.write_str("Foreign solution's answer isn't UTF-8"),
+        }
+    }
+}
+
+fn call(function: ForeignSolution, input: &str) -> Result<String, ForeignError> {
+    let mut answer = [0; ANSWER_CAPACITY];
+    let mut answer_len = 0;
+
+    // SAFETY: The pointers are valid for the given lengths, and `bench_foreign`'s caller
+    // promised the function keeps within them.
+    let status = unsafe {
+        function(
+            input.as_ptr(),
+            input.len(),
+            answer.as_mut_ptr(),
+            answer.len(),
+            &mut answer_len,
+        )
+    };
+    if status != 0 {
+        return Err(ForeignError::Status(status));
+    }
+
+    if answer_len > ANSWER_CAPACITY {
+        // The cut could have been in the middle of a character, and C's string functions end
+        // what they kept with a NUL.
+        let kept = match str::from_utf8(&answer) {
+            Ok(kept) => kept,
+            Err(e) => str::from_utf8(&answer[..e.valid_up_to()]).unwrap_or_default(),
+        };
+        let kept = kept.trim_end_matches('\0');
+        return Ok(format!("{}… (truncated from {} bytes)", kept, answer_len));
+    }
+
+    str::from_utf8(&answer[..answer_len])
+        .map(str::to_owned)
+        .map_err(|_| ForeignError::InvalidUtf8)
+}
+
+/// Benches a foreign solution like any other. Usually called through
+/// [`foreign_part!`](crate::foreign_part).
+///
+/// # Safety
+///
+/// The function must uphold the contract of [`ForeignSolution`].
+pub unsafe fn bench_foreign(function: ForeignSolution, input: &str, bench: Bench) -> BenchResult {
+    bench.report_foreign()?;
+    bench.bench(move || call(function, input))
+}
+
+/// Wraps a [`ForeignSolution`](crate::ffi::ForeignSolution) into a function that can be
+/// registered in a [`Day`](crate::Day). Requires the `ffi` feature.
+///
+/// The `unsafe` is an assertion that the function upholds the contract of `ForeignSolution`.
+///
+/// ```rust
+/// # use aoc_lib::{foreign_part, Day};
+/// # unsafe extern "C" fn day01_part1(
+/// #     _: *const u8, _: usize, _: *mut u8, _: usize, answer_len: *mut usize,
+/// # ) -> i32 {
+/// #     *answer_len = 0;
+/// #     0
+/// # }
+/// pub const DAY: Day = Day::new(1, "Sonar Sweep", foreign_part!(unsafe day01_part1));
+/// ```
+#[macro_export]
+macro_rules! foreign_part {
+    (unsafe $function:path) => {{
+        fn foreign(input: &str, bench: $crate::Bench) -> $crate::BenchResult {
+            // SAFETY: The caller of the macro asserted the function upholds the contract.
+            unsafe { $crate::ffi::bench_foreign($function, input, bench) }
+        }
+        foreign as for<'a> fn(&'a str, $crate::Bench) -> $crate::BenchResult
+    }};
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        env,
+        ffi::{c_void, CStr, CString},
+        fs,
+        process::Command,
+    };
+
+    use super::*;
+
+    // The example from the module's docs, and solutions that fail and overflow the answer.
+    const EXAMPLE: &str = r#"
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+
+int32_t day01_part1(const uint8_t *input, size_t input_len,
+                    uint8_t *answer, size_t answer_capacity, size_t *answer_len) {
+    size_t lines = 0;
+    for (size_t i = 0; i < input_len; i++) {
+        lines += input[i] == '\n';
+    }
+
+    // snprintf returns the full length even if it doesn't fit, so truncation is reported.
+    int written = snprintf((char *)answer, answer_capacity, "%zu", lines);
+    if (written < 0) {
+        return 1;
+    }
+    *answer_len = (size_t)written;
+    return 0;
+}
+
+int32_t fails(const uint8_t *input, size_t input_len,
+              uint8_t *answer, size_t answer_capacity, size_t *answer_len) {
+    return 42;
+}
+
+char long_answer[4000];
+
+int32_t too_long(const uint8_t *input, size_t input_len,
+                 uint8_t *answer, size_t answer_capacity, size_t *answer_len) {
+    memset(long_answer, 'x', sizeof(long_answer) - 1);
+    long_answer[sizeof(long_answer) - 1] = '\0';
+    int written = snprintf((char *)answer, answer_capacity, "%s", long_answer);
+    *answer_len = (size_t)written;
+    return 0;
+}
+
+int32_t not_utf8(const uint8_t *input, size_t input_len,
+                 uint8_t *answer, size_t answer_capacity, size_t *answer_len) {
+    answer[0] = 0xff;
+    *answer_len = 1;
+    return 0;
+}
+"#;
+
+    struct Library(*mut c_void);
+
+    impl Library {
+        // Builds the example with the system's C compiler, or `$CC`.
+        fn compile(dir: &tempfile::TempDir) -> Self {
+            let source = dir.path().join("example.c");
+            let library = dir.path().join("libexample.so");
+            fs::write(&source, EXAMPLE).unwrap();
+
+            let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+            let status = Command::new(&compiler)
+                .args(["-shared", "-fPIC", "-o"])
+                .arg(&library)
+                .arg(&source)
+                .status()
+                .unwrap_or_else(|e| panic!("Unable to run {}: {}", compiler, e));
+            assert!(status.success(), "Compiling the C example failed");
+
+            let path = CString::new(library.to_str().unwrap()).unwrap();
+            // SAFETY: The path is a valid C string, and the library has no initialisers.
+            let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW) };
+            if handle.is_null() {
+                // SAFETY: dlerror returns a C string after a failed dlopen.
+                let err = unsafe { CStr::from_ptr(libc::dlerror()) };
+                panic!("Unable to load the C example: {:?}", err);
+            }
+            Self(handle)
+        }
+
+        fn solution(&self, name: &str) -> ForeignSolution {
+            let name = CString::new(name).unwrap();
+            // SAFETY: The handle is open, and the name is a valid C string.
+            let symbol = unsafe { libc::dlsym(self.0, name.as_ptr()) };
+            assert!(!symbol.is_null(), "{:?} isn't in the C example", name);
+            // SAFETY: Every function in the example has the ForeignSolution signature.
+            unsafe { std::mem::transmute::<*mut c_void, ForeignSolution>(symbol) }
+        }
+    }
+
+    impl Drop for Library {
+        fn drop(&mut self) {
+            // SAFETY: The handle is open, and nothing from it outlives the library.
+            unsafe { libc::dlclose(self.0) };
+        }
+    }
+
+    fn run(function: ForeignSolution, input: &str) -> Result<String, String> {
+        let (bench, sink) = Bench::test_harness();
+        // SAFETY: The example's functions keep within the answer buffer, and don't unwind.
+        unsafe { bench_foreign(function, input, bench) }.unwrap();
+        sink.result().expect("no answer or error reported")
+    }
+
+    #[test]
+    fn c_example() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = Library::compile(&dir);
+
+        assert_eq!(
+            run(library.solution("day01_part1"), "1\n2\n3\n"),
+            Ok("3".to_owned())
+        );
+        assert_eq!(run(library.solution("day01_part1"), ""), Ok("0".to_owned()));
+
+        assert_eq!(
+            run(library.solution("fails"), ""),
+            Err("Foreign solution failed with status 42".to_owned())
+        );
+        assert_eq!(
+            run(library.solution("not_utf8"), ""),
+            Err("Foreign solution's answer isn't UTF-8".to_owned())
+        );
+
+        // snprintf keeps as much as fits, ending it with a NUL, which isn't part of the answer.
+        let kept = "x".repeat(ANSWER_CAPACITY - 1);
+        assert_eq!(
+            run(library.solution("too_long"), ""),
+            Ok(format!("{}… (truncated from 3999 bytes)", kept))
+        );
+    }
+}
@@ -1,6 +1,27 @@
-use std::fmt::Display;
+use std::{
+    cmp::Ordering as CmpOrdering,
+    fmt::Display,
+    io,
+    path::Path,
+    sync::atomic::{AtomicU16, Ordering},
+};
 
-use crate::BenchError;
+use crate::{fetch, BenchError, ARGS, RUNNING_YEAR};
+
+// When multiple years are registered, each year's inputs are in their own directory,
+// e.g. `inputs/2021/day03.txt`. Zero means only one year is registered.
+static INPUT_YEAR: AtomicU16 = AtomicU16::new(0);
+
+pub(crate) fn set_input_year(year: Option<u16>) {
+    INPUT_YEAR.store(year.unwrap_or(0), Ordering::Relaxed);
+}
+
+fn year_dir() -> String {
+    match INPUT_YEAR.load(Ordering::Relaxed) {
+        0 => String::new(),
+        year => format!("{}/", year),
+    }
+}
 
 pub struct ProblemInput;
 impl Display for ProblemInput {
@@ -30,9 +51,41 @@ impl Display for Example {
     }
 }
 
+/// An example chosen on the command line with `--example`, as `part:id`.
+#[derive(Debug, Clone)]
+pub(crate) struct ExampleSelection {
+    part: Example,
+    id: String,
+}
+
+pub(crate) fn parse_example_arg(src: &str) -> Result<ExampleSelection, String> {
+    let (part, id) = src
+        .split_once(':')
+        .ok_or_else(|| format!("expected part:id, e.g. part1:1, got '{}'", src))?;
+    if id.is_empty() {
+        return Err(format!("missing example id in '{}'", src));
+    }
+
+    let part = match part {
+        "parse" => Example::Parse,
+        "part1" => Example::Part1,
+        "part2" => Example::Part2,
+        "" => return Err(format!("missing example part in '{}'", src)),
+        // Arguments are only parsed once, so leaking the name costs nothing.
+        other => Example::Other(Box::leak(other.to_owned().into_boxed_str())),
+    };
+
+    Ok(ExampleSelection {
+        part,
+        id: id.to_owned(),
+    })
+}
+
 pub struct InputFile<T> {
     day: u8,
     example_id: Option<(Example, T)>,
+    /// Another input for the day, such as a friend's, in `dayNN_{variant}.txt`.
+    variant: Option<String>,
 }
 
 impl InputFile<ProblemInput> {
@@ -40,28 +93,177 @@ impl InputFile<ProblemInput> {
         InputFile {
             day: self.day,
             example_id: Some((part, id)),
+            variant: None,
         }
     }
+
+    /// Opens the variant's input, `dayNN_{variant}.txt`, instead of the day's own.
+    pub fn variant(self, variant: impl Into<String>) -> Self {
+        Self {
+            variant: Some(variant.into()),
+            ..self
+        }
+    }
+
+    /// Lists the ids and contents of every example input for the day's part, sorted by id.
+    /// Numeric ids are sorted numerically, and before any others.
+    pub fn examples(day: u8, part: Example) -> Result<Vec<(String, String)>, BenchError> {
+        let dir = format!("./example_inputs/{}", year_dir());
+        let prefix = format!("day{:02}_{}-", day, part);
+
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            // No examples have been saved yet.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(BenchError::InputDirError {
+                    inner: e,
+                    name: dir,
+                })
+            }
+        };
+
+        let mut ids = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| BenchError::InputDirError {
+                inner: e,
+                name: dir.clone(),
+            })?;
+
+            let file_name = entry.file_name();
+            let Some(id) = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|name| name.strip_suffix(".txt"))
+            else {
+                continue;
+            };
+            ids.push(id.to_owned());
+        }
+
+        ids.sort_by(|a, b| compare_ids(a, b));
+
+        ids.into_iter()
+            .map(|id| {
+                let contents = input(day).example(part, &id).open()?;
+                Ok((id, contents))
+            })
+            .collect()
+    }
+}
+
+fn compare_ids(a: &str, b: &str) -> CmpOrdering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => CmpOrdering::Less,
+        (Err(_), Ok(_)) => CmpOrdering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
 }
 
 impl<T: Display> InputFile<T> {
-    pub fn open(self) -> Result<String, BenchError> {
-        let path = if let Some((part, id)) = self.example_id {
-            format!("./example_inputs/day{:02}_{}-{}.txt", self.day, part, id)
+    pub(crate) fn path(&self) -> String {
+        if let Some((part, id)) = &self.example_id {
+            format!(
+                "./example_inputs/{}day{:02}_{}-{}.txt",
+                year_dir(),
+                self.day,
+                part,
+                id
+            )
+        } else if let Some(variant) = &self.variant {
+            format!("./inputs/{}day{:02}_{}.txt", year_dir(), self.day, variant)
         } else {
-            format!("./inputs/day{:02}.txt", self.day)
-        };
+            format!("./inputs/{}day{:02}.txt", year_dir(), self.day)
+        }
+    }
+
+    /// Whether it's the day's own input, rather than an example or a variant.
+    pub(crate) fn is_default(&self) -> bool {
+        self.example_id.is_none() && self.variant.is_none()
+    }
+
+    pub(crate) fn exists(&self) -> bool {
+        Path::new(&self.path()).exists()
+    }
+
+    /// Opens the file. With `--fetch-inputs`, a missing real input is downloaded first.
+    pub fn open(self) -> Result<String, BenchError> {
+        let path = self.path();
+        let year = RUNNING_YEAR.load(Ordering::Relaxed);
+        if ARGS.fetch_inputs && self.is_default() && year != 0 && !Path::new(&path).exists() {
+            fetch::fetch_input(year, self.day, &path)?;
+        }
 
         std::fs::read_to_string(&path).map_err(|e| BenchError::InputFileError {
             inner: e,
             name: path,
         })
     }
+
+    /// Opens the file, or returns the default if it doesn't exist. Useful for keeping the
+    /// small published examples in the code.
+    pub fn open_or(self, default: &str) -> Result<String, BenchError> {
+        let path = self.path();
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Ok(contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(default.to_owned()),
+            Err(e) => Err(BenchError::InputFileError {
+                inner: e,
+                name: path,
+            }),
+        }
+    }
+}
+
+/// Describes the error for the function's row, if it's one from opening the input.
+pub(crate) fn describe_input_error(err: &BenchError) -> Option<String> {
+    match err {
+        BenchError::InputFileError { inner, name } => Some(format!("{}: {:?}", name, inner.kind())),
+        BenchError::InputFetchError { name, reason } => Some(format!("{}: {}", name, reason)),
+        _ => None,
+    }
 }
 
 pub fn input(day: u8) -> InputFile<ProblemInput> {
     InputFile {
         day,
         example_id: None,
+        variant: None,
     }
 }
+
+/// The input the day's functions are given: the one chosen with `--example`, or the real one,
+/// or its variant.
+pub(crate) fn day_input(day: u8, variant: Option<&str>) -> InputFile<String> {
+    InputFile {
+        day,
+        example_id: ARGS
+            .example
+            .as_ref()
+            .map(|example| (example.part, example.id.clone())),
+        variant: variant.map(str::to_owned),
+    }
+}
+
+/// The names of the day's input variants, the `alt1` of `day07_alt1.txt`, sorted like example
+/// ids. An unreadable inputs directory has none.
+pub(crate) fn input_variants(day: u8) -> Vec<String> {
+    let prefix = format!("day{:02}_", day);
+    let Ok(entries) = std::fs::read_dir(format!("./inputs/{}", year_dir())) else {
+        return Vec::new();
+    };
+
+    let mut variants: Vec<_> = entries
+        .filter_map(|entry| {
+            let file_name = entry.ok()?.file_name();
+            let variant = file_name
+                .to_str()?
+                .strip_prefix(&prefix)?
+                .strip_suffix(".txt")?;
+            Some(variant.to_owned())
+        })
+        .collect();
+    variants.sort_by(|a, b| compare_ids(a, b));
+    variants
+}
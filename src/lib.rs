@@ -1,32 +1,181 @@
-use std::{fmt::Display, iter, num::ParseIntError, time::Duration};
+#![cfg_attr(feature = "capture-output", feature(internal_output_capture))]
 
-use console::{style, Term};
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    iter,
+    num::ParseIntError,
+    panic,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicU16, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use console::Color;
 use crossbeam_channel::Receiver;
 use once_cell::sync::Lazy;
 use structopt::StructOpt;
 use thiserror::Error;
 
 mod alloc;
+mod badge;
+mod baseline;
 mod bench;
+mod capture;
+mod clock;
+mod collect;
+mod compare;
+mod digest;
+mod dry_run;
+mod exit;
+mod fetch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 mod input;
+mod macros;
+mod markdown;
 pub mod misc;
+mod output;
+pub mod parsers;
+mod plan;
+mod platform;
+mod provenance;
+mod records;
+pub mod scaffold;
+mod select;
+mod self_test;
+mod speedup;
 
 pub use alloc::TracingAlloc;
-pub use bench::Bench;
 use bench::{
-    simple::run_simple_bench, AlternateAnswer, BenchEvent, MemoryBenchError, SetupFunction,
+    bench_worker, run_function,
+    simple::{bench_simple as run_simple_bench_results, print_captured_output, run_simple_bench},
+    AlternateAnswer, BenchEvent, FunctionSummary, MemoryBenchError, OutlierFilter, SampleMode,
+    SetupFunction,
 };
+pub use bench::{Bench, MemoryData, Metrics, RegionStats, RuntimeData, TestSink};
+pub use collect::{run_collect, Collector, DayResult};
+pub use exit::{main_helper, ExitCategory, IntoExitCode};
 pub use input::*;
+pub use provenance::InputProvenance;
+pub use self_test::ExampleSpec;
+
+use crate::{
+    bench::detailed::run_detailed_bench,
+    output::{ColorChoice, ProgressStream, OUTPUT},
+    plan::{day_functions, PARSE_NAME},
+};
 
-use crate::bench::detailed::run_detailed_bench;
+// The test harness's arguments aren't ours, so the tests run with the defaults.
+static ARGS: Lazy<Args> = Lazy::new(|| {
+    if cfg!(test) {
+        Args::from_iter(["aoc_lib", "run"])
+    } else {
+        Args::parse()
+    }
+});
 
-static ARGS: Lazy<Args> = Lazy::new(Args::from_args);
+// The unit tests trace their own allocations, like a crate using the library would.
+#[cfg(test)]
+#[global_allocator]
+static TEST_ALLOC: TracingAlloc = TracingAlloc;
 
 pub type BenchResult = Result<(), BenchError>;
 
+// The ticker and a day column wide enough for `NN.N`, plus the separator.
 const TABLE_PRE_COL_WIDTH: usize = 9;
-// The amount of space taken up by the ticker, day ID, and bench data columns, plus separators.
-const TABLE_DETAILED_COLS_WIDTH: usize = TABLE_PRE_COL_WIDTH + 46;
+const MIN_DAY_COL_WIDTH: usize = 4;
+// The amount of space taken up by the bench data columns, plus separators.
+const TABLE_DETAILED_COLS_WIDTH: usize = 47;
+// The extra total allocated and worst sample columns in the `--wide` table.
+const TABLE_WIDE_COL_WIDTH: usize = 22;
+
+// The `--sparkline` of the memory use, plus its separator.
+pub(crate) const SPARKLINE_WIDTH: usize = 10;
+const TABLE_SPARKLINE_COL_WIDTH: usize = SPARKLINE_WIDTH + 3;
+
+// The change from the baseline, plus its separator. Being the last column, it can overflow.
+const TABLE_BASELINE_COL_WIDTH: usize = 11;
+
+const ALT_ANSWER_RULE: &str = "--------------------";
+
+// The single-sample time column in run mode, plus its separator.
+const TABLE_RUN_TIME_COL_WIDTH: usize = 14;
+
+// Other functions are labelled by name, so the day column grows to fit them.
+static DAY_COL_WIDTH: AtomicUsize = AtomicUsize::new(MIN_DAY_COL_WIDTH);
+
+/// Widens the day column of the table to fit the longest of the labels.
+pub(crate) fn fit_day_col(labels: impl IntoIterator<Item = impl AsRef<str>>) {
+    let width = labels
+        .into_iter()
+        .map(|label| label.as_ref().chars().count())
+        .fold(MIN_DAY_COL_WIDTH, usize::max);
+    DAY_COL_WIDTH.store(width, Ordering::Relaxed);
+}
+
+fn day_col_width() -> usize {
+    DAY_COL_WIDTH.load(Ordering::Relaxed)
+}
+
+/// Labels a function's row in the table, e.g. ` 1.2` or `12.simd`.
+pub(crate) fn function_row_label(day: u8, function: &str) -> String {
+    format!("{:>2}.{}", day, function)
+}
+
+// Pads the label to the width of the day column.
+pub(crate) fn pad_day_col(label: &str) -> String {
+    format!("{:<width$}", label, width = day_col_width())
+}
+
+// Spaces in place of the ticker and day column, for lines continuing a row.
+pub(crate) fn blank_pre_col() -> String {
+    format!("{:width$} |", "", width = day_col_width() + 2)
+}
+
+pub(crate) fn table_pre_col_width() -> usize {
+    TABLE_PRE_COL_WIDTH - MIN_DAY_COL_WIDTH + day_col_width()
+}
+
+fn day_col_rule() -> String {
+    "_".repeat(table_pre_col_width() - 2)
+}
+
+fn table_cols_width() -> usize {
+    let mut width = table_pre_col_width() + TABLE_DETAILED_COLS_WIDTH;
+    if ARGS.wide {
+        width += TABLE_WIDE_COL_WIDTH;
+    }
+    if ARGS.sparkline {
+        width += TABLE_SPARKLINE_COL_WIDTH;
+    }
+    if ARGS.baseline.is_some() {
+        width += TABLE_BASELINE_COL_WIDTH;
+    }
+    width
+}
+
+// The extra columns after the memory, as their titles and rule.
+fn render_extra_cols() -> (String, String) {
+    let (mut titles, mut rule) = if ARGS.wide {
+        (
+            format!(" | Total Alloc | {:<6}", "Worst"),
+            "_|_____________|________".to_owned(),
+        )
+    } else {
+        (String::new(), "_".to_owned())
+    };
+    if ARGS.sparkline {
+        titles.push_str(&format!(" | {:<width$}", "Memory", width = SPARKLINE_WIDTH));
+        rule.push_str(&format!("|{:_<width$}", "", width = SPARKLINE_WIDTH + 2));
+    }
+    if ARGS.baseline.is_some() {
+        titles.push_str(" | Baseline");
+        rule.push_str("|__________");
+    }
+    (titles, rule)
+}
 
 #[derive(Debug, Error)]
 pub enum BenchError {
@@ -36,6 +185,9 @@ pub enum BenchError {
     #[error("Error returning benchmark result for function {}", .0)]
     ChannelError(usize),
 
+    #[error("The results handler thread panicked, losing the results")]
+    ResultsLost,
+
     #[error("Error opening input file '{}': {:}", .name, .inner)]
     InputFileError {
         #[source]
@@ -43,11 +195,86 @@ pub enum BenchError {
         name: String,
     },
 
+    #[error("Error fetching input file '{}': {}", .name, .reason)]
+    InputFetchError { name: String, reason: String },
+
+    #[error("Error reading input directory '{}': {:}", .name, .inner)]
+    InputDirError {
+        #[source]
+        inner: std::io::Error,
+        name: String,
+    },
+
+    #[error("Error writing output file '{}': {:}", .name, .inner)]
+    OutputFileError {
+        #[source]
+        inner: std::io::Error,
+        name: String,
+    },
+
+    #[error("Error reading saved results '{}': {:}", .name, .inner)]
+    SavedResultsError {
+        #[source]
+        inner: std::io::Error,
+        name: String,
+    },
+
+    #[error("Error reading imported results '{}': {:}", .name, .inner)]
+    ImportError {
+        #[source]
+        inner: serde_json::Error,
+        name: String,
+    },
+
+    #[error("No baseline named '{}', save one with --save-baseline", .0)]
+    MissingBaseline(String),
+
+    #[error("Baseline '{}' in '{}' is corrupt, save it again with --save-baseline: {}", .name, .path, .inner)]
+    CorruptBaseline {
+        #[source]
+        inner: serde_json::Error,
+        name: String,
+        path: String,
+    },
+
     #[error("{}", .0)]
     UserError(#[source] Box<dyn std::error::Error + Send + Sync>),
 
-    #[error("Day {} not defined", .0)]
-    DaysFilterError(u8),
+    #[error("Day {} not defined for {}", .0, .1)]
+    DaysFilterError(u8, u16),
+
+    #[error("Part {} of day {} not defined for {}", .0, .1, .2)]
+    PartFilterError(String, u8, u16),
+
+    #[error("Year {} not defined", .0)]
+    YearFilterError(u16),
+
+    #[error("Day {} has no parse function", .0)]
+    NoParseFunction(u8),
+
+    #[error("{} registration change(s) since the digest", .0)]
+    RegistrationMismatch(usize),
+
+    #[error("{} problem(s) found by the dry run", .0)]
+    DryRunFailed(usize),
+
+    #[error("--select needs an interactive terminal to pick the days")]
+    NotInteractive,
+
+    #[error("Refusing to create the new year in '{}', as it isn't empty", .0)]
+    ScaffoldTargetNotEmpty(String),
+
+    #[error("{} day(s) could not be run due to missing inputs", .missing_inputs.len())]
+    PartialFailure { missing_inputs: Vec<u8> },
+
+    #[error("{} function(s) failed, {} part(s) too slow", .errored.len(), .too_slow.len())]
+    FailedFunctions {
+        errored: Vec<String>,
+        too_slow: Vec<String>,
+    },
+
+    #[error("{} example(s) failed", .0.len())]
+    FailedExamples(Vec<String>),
 }
 
 #[allow(non_snake_case)]
@@ -67,27 +294,230 @@ impl<T> Display for ParseResult<T> {
 
 // Getting an inexplicable compiler error if I just try let structopt handle a the
 // Option<Vec<u8>>, so I'm using this as a workaround.
-fn parse_days_list(src: &str) -> Result<u8, ParseIntError> {
+fn parse_days_list(src: &str) -> Result<DaySelector, String> {
     src.parse()
 }
 
+/// A day given on the command line, optionally narrowed down to one of its functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DaySelector {
+    day: u8,
+    part: Option<PartSelector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PartSelector {
+    Part(u8),
+    /// One of the day's other functions, by its registered name.
+    Other(String),
+}
+
+impl FromStr for DaySelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (day, part) = match s.split_once('.') {
+            Some((day, part)) => (day, Some(part)),
+            None => (s, None),
+        };
+
+        let day = day
+            .parse()
+            .map_err(|e: ParseIntError| format!("invalid day '{}': {}", day, e))?;
+        let part = match part {
+            None => None,
+            Some("") => return Err(format!("missing part after '{}.'", day)),
+            Some(part) => Some(
+                part.parse()
+                    .map_or_else(|_| PartSelector::Other(part.to_owned()), PartSelector::Part),
+            ),
+        };
+
+        Ok(Self { day, part })
+    }
+}
+
+impl DaySelector {
+    fn matches(&self, day: u8, part: Option<u8>, name: &str) -> bool {
+        self.day == day
+            && match &self.part {
+                None => true,
+                Some(PartSelector::Part(selected)) => part == Some(*selected),
+                Some(PartSelector::Other(selected)) => part.is_none() && name == selected,
+            }
+    }
+}
+
+/// Whether the function was selected by the days filter. Parts are numbered, while other
+/// functions go by their name.
+pub(crate) fn is_function_selected(day: u8, part: Option<u8>, name: &str) -> bool {
+    let filter = days_filter();
+    filter.is_empty() || filter.iter().any(|s| s.matches(day, part, name))
+}
+
+// Accepts a plain number of seconds, or a number with an `ms`, `s`, `m` or `h` suffix.
+fn parse_duration_arg(src: &str) -> Result<Duration, String> {
+    let (number, scale_ms) = if let Some(number) = src.strip_suffix("ms") {
+        (number, 1)
+    } else {
+        match src.as_bytes().last() {
+            Some(b's') => (&src[..src.len() - 1], 1000),
+            Some(b'm') => (&src[..src.len() - 1], 60 * 1000),
+            Some(b'h') => (&src[..src.len() - 1], 60 * 60 * 1000),
+            _ => (src, 1000),
+        }
+    };
+
+    number
+        .parse::<u64>()
+        .map(|n| Duration::from_millis(n * scale_ms))
+        .map_err(|e| format!("invalid duration '{}': {}", src, e))
+}
+
+fn parse_badge_thresholds(src: &str) -> Result<(Duration, Duration), String> {
+    let (green, yellow) = src
+        .split_once(',')
+        .ok_or_else(|| format!("expected two comma-separated durations, got '{}'", src))?;
+    Ok((parse_duration_arg(green)?, parse_duration_arg(yellow)?))
+}
+
+// The standard deviation needs at least two samples to mean anything.
+fn parse_iterations(src: &str) -> Result<usize, String> {
+    match src.parse::<usize>() {
+        Ok(count) if count >= 2 => Ok(count),
+        Ok(count) => Err(format!("at least 2 iterations are needed, got {}", count)),
+        Err(e) => Err(format!("invalid iteration count '{}': {}", src, e)),
+    }
+}
+
+// Accepts a number of bytes, optionally with a binary `K`, `M` or `G` suffix.
+fn parse_size_arg(src: &str) -> Result<usize, String> {
+    let (number, scale) = match src.as_bytes().last() {
+        Some(b'K' | b'k') => (&src[..src.len() - 1], 1 << 10),
+        Some(b'M' | b'm') => (&src[..src.len() - 1], 1 << 20),
+        Some(b'G' | b'g') => (&src[..src.len() - 1], 1 << 30),
+        _ => (src, 1),
+    };
+
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(scale))
+        .ok_or_else(|| format!("invalid size '{}'", src))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum YearSelection {
+    All,
+    Year(u16),
+}
+
+impl FromStr for YearSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(YearSelection::All),
+            year => year
+                .parse()
+                .map(YearSelection::Year)
+                .map_err(|e| format!("invalid year '{}': {}", year, e)),
+        }
+    }
+}
+
+// The year currently being run, for labelling functions when running every year.
+static RUNNING_YEAR: AtomicU16 = AtomicU16::new(0);
+
+/// Labels the day, including the year if functions from several years share the output.
+pub(crate) fn day_label(day: u8) -> String {
+    match ARGS.year {
+        Some(YearSelection::All) => {
+            format!("{}-{:02}", RUNNING_YEAR.load(Ordering::Relaxed), day)
+        }
+        _ => day.to_string(),
+    }
+}
+
 #[derive(Clone, StructOpt, PartialEq, Eq)]
 pub(crate) enum RunType {
     /// Just runs the day's primary functions.
     Run {
         #[structopt(parse(try_from_str = parse_days_list))]
-        /// List of days to run [default: all]
-        days: Vec<u8>,
+        /// List of days to run, with `5.2` or `5.name` selecting a single part or other
+        /// function [default: all]
+        days: Vec<DaySelector>,
+
+        #[structopt(long)]
+        /// Only run each day's parse function, reporting its time and peak memory
+        parse_only: bool,
+
+        #[structopt(short, long)]
+        /// Only print the answers, one per line, without a table
+        quiet: bool,
     },
     /// Benchmarks the days' primary functions, and lists them in a simple format.
     Bench {
         #[structopt(parse(try_from_str = parse_days_list))]
-        /// List of days to run [default: all]
-        days: Vec<u8>,
+        /// List of days to run, with `5.2` or `5.name` selecting a single part or other
+        /// function [default: all]
+        days: Vec<DaySelector>,
 
         #[structopt(short)]
         /// Render more detailed benchmarking info.
         detailed: bool,
+
+        #[structopt(long)]
+        /// Check the days, inputs, output paths and options, and print what would be benched,
+        /// without running anything
+        dry_run: bool,
+    },
+    /// Benchmarks the days' primary functions, and compares them with results from criterion.
+    Compare {
+        #[structopt(parse(try_from_str = parse_days_list))]
+        /// List of days to run, with `5.2` or `5.name` selecting a single part or other
+        /// function [default: all]
+        days: Vec<DaySelector>,
+
+        #[structopt(long, parse(from_os_str))]
+        /// Criterion's output directory, usually target/criterion
+        criterion_dir: PathBuf,
+
+        #[structopt(long, default_value = "day{day}/part{part}")]
+        /// How criterion benchmark names map to days and parts, using {day} and {part}
+        /// for the numbers. Names include the group, separated by `/`
+        name_pattern: String,
+    },
+    /// Checks the days' functions against the answers to their registered examples.
+    Test {
+        #[structopt(parse(try_from_str = parse_days_list))]
+        /// List of days to check, with `5.2` or `5.name` selecting a single part or other
+        /// function [default: all]
+        days: Vec<DaySelector>,
+    },
+    /// Lists the personal records saved in the --records-file.
+    Records,
+    /// Creates the skeleton of a solutions crate for a new year.
+    NewYear {
+        // Named apart from the global --year option, which clap would otherwise clash with.
+        #[structopt(name = "new-year", value_name = "YEAR")]
+        /// The year to solve
+        year: u16,
+
+        #[structopt(parse(from_os_str))]
+        /// Where to create the crate, which must be empty or not exist [default: aoc_<year>]
+        path: Option<PathBuf>,
+
+        #[structopt(long, parse(from_os_str))]
+        /// A directory of files to copy instead of the built-in template, with {{year}}
+        /// replaced by the year in paths and contents
+        template: Option<PathBuf>,
+    },
+    /// Compares the registered functions with a digest saved by --registration-digest.
+    DiffRegistrations {
+        #[structopt(parse(from_os_str))]
+        /// The saved digest
+        old_digest: PathBuf,
     },
 }
 
@@ -96,9 +526,25 @@ impl RunType {
         matches!(self, RunType::Run { .. })
     }
 
-    fn days(&self) -> &[u8] {
+    fn is_quiet(&self) -> bool {
+        // The parse timings are the whole point of --parse-only, so it takes priority.
+        matches!(
+            self,
+            RunType::Run {
+                quiet: true,
+                parse_only: false,
+                ..
+            }
+        )
+    }
+
+    fn days(&self) -> &[DaySelector] {
         match self {
-            RunType::Run { days } | RunType::Bench { days, .. } => days,
+            RunType::Run { days, .. }
+            | RunType::Bench { days, .. }
+            | RunType::Compare { days, .. }
+            | RunType::Test { days } => days,
+            RunType::Records | RunType::NewYear { .. } | RunType::DiffRegistrations { .. } => &[],
         }
     }
 }
@@ -106,45 +552,477 @@ impl RunType {
 #[derive(StructOpt)]
 pub(crate) struct Args {
     #[structopt(subcommand)]
-    // Selects how to run the days
-    run_type: RunType,
+    // Selects how to run the days. Only optional for --registration-digest.
+    run_type: Option<RunType>,
+
+    #[structopt(long)]
+    /// Print a sorted list of every registered function, with a hash, and exit
+    registration_digest: bool,
 
     #[structopt(long, default_value = "3")]
     /// Benchmarking period in seconds to measure run time of parts
     bench_time: u64,
 
+    #[structopt(long, conflicts_with = "bench-time", parse(try_from_str = parse_iterations))]
+    /// Run exactly this many timing iterations per function, instead of sampling for a time
+    iterations: Option<usize>,
+
+    #[structopt(long, default_value = "10", parse(try_from_str = parse_iterations))]
+    /// Keep timing each function until it has at least this many samples, even past the bench
+    /// time
+    min_samples: usize,
+
+    #[structopt(long, default_value = "500ms", parse(try_from_str = parse_duration_arg))]
+    /// Run each function for this long before timing it, discarding the results, so the first
+    /// samples don't include cold caches (e.g. 0, 500ms, 2s)
+    warmup: Duration,
+
+    #[structopt(long, global = true)]
+    /// Which registered year to run, or `all` [default: the latest]
+    year: Option<YearSelection>,
+
+    #[structopt(long, global = true, parse(try_from_str = input::parse_example_arg))]
+    /// Give the day the example input `part:id` instead of its real input, e.g. part1:1 reads
+    /// example_inputs/dayNN_part1-1.txt. Only one day can be run
+    example: Option<ExampleSelection>,
+
+    #[structopt(long, global = true)]
+    /// Give the days the input variant inputs/dayNN_SUFFIX.txt instead of their own input, e.g.
+    /// a friend's. Days without it are skipped
+    input_suffix: Option<String>,
+
+    #[structopt(long, global = true, conflicts_with = "input-suffix")]
+    /// Bench each function with the day's own input and every variant of it, dayNN_*.txt, as
+    /// separate rows
+    all_inputs: bool,
+
     #[structopt(long = "threads")]
-    /// How many worker threads to spawn for benchmarking [default: cores - 2, min: 1]
+    /// How many worker threads to spawn for benchmarking [default: cores - 2]. 0 benches one
+    /// function at a time on a pinned core, only updating the progress between functions
     num_threads: Option<usize>,
 
     #[structopt(long)]
     censor: bool,
+
+    #[structopt(long, default_value = "64M", parse(try_from_str = parse_size_arg))]
+    /// Stack size of the threads running the solutions (e.g. 512K, 64M, 1G)
+    stack_size: usize,
+
+    #[structopt(long, default_value = "256M", parse(try_from_str = parse_size_arg))]
+    /// Most memory each thread's allocation trace may use. Past this the memory graph is cut
+    /// short, but the peak and allocation counts stay exact
+    trace_buffer_cap: usize,
+
+    #[structopt(long, default_value = "4M", parse(try_from_str = parse_size_arg))]
+    /// How much memory to set aside for each thread's allocation trace before tracing, so
+    /// allocation-heavy functions don't wait on the buffer growing
+    trace_reserve: usize,
+
+    #[structopt(long, default_value = "1")]
+    /// Only record every Nth allocation or free in the memory trace, for functions where
+    /// tracing is slow. The graph is coarser, but the peak and allocation counts stay exact
+    mem_sample_rate: usize,
+
+    #[structopt(long, default_value = "auto")]
+    /// Where to draw live progress: auto, stdout, stderr, or none
+    progress_stream: ProgressStream,
+
+    #[structopt(long, conflicts_with = "progress-stream")]
+    /// Act as if there's no terminal, for clean logs: no progress, no colour unless
+    /// --color always, and a width of 100 columns unless AOC_TERM_WIDTH is set
+    no_tty: bool,
+
+    #[structopt(long, default_value = "auto")]
+    /// When to use colours: auto, always, or never. Auto respects AOC_FORCE_STYLE, NO_COLOR and
+    /// TERM=dumb, and is never if stdout isn't a terminal. Without colour, slow times are marked
+    /// with * or !, and errors with [E]
+    color: ColorChoice,
+
+    #[structopt(long)]
+    /// Add a column for the total bytes allocated to the simple table
+    wide: bool,
+
+    #[structopt(long)]
+    /// Add a column to the simple table sketching each function's memory use over its run,
+    /// scaled to its peak
+    sparkline: bool,
+
+    #[structopt(long, default_value = "1")]
+    /// Run the whole benchmark this many times, and report the variation between runs.
+    /// Memory is only measured in the first run
+    repeat_runs: usize,
+
+    #[structopt(long, parse(from_os_str))]
+    /// Write the mean of every function from every repeated run to this CSV file. A
+    /// {timestamp} in the path is replaced by the time of the export, e.g. 20231216T093000Z
+    runs_export: Option<PathBuf>,
+
+    #[structopt(long)]
+    /// Show dates in local time, with the offset from UTC. Saved dates are always UTC
+    local_time: bool,
+
+    #[structopt(long, default_value = "iqr")]
+    /// Which timing samples are dropped as outliers: iqr drops those more than 1.5 times the
+    /// interquartile range beyond the quartiles, 2sigma those more than 2 standard deviations
+    /// from the mean, and none keeps every sample
+    outlier: OutlierFilter,
+
+    #[structopt(long)]
+    /// When no days are given, list the registered days and ask which to run
+    select: bool,
+
+    #[structopt(long)]
+    /// Lower the priority of the benchmark threads, so the machine stays usable
+    background: bool,
+
+    #[structopt(long)]
+    /// Pin each worker thread to its own physical core, keeping off the first two and any
+    /// efficiency cores, where supported
+    pin_threads: bool,
+
+    #[structopt(long, allow_hyphen_values = true)]
+    /// Set the nice value of the benchmark threads where supported, overriding --background.
+    /// Negative values usually need privileges
+    nice: Option<i32>,
+
+    #[structopt(long, parse(try_from_str = parse_duration_arg))]
+    /// Only bench as many functions as are estimated to fit in this time (e.g. 90s, 5m, 1h)
+    wall_budget: Option<Duration>,
+
+    #[structopt(long)]
+    /// Let solutions print straight to the terminal, instead of collecting what they print
+    /// while answering and showing it after the table. Capturing needs the `capture-output`
+    /// feature, which requires nightly
+    nocapture: bool,
+
+    #[structopt(long, parse(try_from_str = parse_duration_arg))]
+    /// Give up on a function if it hasn't produced its answer within this time (e.g. 60s, 5m).
+    /// Its thread can't be stopped, so it keeps running in the background until it next reports
+    /// to the bench, and may skew the measurements of others
+    timeout: Option<Duration>,
+
+    #[structopt(long, parse(from_os_str))]
+    /// Keep the speedups of parts over their reference implementations in this JSON file, so
+    /// they're still reported after a reference is deleted
+    speedup_file: Option<PathBuf>,
+
+    #[structopt(long, parse(from_os_str))]
+    /// Keep each function's best mean time and peak memory in this JSON file, and report when
+    /// they're beaten. Records are kept per input, and never set by debug builds or with
+    /// --background
+    records_file: Option<PathBuf>,
+
+    #[structopt(long, parse(try_from_str = baseline::parse_baseline_name))]
+    /// Save the mean times and allocation counts under this name in .aoc_baselines, replacing
+    /// those of the same functions
+    save_baseline: Option<String>,
+
+    #[structopt(long)]
+    /// Save a baseline with --background anyway, although its times aren't comparable
+    force: bool,
+
+    #[structopt(long, parse(try_from_str = baseline::parse_baseline_name))]
+    /// Show the change from the baseline saved under this name in the simple table
+    baseline: Option<String>,
+
+    #[structopt(long)]
+    /// Download missing inputs from adventofcode.com, using the session token in the
+    /// AOC_SESSION environment variable or the .session file
+    fetch_inputs: bool,
+
+    #[structopt(long, parse(from_os_str))]
+    /// Write an SVG badge showing the total time to this file
+    badge: Option<PathBuf>,
+
+    #[structopt(long, default_value = "AoC {year}")]
+    /// The badge's label, with {year} replaced by the year
+    badge_label: String,
+
+    #[structopt(long, default_value = "1s,10s", parse(try_from_str = parse_badge_thresholds))]
+    /// The badge is green below the first time, yellow below the second, and red otherwise
+    badge_thresholds: (Duration, Duration),
+
+    #[structopt(long, parse(from_os_str))]
+    /// Write each function's raw timing samples, before outliers are filtered, to
+    /// DIR/dayNN_partM.csv, one nanosecond count per line
+    dump_samples: Option<PathBuf>,
+
+    #[structopt(long, parse(from_os_str))]
+    /// Write a markdown table of the results to this file, replacing it, e.g. for a README
+    markdown: Option<PathBuf>,
+
+    #[structopt(long)]
+    /// Include the answers in the --markdown table
+    show_answers: bool,
+
+    #[structopt(long)]
+    /// After the table, print one line per day: `day DD: SS P1/P2 NAME`, then `total: N stars TIME`.
+    /// A star is earned by each part that produced an answer without error
+    summary_line: bool,
+
+    #[structopt(long)]
+    /// Use emoji stars in the summary line
+    emoji: bool,
+
+    #[structopt(long, parse(try_from_str = parse_duration_arg))]
+    /// Fail the run if any part's mean time is above this (e.g. 250ms, 1s). Failing functions
+    /// always fail the run
+    fail_above: Option<Duration>,
+
+    #[structopt(long)]
+    /// Record the answer of every timing iteration, and report any that differ
+    check_determinism: bool,
+
+    #[structopt(long, default_value = "8")]
+    /// Maximum number of distinct answers stored when checking determinism
+    max_distinct_answers: usize,
+
+    #[structopt(long)]
+    /// Fail a function if its first timing iteration's result differs from its answer, instead
+    /// of warning
+    strict: bool,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let args = Args::from_args();
+        if args.run_type.is_none() && !args.registration_digest {
+            structopt::clap::Error::with_description(
+                "A subcommand is required, see --help",
+                structopt::clap::ErrorKind::MissingSubcommand,
+            )
+            .exit();
+        }
+        if args.example.is_some() {
+            let days: HashSet<_> = args
+                .run_type
+                .as_ref()
+                .map_or(&[][..], RunType::days)
+                .iter()
+                .map(|selector| selector.day)
+                .collect();
+            if days.len() != 1 {
+                structopt::clap::Error::with_description(
+                    "--example needs exactly one day, as example ids are per day",
+                    structopt::clap::ErrorKind::ArgumentConflict,
+                )
+                .exit();
+            }
+        }
+        if args.background && args.save_baseline.is_some() && !args.force {
+            structopt::clap::Error::with_description(
+                "--background timings aren't comparable, so aren't saved as a baseline without \
+                 --force",
+                structopt::clap::ErrorKind::ArgumentConflict,
+            )
+            .exit();
+        }
+        if matches!(args.run_type, Some(RunType::Records)) && args.records_file.is_none() {
+            structopt::clap::Error::with_description(
+                "The records subcommand requires --records-file",
+                structopt::clap::ErrorKind::MissingRequiredArgument,
+            )
+            .exit();
+        }
+
+        args
+    }
+
+    fn run_type(&self) -> &RunType {
+        self.run_type
+            .as_ref()
+            .expect("Subcommand is checked when parsing")
+    }
+
+    fn sample_mode(&self) -> SampleMode {
+        match self.iterations {
+            Some(count) => SampleMode::Iterations(count),
+            None => SampleMode::Timed(self.bench_time),
+        }
+    }
+
+    /// The sample mode for the day's functions, which may have their own bench time. An
+    /// explicit `--iterations` still applies to every day.
+    fn day_sample_mode(&self, day: &Day) -> SampleMode {
+        match (self.iterations, day.bench_time) {
+            (None, Some(bench_time)) => SampleMode::Timed(bench_time),
+            _ => self.sample_mode(),
+        }
+    }
+
+    fn answer_history(&self) -> Option<usize> {
+        self.check_determinism.then_some(self.max_distinct_answers)
+    }
 }
 
 #[derive(Clone)]
 pub struct Day {
     pub name: &'static str,
     pub day: u8,
+    /// Parses the input on its own, for debugging the parser with `run --parse-only`.
+    pub parse: Option<SetupFunction>,
     pub part_1: SetupFunction,
     pub part_2: Option<SetupFunction>,
+    /// Extra functions to bench, by name. One named `part1_reference` or `part2_reference`
+    /// is taken as the reference implementation of that part, and the detailed bench reports
+    /// how much faster the part is.
     pub other: &'static [(&'static str, SetupFunction)],
+    /// Overrides `--bench-time` for this day's functions, in seconds. Useful for days too slow
+    /// to get enough samples in the usual time, or too fast to need it.
+    pub bench_time: Option<u64>,
+    /// The answers the parts should give. A different answer is an error, so refactoring
+    /// can be checked against answers already accepted.
+    pub expected_p1: Option<&'static str>,
+    pub expected_p2: Option<&'static str>,
+    /// The puzzle's examples, checked by the `test` subcommand.
+    pub examples: &'static [ExampleSpec],
 }
 
-fn get_days<'d>(days: &'d [Day], filter: &[u8]) -> Result<Vec<&'d Day>, BenchError> {
+impl Day {
+    /// Starts building a day with only its first part, with the rest added by the other
+    /// methods. Everything but [`other`](Self::other) can be used in a `const`:
+    ///
+    /// ```rust
+    /// # use aoc_lib::{Bench, BenchResult, Day};
+    /// # fn part_1(input: &str, bench: Bench) -> BenchResult {
+    /// #     bench.bench(|| Ok::<_, String>(input.len()))
+    /// # }
+    /// # use part_1 as part_2;
+    /// # use part_1 as parse;
+    /// # use part_1 as brute;
+    /// # use part_1 as bitset;
+    /// pub const DAY: Day = Day::new(5, "Hydrothermal Venture", part_1)
+    ///     .part_2(part_2)
+    ///     .parse(parse)
+    ///     .others(&[("brute", brute), ("bitset", bitset)]);
+    /// # assert_eq!(DAY.day, 5);
+    /// # assert_eq!(DAY.other.len(), 2);
+    /// ```
+    ///
+    /// An out of range day doesn't compile:
+    ///
+    /// ```rust,compile_fail
+    /// # use aoc_lib::{Bench, BenchResult, Day};
+    /// # fn part_1(_: &str, _: Bench) -> BenchResult {
+    /// #     Ok(())
+    /// # }
+    /// pub const DAY: Day = Day::new(26, "Out of range", part_1);
+    /// # let _ = DAY;
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the day isn't from 1 to 25, which is a compile error in a `const`.
+    pub const fn new(day: u8, name: &'static str, part_1: SetupFunction) -> Self {
+        assert!(day >= 1 && day <= 25, "Day must be from 1 to 25");
+
+        Self {
+            name,
+            day,
+            parse: None,
+            part_1,
+            part_2: None,
+            other: &[],
+            bench_time: None,
+            expected_p1: None,
+            expected_p2: None,
+            examples: &[],
+        }
+    }
+
+    pub const fn part_2(mut self, part_2: SetupFunction) -> Self {
+        self.part_2 = Some(part_2);
+        self
+    }
+
+    pub const fn parse(mut self, parse: SetupFunction) -> Self {
+        self.parse = Some(parse);
+        self
+    }
+
+    /// Times this day's functions for this many seconds, instead of `--bench-time`.
+    pub const fn bench_time(mut self, secs: u64) -> Self {
+        self.bench_time = Some(secs);
+        self
+    }
+
+    /// Sets the answer part 1 should give.
+    pub const fn expected_p1(mut self, answer: &'static str) -> Self {
+        self.expected_p1 = Some(answer);
+        self
+    }
+
+    /// Sets the answer part 2 should give.
+    pub const fn expected_p2(mut self, answer: &'static str) -> Self {
+        self.expected_p2 = Some(answer);
+        self
+    }
+
+    /// Sets the examples for the `test` subcommand, replacing any already added.
+    pub const fn examples(mut self, examples: &'static [ExampleSpec]) -> Self {
+        self.examples = examples;
+        self
+    }
+
+    /// Sets the extra functions to bench, replacing any already added.
+    pub const fn others(mut self, others: &'static [(&'static str, SetupFunction)]) -> Self {
+        self.other = others;
+        self
+    }
+
+    /// Adds an extra function to bench. This can't be used in a `const`, as the list has to live
+    /// for the rest of the program, so is leaked. Prefer [`others`](Self::others) where possible.
+    pub fn other(mut self, name: &'static str, function: SetupFunction) -> Self {
+        let mut other = self.other.to_vec();
+        other.push((name, function));
+        self.other = Vec::leak(other);
+        self
+    }
+}
+
+// The days picked with `--select`, or those given on the command line.
+fn days_filter() -> &'static [DaySelector] {
+    select::picked().unwrap_or_else(|| ARGS.run_type().days())
+}
+
+fn get_days<'d>(
+    year: u16,
+    days: &'d [Day],
+    filter: &[DaySelector],
+) -> Result<Vec<&'d Day>, BenchError> {
     match filter {
         [] => Ok(days.iter().collect()),
         filter => {
             let mut new_days = Vec::with_capacity(filter.len());
 
-            for &filter_day in filter {
+            for selector in filter {
                 let day = days
                     .iter()
-                    .find(|d| d.day == filter_day)
-                    .ok_or(BenchError::DaysFilterError(filter_day))?;
+                    .find(|d| d.day == selector.day)
+                    .ok_or(BenchError::DaysFilterError(selector.day, year))?;
+
+                let has_part = match &selector.part {
+                    None | Some(PartSelector::Part(1)) => true,
+                    Some(PartSelector::Part(2)) => day.part_2.is_some(),
+                    Some(PartSelector::Part(_)) => false,
+                    Some(PartSelector::Other(name)) if name == PARSE_NAME => day.parse.is_some(),
+                    Some(PartSelector::Other(name)) => day.other.iter().any(|(n, _)| n == name),
+                };
+                if !has_part {
+                    let part = match &selector.part {
+                        Some(PartSelector::Part(part)) => part.to_string(),
+                        Some(PartSelector::Other(name)) => name.clone(),
+                        None => unreachable!(),
+                    };
+                    return Err(BenchError::PartFilterError(part, day.day, year));
+                }
+
                 new_days.push(day);
             }
 
+            // Several parts of the same day could have been selected.
             new_days.sort_by_key(|d| d.day);
+            new_days.dedup_by_key(|d| d.day);
             Ok(new_days)
         }
     }
@@ -183,25 +1061,42 @@ pub(crate) fn render_decimal(val: usize) -> String {
     )
 }
 
+// Always renders to 5 characters for the number, a space, and 2 for the unit, plus 1 for the
+// slow marker when coloured, so that tables embedding the duration stay aligned regardless of
+// magnitude.
 pub fn render_duration(duration: Duration, colour: bool) -> String {
+    const SECS_PER_MINUTE: f64 = 60.0;
+    const SECS_PER_HOUR: f64 = 60.0 * 60.0;
+    const MAX_HOURS: f64 = 99_999.0;
+
+    if duration.is_zero() {
+        let zero = "    0 ns";
+        return if colour {
+            format!("{} ", zero)
+        } else {
+            zero.to_owned()
+        };
+    }
+
     // The logic here is basically copied from Criterion.
     let time = duration.as_nanos() as f64;
+    let secs = duration.as_secs_f64();
 
-    let (factor, unit) = if time < 10f64.powi(0) {
-        (10f64.powi(3), "ps")
-    } else if time < 10f64.powi(3) {
-        (10f64.powi(0), "ns")
+    let (time, unit) = if time < 10f64.powi(3) {
+        (time, "ns")
     } else if time < 10f64.powi(6) {
-        (10f64.powi(-3), "µs")
+        (time * 10f64.powi(-3), "µs")
     } else if time < 10f64.powi(9) {
-        (10f64.powi(-6), "ms")
+        (time * 10f64.powi(-6), "ms")
+    } else if secs < SECS_PER_MINUTE {
+        (secs, "s ")
+    } else if secs < SECS_PER_HOUR {
+        (secs / SECS_PER_MINUTE, "m ")
     } else {
-        (10f64.powi(-9), "s ")
+        (secs / SECS_PER_HOUR, "h ")
     };
 
-    let time = time * factor;
-
-    let prec = if time < 10.0 {
+    let mut prec = if time < 10.0 {
         3
     } else if time < 100.0 {
         2
@@ -211,146 +1106,949 @@ pub fn render_duration(duration: Duration, colour: bool) -> String {
         0
     };
 
-    let mut rendered_time = style(format!("{:>5.prec$}", time, prec = prec));
+    let mut number = if time > MAX_HOURS {
+        " >99k".to_owned()
+    } else {
+        format!("{:>5.prec$}", time, prec = prec)
+    };
+
+    // Rounding can carry into another digit (e.g. 9.9996 -> 10.000), so drop precision until it fits.
+    while number.len() > 5 && prec > 0 {
+        prec -= 1;
+        number = format!("{:>5.prec$}", time, prec = prec);
+    }
+
+    if !colour {
+        return format!("{} {}", number, unit);
+    }
+
     let duration_millis = duration.as_millis();
-    if colour {
-        if duration_millis > 500 {
-            rendered_time = rendered_time.red();
-        } else if colour && duration_millis > 50 {
-            rendered_time = rendered_time.yellow();
+    let (colour, symbol) = if duration_millis > 500 {
+        (Some(Color::Red), "!")
+    } else if duration_millis > 50 {
+        (Some(Color::Yellow), "*")
+    } else {
+        (None, "")
+    };
+
+    // Colour-blind users and plain text still need to know it's slow, so there's a column for
+    // the symbol after the unit, which is blank if there isn't one. That keeps the width the
+    // same whether it's slow or not.
+    match colour {
+        Some(colour) if OUTPUT.style().has_colour() => {
+            format!(
+                "{} {:<3}",
+                OUTPUT.style().paint(number, colour),
+                unit.trim_end()
+            )
         }
+        _ => format!("{} {:<3}", number, format!("{}{}", unit.trim_end(), symbol)),
     }
+}
 
-    format!("{} {}", rendered_time, unit)
+/// Renders the day label at the start of a table row, marking errors in red, or with `[E]` if
+/// colours are off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RowStatus {
+    Ok,
+    /// Measured, but the measurements may not be trustworthy.
+    Warning,
+    Error,
 }
 
-fn print_header(term_width: usize) {
-    if ARGS.run_type.is_run_only() {
-        println!("   Day | Answer");
-        println!("_______|_{0:_<30}", "");
+pub(crate) fn render_row_label(label: &str, status: RowStatus) -> String {
+    let label = pad_day_col(label);
+    let label = label.as_str();
+    let style = OUTPUT.style();
+    match (status, style.has_colour()) {
+        (RowStatus::Error, true) => format!("  {}", style.paint(label, Color::Red)),
+        (RowStatus::Error, false) => format!("[E]{}", label),
+        (RowStatus::Warning, true) => format!("  {}", style.paint(label, Color::Yellow)),
+        (RowStatus::Warning, false) => format!("[W]{}", label),
+        (RowStatus::Ok, true) => format!("  {}", style.paint(label, Color::Green)),
+        (RowStatus::Ok, false) => format!("  {}", label),
+    }
+}
+
+/// Renders the time of the single call that produced the answer, marked so it isn't mistaken
+/// for a benched mean.
+pub(crate) fn render_answer_time(elapsed: Option<Duration>) -> String {
+    match elapsed {
+        Some(elapsed) => format!("~ {}", render_duration(elapsed, true)),
+        None => " ".repeat(TABLE_RUN_TIME_COL_WIDTH - 3),
+    }
+}
+
+/// How wide the answer column of the bench table is for the terminal width. The header only
+/// needs redrawing when this changes.
+pub(crate) fn message_col_width(term_width: usize) -> usize {
+    term_width.saturating_sub(table_cols_width()).clamp(12, 30)
+}
+
+fn render_header(term_width: usize) -> String {
+    let day = format!("{:>width$}", "Day", width = day_col_width() + 2);
+    if ARGS.run_type().is_run_only() {
+        format!(
+            "{} | Time (1x)   | Answer\n{}|_____________|_{:_<30}",
+            day,
+            day_col_rule(),
+            ""
+        )
     } else {
-        let msg_max_width = term_width
-            .saturating_sub(TABLE_DETAILED_COLS_WIDTH)
-            .clamp(12, 30);
-        println!(
-            "   Day | {:<max_width$} | {:<21} | Allocs  | Max Mem.",
+        let msg_max_width = message_col_width(term_width);
+        let (wide_header, wide_rule) = render_extra_cols();
+        let titles = format!(
+            "{} | {:<max_width$} | {:<22} | Allocs  | Max Mem.{}",
+            day,
             "Answer",
             "Time",
+            wide_header,
             max_width = msg_max_width
         );
-        println!(
-            "_______|_{0:_<max_width$}_|_{0:_<21}_|_________|__________",
+        let rule = format!(
+            "{}|_{2:_<max_width$}_|_{2:_<22}_|_________|_________{1}",
+            day_col_rule(),
+            wide_rule,
             "",
             max_width = msg_max_width
         );
+        format!("{}\n{}", titles.trim_end(), rule)
     }
 }
 
-fn print_footer(total_time: Duration, term_width: usize) {
-    if ARGS.run_type.is_run_only() {
-        println!("_______|_{0:_<30}", "");
+fn print_header(term_width: usize) {
+    println!("{}", render_header(term_width));
+}
+
+fn print_footer(total_time: Duration, is_partial: bool, term_width: usize) {
+    if ARGS.run_type().is_run_only() {
+        println!("{}|_____________|_{:_<30}", day_col_rule(), "");
+        // Run mode is never cut short by --wall-budget, so it's never partial.
+        println!(
+            "{:>width$} | {}",
+            "Total",
+            render_answer_time(Some(total_time)).trim_end(),
+            width = day_col_width() + 2
+        );
     } else {
-        let msg_max_width = term_width
-            .saturating_sub(TABLE_DETAILED_COLS_WIDTH)
-            .clamp(12, 30);
+        let msg_max_width = message_col_width(term_width);
+        let (_, wide_rule) = render_extra_cols();
         let time = render_duration(total_time, false);
+        // Make it obvious that not everything was benched.
+        let label = if is_partial {
+            " Partial Time: "
+        } else {
+            " Total Time: "
+        };
         println!(
-            "_______|_{0:_<max_width$}_|_{0:_<21}_|_________|__________",
+            "{}|_{2:_<max_width$}_|_{2:_<22}_|_________|_________{1}",
+            day_col_rule(),
+            wide_rule,
             "",
             max_width = msg_max_width
         );
         println!(
-            " Total Time: {:max_width$} | {}",
+            "{}{:max_width$} | {}",
+            label,
             "",
             time,
-            max_width = msg_max_width - 4
+            max_width = msg_max_width + table_pre_col_width() - label.len()
         );
     }
 }
 
+// Plain answers for copying into the website, so no table, colour, or truncation.
+fn run_quiet(alloc: &'static TracingAlloc, days: &[&Day]) -> Result<(), BenchError> {
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let mut results = Vec::new();
+
+    // The errors are reported in the output, so we don't want the panic message as well.
+    let old_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let suffix = ARGS.input_suffix.as_deref();
+    for day in days {
+        // Like the bench, days without the variant are skipped.
+        if suffix.is_some() && !day_input(day.day, suffix).exists() {
+            continue;
+        }
+
+        let parts = iter::once((day.part_1, day.expected_p1))
+            .chain(day.part_2.map(|part| (part, day.expected_p2)))
+            .zip(1..);
+        let parts = parts.filter(|&(_, id)| is_function_selected(day.day, Some(id), ""));
+        for ((part, expected), id) in parts {
+            let bench = Bench {
+                alloc,
+                id: 0,
+                chan: sender.clone(),
+                run_only: true,
+                sample_mode: SampleMode::Timed(0),
+                answer_history: None,
+                measure_memory: false,
+                measure_single: false,
+                input: None,
+                expected,
+                samples_path: None,
+                input_bytes: None,
+            };
+
+            bench_worker(day.day, suffix.map(str::to_owned), bench, part);
+
+            let mut is_error = false;
+            let mut is_missing_input = false;
+            let mut input = None;
+            for event in receiver.try_iter() {
+                if let BenchEvent::Answer { input: source, .. } = &event {
+                    input = source.clone();
+                }
+                let line = match event {
+                    BenchEvent::Answer { .. } if ARGS.censor => "**CENSORED**".to_owned(),
+                    BenchEvent::Answer { answer, .. } => answer,
+                    BenchEvent::Error { err, .. } => {
+                        is_error = true;
+                        format!("ERROR: {}", err)
+                    }
+                    BenchEvent::InputError { err, .. } => {
+                        is_error = true;
+                        is_missing_input = true;
+                        format!("ERROR: {}", err)
+                    }
+                    _ => continue,
+                };
+                println!("{}.{}: {}", day_label(day.day), id, line);
+            }
+
+            results.push(FunctionSummary {
+                day: day.day,
+                name: day.name,
+                part: Some(id),
+                label: format!("{}.{}", day_label(day.day), id),
+                mean: None,
+                max_sample: None,
+                is_error,
+                is_missing_input,
+                input,
+            });
+        }
+    }
+
+    panic::set_hook(old_panic_hook);
+
+    if ARGS.summary_line {
+        print_summary_lines(&results);
+    }
+
+    outcome_result(&results)
+}
+
+// The answers arrive in whatever order the functions finished, so they're sorted first.
+// A function can send several, which are listed under a single heading, but the same answer
+// sent again, such as by each repeated run, is only listed once. They're separated by a rule
+// so ASCII art answers don't run together.
 fn print_alt_answers(receiver: Receiver<AlternateAnswer>) {
-    if !receiver.is_empty() {
-        println!("\n -- Alternate Answers --");
-        for alt_ans in receiver.iter() {
-            println!("Day {}, Part: {}", alt_ans.day, alt_ans.day_function_id);
-            if ARGS.censor {
-                println!("**CENSORED**\n");
-            } else {
-                println!("{}\n", alt_ans.answer);
+    let mut alt_answers: Vec<_> = receiver.try_iter().collect();
+    if alt_answers.is_empty() {
+        return;
+    }
+    alt_answers.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    alt_answers.dedup_by(|a, b| a.sort_key() == b.sort_key() && a.answer == b.answer);
+
+    println!("\n -- Alternate Answers --");
+    let mut prev_heading = None;
+    for alt_ans in &alt_answers {
+        if prev_heading.is_some() {
+            println!("{}", ALT_ANSWER_RULE);
+        }
+        let heading = alt_ans.heading();
+        if prev_heading.as_ref() != Some(&heading) {
+            println!("{}", heading);
+        }
+
+        if ARGS.censor {
+            println!("**CENSORED**");
+        } else {
+            println!("{}", alt_ans.answer.trim_end());
+        }
+        prev_heading = Some(heading);
+    }
+    println!();
+}
+
+// Heads the rows of each day, so the puzzle can be identified without looking it up.
+fn render_day_title(day: u8, name: &str, term_width: usize) -> String {
+    let title = format!("  {:>2}. {}", day_label(day), name);
+    let title: String = title.chars().take(term_width).collect();
+    OUTPUT.style().bold(title)
+}
+
+fn print_day_title(day: u8, name: &str, term_width: usize) {
+    println!("{}", render_day_title(day, name, term_width));
+}
+
+// Renders a duration without padding or spaces, e.g. `1.200ms`.
+fn render_short_duration(duration: Option<Duration>) -> String {
+    duration.map_or_else(
+        || "-".to_owned(),
+        |d| render_duration(d, false).replace(' ', ""),
+    )
+}
+
+/// Prints the compact per-day summary requested by `--summary-line`.
+///
+/// The format is meant to be scripted against, so should stay stable:
+///
+/// ```text
+/// day  1: ** 12.30µs/1.402ms Sonar Sweep
+/// day  2: *. 5.100µs/- Dive!
+/// total: 3 stars 1.419ms
+/// ```
+///
+/// The day's name is everything after the times.
+/// Each day has two star slots. A part earns its star if it produced an answer without
+/// error, shown as `*` (or `⭐` with `--emoji`), otherwise the slot is `.` (or `☆`). Times
+/// are the mean of each part, or `-` if it wasn't timed. With `--year all`, the day includes
+/// its year, e.g. `day 2021-05`.
+fn print_summary_lines(results: &[FunctionSummary]) {
+    println!();
+    for line in render_summary_lines(results, ARGS.emoji) {
+        println!("{}", line);
+    }
+}
+
+fn render_summary_lines(results: &[FunctionSummary], emoji: bool) -> Vec<String> {
+    let (earned, unearned) = if emoji { ("⭐", "☆") } else { ("*", ".") };
+
+    let mut days: Vec<_> = results.iter().map(|r| (r.day, r.name)).collect();
+    days.dedup();
+
+    let mut total_stars = 0;
+    let mut total_time = None;
+    let mut lines = Vec::new();
+
+    for (day, name) in days {
+        let find_part = |id| results.iter().find(|r| r.day == day && r.part == Some(id));
+
+        let mut stars = String::new();
+        let mut times = Vec::new();
+        for id in 1..=2 {
+            let part = find_part(id);
+            let is_star = part.is_some_and(|p| !p.is_error);
+            total_stars += is_star as usize;
+            stars.push_str(if is_star { earned } else { unearned });
+
+            let mean = part.and_then(|p| p.mean);
+            if let Some(mean) = mean {
+                *total_time.get_or_insert(Duration::ZERO) += mean;
             }
+            times.push(render_short_duration(mean));
         }
+
+        lines.push(format!(
+            "day {:>2}: {} {} {}",
+            day_label(day),
+            stars,
+            times.join("/"),
+            name
+        ));
+    }
+
+    lines.push(format!(
+        "total: {} stars {}",
+        total_stars,
+        render_short_duration(total_time)
+    ));
+    lines
+}
+
+/// Labels the results of a `--background` run, in the footer and every export.
+pub(crate) const BACKGROUND_NOTE: &str = "Background mode — timings not comparable";
+
+/// Whether the functions are being timed at a lower priority, with `--background`.
+pub(crate) fn is_background_bench() -> bool {
+    ARGS.background && !ARGS.run_type().is_run_only()
+}
+
+// Writes the `--badge` file, if requested, for the total time of the run.
+fn write_badge(total_time: Duration) -> BenchResult {
+    // There's no timing to show when just running.
+    let Some(path) = ARGS
+        .badge
+        .as_ref()
+        .filter(|_| !ARGS.run_type().is_run_only())
+    else {
+        return Ok(());
+    };
+
+    let label = ARGS
+        .badge_label
+        .replace("{year}", &RUNNING_YEAR.load(Ordering::Relaxed).to_string());
+    let mut value = format!("{} total", render_duration(total_time, false).trim());
+    if is_background_bench() {
+        value += " (background)";
+    }
+    let colour = badge::badge_colour(total_time, ARGS.badge_thresholds);
+
+    output::write_atomic(path, badge::render_badge(&label, &value, colour).as_bytes())
+}
+
+// Notes about the run that affect how the results should be read.
+fn print_run_notes(results: &[FunctionSummary]) {
+    if is_background_bench() {
+        println!("{}", BACKGROUND_NOTE);
+    }
+
+    // An answer from an example or a variant is easily mistaken for the real one.
+    let mut inputs: Vec<&InputProvenance> = Vec::new();
+    for input in results.iter().filter_map(|r| r.input.as_deref()) {
+        if !input.is_default && !inputs.iter().any(|seen| seen.source == input.source) {
+            inputs.push(input);
+        }
+    }
+    for input in inputs {
+        println!("Input: {}", input.render());
+    }
+}
+
+// Prints a summary of any days that were skipped, functions that failed, and parts slower
+// than `--fail-above`, and fails the run if there were any. Missing inputs take priority, as
+// they mean the results are incomplete.
+fn outcome_result(results: &[FunctionSummary]) -> BenchResult {
+    check_outcome(results, ARGS.fail_above)
+}
+
+/// The outcome of the run from its results, failing if any part's mean is above the limit.
+pub(crate) fn check_outcome(
+    results: &[FunctionSummary],
+    fail_above: Option<Duration>,
+) -> BenchResult {
+    let mut missing_inputs: Vec<_> = results
+        .iter()
+        .filter(|r| r.is_missing_input)
+        .map(|r| r.day)
+        .collect();
+    missing_inputs.sort_unstable();
+    missing_inputs.dedup();
+
+    let errored: Vec<_> = results
+        .iter()
+        .filter(|r| r.is_error && !r.is_missing_input)
+        .map(|r| r.label.clone())
+        .collect();
+
+    let too_slow: Vec<_> = match fail_above {
+        Some(limit) => results
+            .iter()
+            .filter(|r| r.part.is_some() && !r.is_error)
+            .filter_map(|r| Some((r.label.clone(), r.mean.filter(|&mean| mean > limit)?)))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    if !missing_inputs.is_empty() {
+        let days: Vec<_> = missing_inputs.iter().map(u8::to_string).collect();
+        println!(
+            "\nSkipped {} day(s) due to missing inputs: {}",
+            missing_inputs.len(),
+            days.join(", ")
+        );
+    }
+
+    if !errored.is_empty() {
+        println!(
+            "\n{} of {} function(s) failed: {}",
+            errored.len(),
+            results.len(),
+            errored.join(", ")
+        );
+    }
+
+    if let (Some(limit), false) = (fail_above, too_slow.is_empty()) {
+        println!(
+            "\n{} part(s) slower than {}:",
+            too_slow.len(),
+            render_duration(limit, false).trim()
+        );
+        for (label, mean) in &too_slow {
+            println!("  {:>5}: {}", label, render_duration(*mean, false).trim());
+        }
+    }
+
+    if !missing_inputs.is_empty() {
+        Err(BenchError::PartialFailure { missing_inputs })
+    } else if !errored.is_empty() || !too_slow.is_empty() {
+        Err(BenchError::FailedFunctions {
+            errored,
+            too_slow: too_slow.into_iter().map(|(label, _)| label).collect(),
+        })
+    } else {
+        Ok(())
     }
 }
 
 // No need for all of the complex machinery just to run the two functions, given we want
 // panics to happen as normal.
 fn run_single(alloc: &'static TracingAlloc, day: &Day) -> Result<(), BenchError> {
-    let stdout = Term::stdout();
-    let (_, cols) = stdout.size();
+    let functions: Vec<_> = day_functions(day)
+        .filter(|f| is_function_selected(day.day, f.part, f.name))
+        .map(|f| {
+            let id = f
+                .part
+                .map_or_else(|| f.name.to_owned(), |part| part.to_string());
+            (f, id)
+        })
+        .collect();
+    let labels: Vec<_> = functions
+        .iter()
+        .map(|(_, id)| function_row_label(day.day, id))
+        .collect();
+    fit_day_col(&labels);
+
+    let (_, cols) = OUTPUT.term_size();
     print_header(cols as _);
 
+    print_day_title(day.day, day.name, cols as _);
+
     let (sender, receiver) = crossbeam_channel::unbounded();
     let (alt_answer_sender, alt_answer_receiver) = crossbeam_channel::unbounded();
 
-    let parts = iter::once(day.part_1).chain(day.part_2).zip(1..);
+    let input_file = day_input(day.day, ARGS.input_suffix.as_deref());
+    let (path, is_default) = (input_file.path(), input_file.is_default());
+    let input = match input_file.open() {
+        Ok(input) => input,
+        Err(e) => {
+            let Some(message) = describe_input_error(&e) else {
+                return Err(e);
+            };
+            // Every function fails without it, as when benching.
+            let mut results = Vec::new();
+            for ((function, id), label) in functions.iter().zip(&labels) {
+                let label = render_row_label(label, RowStatus::Error);
+                println!("{} | {} | {}", label, render_answer_time(None), message);
+                results.push(FunctionSummary {
+                    day: day.day,
+                    name: day.name,
+                    part: function.part,
+                    label: format!("{}.{}", day_label(day.day), id),
+                    mean: None,
+                    max_sample: None,
+                    is_error: true,
+                    is_missing_input: true,
+                    input: None,
+                });
+            }
+            print_footer(Duration::ZERO, false, cols as _);
+            return outcome_result(&results);
+        }
+    };
+
+    let provenance = InputProvenance::shared(path, &input, is_default);
+    let mut results = Vec::new();
+    let mut captured_output = Vec::new();
+    let mut total_time = Duration::ZERO;
 
-    for (part, id) in parts {
+    for ((function, id), label) in functions.iter().zip(&labels) {
         let dummy = Bench {
             alloc,
             id: 0,
             chan: sender.clone(),
             run_only: true,
-            bench_time: 0,
+            sample_mode: SampleMode::Timed(0),
+            answer_history: None,
+            measure_memory: false,
+            measure_single: false,
+            input: Some(provenance.clone()),
+            expected: function.expected,
+            samples_path: None,
+            input_bytes: None,
         };
 
-        let input = input(day.day).open()?;
-        part(&input, dummy)?;
+        // Deep recursion and timeouts should behave the same as when benching. A function
+        // failing or panicking is sent as an error, so it's only a failed row.
+        run_function(input.clone(), dummy, function.function);
 
-        let message = match receiver.recv().expect("Failed to receive from channel") {
-            BenchEvent::Answer {
-                answer,
-                is_alt: true,
-                ..
-            } => {
-                if !ARGS.censor {
+        // The part has returned, so everything it sent is waiting in the channel.
+        let mut message = String::new();
+        let mut is_error = false;
+        let mut answer_time = None;
+        for event in receiver.try_iter() {
+            match event {
+                BenchEvent::Answer {
+                    answer,
+                    is_alt: true,
+                    ..
+                } if !ARGS.censor => {
                     alt_answer_sender
-                        .send(AlternateAnswer {
+                        .send(AlternateAnswer::for_function(
                             answer,
-                            day: day.day,
-                            day_function_id: id,
-                        })
+                            day.day,
+                            day.name,
+                            function.part,
+                            id,
+                        ))
                         .expect("Failed to send alternate answer");
 
-                    "Check alternate answers".to_owned()
-                } else {
-                    String::new()
+                    message = "Check alternate answers".to_owned();
+                }
+                BenchEvent::Answer { answer, .. } => message = answer,
+                BenchEvent::Error { err, .. } => {
+                    message = err;
+                    is_error = true;
+                }
+                BenchEvent::AnswerTime { elapsed, .. } => {
+                    total_time += elapsed;
+                    answer_time = Some(elapsed);
+                }
+                BenchEvent::Output { output, .. } => {
+                    captured_output.push(AlternateAnswer::for_function(
+                        output,
+                        day.day,
+                        day.name,
+                        function.part,
+                        id,
+                    ));
                 }
+                _ => {}
             }
-            BenchEvent::Answer { answer: msg, .. } | BenchEvent::Error { err: msg, .. } => msg,
-            _ => unreachable!("Should only receive an Answer or Error"),
-        };
+        }
 
-        print!("  {:>2}.{} | ", day.day, id,);
-        if ARGS.censor {
+        let status = if is_error {
+            RowStatus::Error
+        } else {
+            RowStatus::Ok
+        };
+        print!(
+            "{} | {} | ",
+            render_row_label(label, status),
+            render_answer_time(answer_time)
+        );
+        if ARGS.censor && !is_error {
             println!("**CENSORED**");
         } else {
             println!("{}", message);
         }
+
+        results.push(FunctionSummary {
+            day: day.day,
+            name: day.name,
+            part: function.part,
+            label: format!("{}.{}", day_label(day.day), id),
+            mean: None,
+            max_sample: None,
+            is_error,
+            is_missing_input: false,
+            input: Some(provenance.clone()),
+        });
     }
 
-    print_footer(Duration::ZERO, cols as _);
+    print_footer(total_time, false, cols as _);
+    print_run_notes(&results);
+
+    if ARGS.summary_line {
+        print_summary_lines(&results);
+    }
 
+    print_captured_output(&captured_output);
     drop(alt_answer_sender);
     print_alt_answers(alt_answer_receiver);
 
-    Ok(())
+    outcome_result(&results)
+}
+
+// Runs each day's parse function once, so a slow parser can be spotted before the
+// parts are written.
+fn run_parse_only(alloc: &'static TracingAlloc, days: &[&Day]) -> Result<(), BenchError> {
+    let parsers = days
+        .iter()
+        .map(|day| {
+            day.parse
+                .map(|p| (day.day, day.name, p))
+                .ok_or(BenchError::NoParseFunction(day.day))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let labels: Vec<_> = parsers
+        .iter()
+        .map(|&(day, ..)| function_row_label(day, PARSE_NAME))
+        .collect();
+    fit_day_col(&labels);
+
+    let (_, cols) = OUTPUT.term_size();
+    print_header(cols as _);
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let mut results = Vec::new();
+    let mut total_time = Duration::ZERO;
+
+    for ((day, name, parse), label) in parsers.into_iter().zip(&labels) {
+        let input_file = day_input(day, ARGS.input_suffix.as_deref());
+        let (path, is_default) = (input_file.path(), input_file.is_default());
+        let input = match input_file.open() {
+            Ok(input) => input,
+            Err(e) => {
+                let Some(message) = describe_input_error(&e) else {
+                    return Err(e);
+                };
+                println!(
+                    "{} | {}",
+                    render_row_label(label, RowStatus::Error),
+                    message
+                );
+                results.push(FunctionSummary {
+                    day,
+                    name,
+                    part: None,
+                    label: format!("{}.{}", day_label(day), PARSE_NAME),
+                    mean: None,
+                    max_sample: None,
+                    is_error: true,
+                    is_missing_input: true,
+                    input: None,
+                });
+                continue;
+            }
+        };
+        let provenance = InputProvenance::shared(path, &input, is_default);
+
+        let bench = Bench {
+            alloc,
+            id: 0,
+            chan: sender.clone(),
+            run_only: true,
+            sample_mode: SampleMode::Timed(0),
+            answer_history: None,
+            measure_memory: false,
+            measure_single: true,
+            input: Some(provenance.clone()),
+            expected: None,
+            samples_path: None,
+            input_bytes: None,
+        };
+
+        // A parser failing or panicking is only a failed row.
+        run_function(input, bench, parse);
+
+        let mut message = String::new();
+        let mut is_error = false;
+        let mut measurement = None;
+        let mut max_memory = None;
+        for event in receiver.try_iter() {
+            match event {
+                BenchEvent::Answer { answer, .. } => message = answer,
+                BenchEvent::Error { err, .. } => {
+                    message = err;
+                    is_error = true;
+                }
+                BenchEvent::Timing { data, .. } => {
+                    total_time += data.mean;
+                    measurement = Some(data.mean);
+                }
+                BenchEvent::Memory { data, .. } => max_memory = Some(data.max_memory),
+                _ => {}
+            }
+        }
+
+        let status = if is_error {
+            RowStatus::Error
+        } else {
+            RowStatus::Ok
+        };
+        print!(
+            "{} | {} | {}",
+            render_row_label(label, status),
+            render_answer_time(measurement),
+            message
+        );
+        match max_memory {
+            Some(max_memory) => println!(" ({} peak)", bytesize::ByteSize(max_memory as u64)),
+            None => println!(),
+        }
+
+        results.push(FunctionSummary {
+            day,
+            name,
+            part: None,
+            label: format!("{}.{}", day_label(day), PARSE_NAME),
+            mean: measurement,
+            max_sample: measurement,
+            is_error,
+            is_missing_input: false,
+            input: Some(provenance),
+        });
+    }
+
+    print_footer(total_time, false, cols as _);
+    print_run_notes(&results);
+
+    outcome_result(&results)
+}
+
+fn run_compare(
+    alloc: &'static TracingAlloc,
+    days: &[&Day],
+    criterion_dir: &Path,
+    name_pattern: &str,
+) -> Result<(), BenchError> {
+    // Read these first, so a bad directory doesn't waste a whole bench run.
+    let (old_timings, unmapped) = compare::read_criterion_dir(criterion_dir, name_pattern)?;
+
+    let (runs, _) = run_simple_bench_results(alloc, days)?;
+    compare::print_comparison(
+        "criterion",
+        &criterion_dir.display().to_string(),
+        &old_timings,
+        &runs[0],
+    );
+
+    if !unmapped.is_empty() {
+        println!(
+            "\nCouldn't map {} criterion benchmark(s) with '{}': {}",
+            unmapped.len(),
+            name_pattern,
+            unmapped.join(", ")
+        );
+    }
+
+    outcome_result(&runs[0])
 }
 
 pub fn run(alloc: &'static TracingAlloc, year: u16, days: &[Day]) -> Result<(), BenchError> {
-    let days = get_days(days, ARGS.run_type.days())?;
+    run_years(alloc, &[(year, days)])
+}
+
+fn new_year(year: u16, path: Option<&Path>, template: Option<&Path>) -> BenchResult {
+    let path = path.map_or_else(|| PathBuf::from(format!("aoc_{}", year)), Path::to_owned);
+    match template {
+        Some(template) => scaffold::new_year_from_template(&path, year, template)?,
+        None => scaffold::new_year(&path, year)?,
+    }
+
+    println!("Created {} for {}", path.display(), year);
+    if template.is_none() {
+        println!("Put your inputs in inputs/, and register the days in src/days/mod.rs");
+    }
+    Ok(())
+}
+
+/// Like [`run`], but for solutions from several years, selected with `--year`.
+///
+/// When more than one year is given, each year's inputs are read from its own directory,
+/// e.g. `inputs/2021/day03.txt`.
+pub fn run_years(alloc: &'static TracingAlloc, years: &[(u16, &[Day])]) -> BenchResult {
+    // The main thread renders the output, which isn't part of any function's trace.
+    alloc::exclude_current_thread();
+
+    // These are about what's registered, so they cover every year.
+    if ARGS.registration_digest {
+        print!("{}", digest::registration_digest(years));
+        return Ok(());
+    }
+    match ARGS.run_type() {
+        RunType::DiffRegistrations { old_digest } => {
+            return digest::diff_registrations(years, old_digest)
+        }
+        RunType::Records => {
+            let path = ARGS.records_file.as_deref();
+            return records::print_records(path.expect("Checked when parsing"));
+        }
+        RunType::NewYear {
+            year,
+            path,
+            template,
+        } => return new_year(*year, path.as_deref(), template.as_deref()),
+        _ => {}
+    }
+
+    let latest_year = years.iter().map(|&(year, _)| year).max();
+    let selected: Vec<_> = match ARGS.year {
+        Some(YearSelection::All) => {
+            let mut years = years.to_vec();
+            years.sort_by_key(|&(year, _)| year);
+            years
+        }
+        Some(YearSelection::Year(selected)) => {
+            let year = years
+                .iter()
+                .find(|&&(year, _)| year == selected)
+                .ok_or(BenchError::YearFilterError(selected))?;
+            vec![*year]
+        }
+        None => years
+            .iter()
+            .filter(|&&(year, _)| Some(year) == latest_year)
+            .copied()
+            .collect(),
+    };
+
+    if ARGS.select && ARGS.run_type().days().is_empty() {
+        select::pick_days(&selected)?;
+    }
+
+    // Check the filter against every year before running anything.
+    let selected = selected
+        .into_iter()
+        .map(|(year, days)| Ok((year, get_days(year, days, days_filter())?)))
+        .collect::<Result<Vec<_>, BenchError>>()?;
+
+    let mut missing_inputs = Vec::new();
+    let mut failed = (Vec::new(), Vec::new());
+    let mut failed_examples = Vec::new();
+    for (year, days) in selected {
+        if years.len() > 1 {
+            input::set_input_year(Some(year));
+        }
+        RUNNING_YEAR.store(year, Ordering::Relaxed);
+
+        match run_year(alloc, year, &days) {
+            Ok(()) => {}
+            // Missing inputs shouldn't stop the other years being run.
+            Err(BenchError::PartialFailure {
+                missing_inputs: days,
+            }) => missing_inputs.extend(days),
+            Err(BenchError::FailedFunctions { errored, too_slow }) => {
+                failed.0.extend(errored);
+                failed.1.extend(too_slow);
+            }
+            Err(BenchError::FailedExamples(examples)) => failed_examples.extend(examples),
+            Err(e) => return Err(e),
+        }
+    }
+
+    match failed {
+        _ if !missing_inputs.is_empty() => Err(BenchError::PartialFailure { missing_inputs }),
+        _ if !failed_examples.is_empty() => Err(BenchError::FailedExamples(failed_examples)),
+        (errored, too_slow) if !errored.is_empty() || !too_slow.is_empty() => {
+            Err(BenchError::FailedFunctions { errored, too_slow })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn run_year(alloc: &'static TracingAlloc, year: u16, days: &[&Day]) -> BenchResult {
+    // Resolve the output streams before anything is printed.
+    Lazy::force(&OUTPUT);
+    alloc::set_trace_buffer_cap(ARGS.trace_buffer_cap);
+    alloc::set_sample_rate(ARGS.mem_sample_rate);
+
+    if ARGS.run_type().is_quiet() {
+        return run_quiet(alloc, days);
+    }
 
     println!("Advent of Code {}", year);
-    match (&ARGS.run_type, &*days) {
+    match (ARGS.run_type(), days) {
+        (
+            RunType::Bench {
+                dry_run: true,
+                detailed,
+                ..
+            },
+            days,
+        ) => dry_run::dry_run(days, *detailed),
+        (
+            RunType::Run {
+                parse_only: true, ..
+            },
+            days,
+        ) => run_parse_only(alloc, days),
         (RunType::Run { .. }, [day]) => run_single(alloc, day),
         (
             RunType::Run { .. }
@@ -361,5 +2059,152 @@ pub fn run(alloc: &'static TracingAlloc, year: u16, days: &[Day]) -> Result<(),
         ) => run_simple_bench(alloc, days),
 
         (RunType::Bench { .. }, days) => run_detailed_bench(alloc, days),
+        (
+            RunType::Compare {
+                criterion_dir,
+                name_pattern,
+                ..
+            },
+            days,
+        ) => run_compare(alloc, days, criterion_dir, name_pattern),
+        (RunType::Test { .. }, days) => self_test::run_examples(alloc, days),
+        (RunType::Records | RunType::NewYear { .. } | RunType::DiffRegistrations { .. }, _) => {
+            unreachable!()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_duration_width() {
+        let durations = [
+            Duration::ZERO,
+            Duration::from_nanos(1),
+            Duration::from_nanos(999),
+            Duration::from_micros(9_999),
+            Duration::from_millis(60),
+            Duration::from_millis(600),
+            Duration::from_micros(9_999_600),
+            Duration::from_secs(59),
+            Duration::from_secs(10 * 60),
+            Duration::from_secs(5 * 60 * 60),
+            Duration::from_secs(200_000 * 60 * 60),
+        ];
+        for duration in durations {
+            let plain = render_duration(duration, false);
+            assert_eq!(plain.chars().count(), 8, "{:?} as {:?}", duration, plain);
+            // The slow marker has its own column, whether it's there or not.
+            let marked = render_duration(duration, true);
+            assert_eq!(
+                console::measure_text_width(&marked),
+                9,
+                "{:?} as {:?}",
+                duration,
+                marked
+            );
+        }
+    }
+
+    #[test]
+    fn render_duration_width_random() {
+        // A fixed xorshift, so a failure can be reproduced.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..100_000 {
+            // Shifted by a random amount, so every magnitude is covered.
+            let bits = next();
+            let duration = Duration::from_nanos(bits >> (next() % 64));
+            let plain = render_duration(duration, false);
+            assert_eq!(plain.chars().count(), 8, "{:?} as {:?}", duration, plain);
+            let marked = render_duration(duration, true);
+            assert_eq!(
+                console::measure_text_width(&marked),
+                9,
+                "{:?} as {:?}",
+                duration,
+                marked
+            );
+        }
+
+        let plain = render_duration(Duration::MAX, false);
+        assert_eq!(plain, " >99k h ");
+    }
+
+    #[test]
+    fn render_duration_marks_slow_times() {
+        let symbol = |duration| {
+            let rendered = render_duration(duration, true);
+            ['*', '!'].into_iter().find(|&c| rendered.contains(c))
+        };
+
+        assert_eq!(symbol(Duration::from_millis(50)), None);
+        assert_eq!(symbol(Duration::from_millis(51)), Some('*'));
+        assert_eq!(symbol(Duration::from_millis(501)), Some('!'));
+        // The thresholds are on the time, whatever unit it's shown in.
+        assert_eq!(symbol(Duration::from_secs(10 * 60)), Some('!'));
+        assert_eq!(symbol(Duration::from_secs(5 * 60 * 60)), Some('!'));
+    }
+
+    fn summary(day: u8, name: &'static str, part: u8, mean_us: Option<u64>) -> FunctionSummary {
+        FunctionSummary {
+            day,
+            name,
+            part: Some(part),
+            label: format!("{}.{}", day, part),
+            mean: mean_us.map(Duration::from_micros),
+            max_sample: None,
+            is_error: false,
+            is_missing_input: false,
+            input: None,
+        }
+    }
+
+    #[test]
+    fn summary_lines() {
+        // Day 2 has no part 2.
+        let results = [
+            summary(1, "Sonar Sweep", 1, Some(12)),
+            summary(1, "Sonar Sweep", 2, Some(1402)),
+            summary(2, "Dive!", 1, Some(5)),
+        ];
+        assert_eq!(
+            render_summary_lines(&results, false),
+            [
+                "day  1: ** 12.00µs/1.402ms Sonar Sweep",
+                "day  2: *. 5.000µs/- Dive!",
+                "total: 3 stars 1.419ms",
+            ]
+        );
+        assert_eq!(
+            render_summary_lines(&results[2..], true),
+            ["day  2: ⭐☆ 5.000µs/- Dive!", "total: 1 stars 5.000µs"]
+        );
+    }
+
+    #[test]
+    fn summary_lines_failures() {
+        // Part 1 returned an error before it was timed, and part 2's answer wasn't the one
+        // expected, which is an error after timing.
+        let mut errored = summary(3, "Binary Diagnostic", 1, None);
+        errored.is_error = true;
+        let mut wrong_answer = summary(3, "Binary Diagnostic", 2, Some(40));
+        wrong_answer.is_error = true;
+
+        assert_eq!(
+            render_summary_lines(&[errored, wrong_answer], false),
+            [
+                "day  3: .. -/40.00µs Binary Diagnostic",
+                "total: 0 stars 40.00µs"
+            ]
+        );
     }
 }
@@ -0,0 +1,99 @@
+/// Declares a [`Day`](crate::Day), allowing the optional fields to be omitted.
+///
+/// The expansion is a constant expression, so it can be used to initialize a `const`:
+///
+/// ```rust
+/// # use aoc_lib::{day, Bench, BenchResult, Day};
+/// # fn part_1(input: &str, bench: Bench) -> BenchResult {
+/// #     bench.bench(|| Ok::<_, String>(input.len()))
+/// # }
+/// # use part_1 as part_2;
+/// # use part_1 as brute;
+/// pub const DAY: Day = day! {
+///     day 5, "Hydrothermal Venture",
+///     part_1, part_2,
+///     extra: [("brute force", brute)],
+/// };
+/// # assert_eq!(DAY.day, 5);
+/// # assert!(DAY.part_2.is_some());
+/// # assert_eq!(DAY.other[0].0, "brute force");
+/// ```
+///
+/// A parse function, used by `run --parse-only`, can be given before the parts:
+///
+/// ```rust
+/// # use aoc_lib::{day, Bench, BenchResult, Day};
+/// # fn parse(input: &str, bench: Bench) -> BenchResult {
+/// #     bench.bench(|| Ok::<_, String>(input.len()))
+/// # }
+/// # use parse as part_1;
+/// # use parse as part_2;
+/// pub const DAY: Day = day! { day 5, "Hydrothermal Venture", parse: parse, part_1, part_2 };
+/// # assert!(DAY.parse.is_some());
+/// # assert!(DAY.other.is_empty());
+/// ```
+#[macro_export]
+macro_rules! day {
+    (day $day:literal, $name:literal, parse: $parse:path, $($rest:tt)*) => {
+        $crate::day!(@parts $day, $name, ::core::option::Option::Some($parse), $($rest)*)
+    };
+    (day $day:literal, $name:literal, $($rest:tt)*) => {
+        $crate::day!(@parts $day, $name, ::core::option::Option::None, $($rest)*)
+    };
+
+    (@parts $day:literal, $name:literal, $parse:expr, $p1:path, extra: [$(($oname:literal, $ofn:path)),* $(,)?] $(,)?) => {
+        $crate::day!(@build $day, $name, $parse, $p1, ::core::option::Option::None, [$(($oname, $ofn)),*])
+    };
+    (@parts $day:literal, $name:literal, $parse:expr, $p1:path, $p2:path, extra: [$(($oname:literal, $ofn:path)),* $(,)?] $(,)?) => {
+        $crate::day!(@build $day, $name, $parse, $p1, ::core::option::Option::Some($p2), [$(($oname, $ofn)),*])
+    };
+    (@parts $day:literal, $name:literal, $parse:expr, $p1:path, $p2:path $(,)?) => {
+        $crate::day!(@build $day, $name, $parse, $p1, ::core::option::Option::Some($p2), [])
+    };
+    (@parts $day:literal, $name:literal, $parse:expr, $p1:path $(,)?) => {
+        $crate::day!(@build $day, $name, $parse, $p1, ::core::option::Option::None, [])
+    };
+
+    (@build $day:literal, $name:literal, $parse:expr, $p1:path, $p2:expr, [$(($oname:literal, $ofn:path)),*]) => {
+        $crate::Day {
+            name: $name,
+            day: $day,
+            parse: $parse,
+            part_1: $p1,
+            part_2: $p2,
+            other: &[$(($oname, $ofn)),*],
+            bench_time: ::core::option::Option::None,
+            expected_p1: ::core::option::Option::None,
+            expected_p2: ::core::option::Option::None,
+            examples: &[],
+        }
+    };
+}
+
+/// Builds the `&[Day]` slice passed to [`run`](crate::run) from a list of modules, each of
+/// which must contain a `DAY` constant.
+///
+/// ```rust,no_run
+/// # use aoc_lib::{days, TracingAlloc};
+/// # mod day01 {
+/// #     use aoc_lib::{Bench, BenchResult, Day};
+/// #     fn part_1(_: &str, bench: Bench) -> BenchResult {
+/// #         bench.bench(|| Ok::<_, String>(0))
+/// #     }
+/// #     pub const DAY: Day = Day::new(1, "Sonar Sweep", part_1);
+/// # }
+/// # use day01 as day02;
+/// # use day01 as day03;
+/// #[global_allocator]
+/// static ALLOC: TracingAlloc = TracingAlloc;
+///
+/// fn main() -> aoc_lib::BenchResult {
+///     aoc_lib::run(&ALLOC, 2021, days![day01, day02, day03])
+/// }
+/// ```
+#[macro_export]
+macro_rules! days {
+    ($($module:ident),* $(,)?) => {
+        &[$($module::DAY),*]
+    };
+}
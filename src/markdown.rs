@@ -0,0 +1,134 @@
+//! The `--markdown` results table, for keeping in a README.
+//!
+//! The numbers are formatted like the simple table's, without colour, and the file is
+//! replaced on every run.
+
+use std::{fmt::Write, path::Path, sync::Arc, time::Duration};
+
+use bytesize::ByteSize;
+
+use crate::{
+    output::write_atomic, provenance::InputProvenance, render_decimal, render_duration, BenchError,
+    BACKGROUND_NOTE,
+};
+
+const HEADER: &str = "| Day | Part | Mean | Std Dev | Allocations | Peak Memory |";
+const HEADER_WITH_ANSWERS: &str =
+    "| Day | Part | Answer | Mean | Std Dev | Allocations | Peak Memory |";
+
+/// One function's results, as shown in the table.
+pub(crate) struct MarkdownRow {
+    /// The day, with its year under `--year all`.
+    pub(crate) day: String,
+    /// The part number, or the other function's name.
+    pub(crate) function: String,
+    /// The answer, or the error if the function failed.
+    pub(crate) message: String,
+    pub(crate) is_error: bool,
+    pub(crate) mean: Option<Duration>,
+    pub(crate) std_dev: Option<Duration>,
+    pub(crate) num_allocs: Option<usize>,
+    pub(crate) max_memory: Option<usize>,
+    pub(crate) input: Option<Arc<InputProvenance>>,
+}
+
+// Keeps the text in its cell.
+fn escape(text: &str) -> String {
+    text.trim_end()
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+fn render_time(duration: Option<Duration>) -> String {
+    duration.map_or_else(String::new, |d| render_duration(d, false).trim().to_owned())
+}
+
+fn render_table(
+    rows: &[MarkdownRow],
+    total_time: Duration,
+    show_answers: bool,
+    background: bool,
+) -> String {
+    let mut table = String::new();
+    let header = if show_answers {
+        HEADER_WITH_ANSWERS
+    } else {
+        HEADER
+    };
+    writeln!(table, "{}", header).unwrap();
+    writeln!(table, "|{}", "---|".repeat(header.matches('|').count() - 1)).unwrap();
+
+    for row in rows {
+        let mut cells = vec![row.day.clone(), escape(&row.function)];
+        if show_answers {
+            cells.push(escape(&row.message));
+        }
+
+        if row.is_error {
+            cells.push("failed".to_owned());
+            cells.extend(["", "", ""].map(str::to_owned));
+        } else {
+            cells.push(render_time(row.mean));
+            cells.push(render_time(row.std_dev));
+            cells.push(
+                row.num_allocs
+                    .map_or_else(String::new, |n| render_decimal(n).trim().to_owned()),
+            );
+            cells.push(
+                row.max_memory
+                    .map_or_else(String::new, |m| ByteSize(m as u64).to_string()),
+            );
+        }
+
+        writeln!(table, "| {} |", cells.join(" | ")).unwrap();
+    }
+
+    let mut footer = vec!["**Total**".to_owned(), String::new()];
+    if show_answers {
+        footer.push(String::new());
+    }
+    footer.push(format!("**{}**", render_time(Some(total_time))));
+    footer.extend(["", "", ""].map(str::to_owned));
+    writeln!(table, "| {} |", footer.join(" | ")).unwrap();
+
+    if background {
+        writeln!(table, "\n_{}_", BACKGROUND_NOTE).unwrap();
+    }
+
+    // Each input is listed once, under the table, as they're shared by the day's functions.
+    let mut inputs: Vec<&InputProvenance> = Vec::new();
+    for input in rows.iter().filter_map(|r| r.input.as_deref()) {
+        if !inputs.iter().any(|seen| seen.source == input.source) {
+            inputs.push(input);
+        }
+    }
+    if !inputs.is_empty() {
+        writeln!(table, "\nInputs:\n").unwrap();
+    }
+    for input in inputs {
+        writeln!(
+            table,
+            "- `{}`, {}, sha256 `{}`",
+            input.source,
+            ByteSize(input.len as u64),
+            input.sha256
+        )
+        .unwrap();
+    }
+
+    table
+}
+
+/// Writes the table of the results to the file, replacing it. The table of a background run
+/// is labelled as such.
+pub(crate) fn write_markdown(
+    path: &Path,
+    rows: &[MarkdownRow],
+    total_time: Duration,
+    show_answers: bool,
+    background: bool,
+) -> Result<(), BenchError> {
+    let table = render_table(rows, total_time, show_answers, background);
+    write_atomic(path, table.as_bytes())
+}
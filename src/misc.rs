@@ -1,9 +1,11 @@
+mod grid;
 mod iter_arr;
 use std::{
     collections::HashMap,
     ops::{Index, IndexMut},
 };
 
+pub use grid::*;
 pub use iter_arr::*;
 
 pub trait ResultZip<T, U, E> {
@@ -16,15 +18,57 @@ impl<T, U, E> ResultZip<T, U, E> for Result<T, E> {
     }
 }
 
+/// Keeps the `N` largest values pushed, largest first.
+///
+/// The initial values count as pushed, so should be no larger than any real value, such as
+/// zero for counts.
 pub struct Top<T, const N: usize>(pub [T; N]);
+
+impl<T: Default, const N: usize> Top<T, N> {
+    /// Starts with every slot holding the default value.
+    pub fn new() -> Self {
+        Self(std::array::from_fn(|_| T::default()))
+    }
+}
+
+impl<T: Default, const N: usize> Default for Top<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, const N: usize> Top<T, N> {
+    /// Starts with every slot holding the value.
+    pub fn with_fill(value: T) -> Self {
+        Self(std::array::from_fn(|_| value.clone()))
+    }
+}
+
 impl<T: Ord, const N: usize> Top<T, N> {
+    /// Inserts the value in order, shifting the smaller values down and dropping the
+    /// smallest. Does nothing if it's no larger than any of them.
     #[inline]
-    pub fn push(&mut self, mut value: T) {
-        for v in &mut self.0 {
-            if &mut value > v {
-                std::mem::swap(v, &mut value);
-            }
-        }
+    pub fn push(&mut self, value: T) {
+        let Some(pos) = self.0.iter().position(|v| value > *v) else {
+            return;
+        };
+
+        self.0[pos..].rotate_right(1);
+        self.0[pos] = value;
+    }
+}
+
+impl<T, const N: usize> Top<T, N> {
+    /// The values, largest first.
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.0.iter()
+    }
+
+    /// The values, largest first.
+    #[inline]
+    pub fn into_sorted(self) -> [T; N] {
+        self.0
     }
 }
 
@@ -36,6 +80,8 @@ pub trait IdType {
 #[derive(Debug)]
 pub struct IdGen<'a, T, I> {
     items: Vec<T>,
+    /// The name of each id, in the same order as the items.
+    names: Vec<&'a str>,
     map: HashMap<&'a str, I>,
 }
 
@@ -51,27 +97,71 @@ where
 
         let new_id = I::from_usize(self.map.len());
         self.items.push(T::default());
+        self.names.push(id);
         self.map.insert(id, new_id);
         new_id
     }
 }
 
+impl<'a, T, I> IdGen<'a, T, I>
+where
+    I: Copy + IdType,
+{
+    /// The name's id, without giving it one if it hasn't been seen.
+    pub fn get(&self, name: &str) -> Option<I> {
+        self.map.get(name).copied()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.map.contains_key(name)
+    }
+
+    /// The name the id was given for.
+    pub fn name_of(&self, id: I) -> &'a str {
+        self.names[id.to_usize()]
+    }
+
+    /// Every name with its id and item, in the order they were seen.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, I, &T)> + '_ {
+        self.names
+            .iter()
+            .zip(&self.items)
+            .enumerate()
+            .map(|(i, (&name, item))| (name, I::from_usize(i), item))
+    }
+}
+
 impl<'a, T, I> Default for IdGen<'a, T, I> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<T, I> IdGen<'_, T, I> {
+impl<'a, T, I> IdGen<'a, T, I> {
     pub fn new() -> Self {
         Self {
             items: Vec::new(),
+            names: Vec::new(),
             map: HashMap::new(),
         }
     }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
     pub fn into_items(self) -> Vec<T> {
         self.items
     }
+
+    /// The items and their names, both indexed by id.
+    pub fn into_parts(self) -> (Vec<T>, Vec<&'a str>) {
+        (self.items, self.names)
+    }
 }
 
 impl<'a, T, I> Index<I> for IdGen<'a, T, I>
@@ -93,3 +183,64 @@ where
         &mut self.items[index.to_usize()]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn top_of<const N: usize>(values: impl IntoIterator<Item = u32>) -> [u32; N] {
+        let mut top = Top::<u32, N>::new();
+        for value in values {
+            top.push(value);
+        }
+        top.into_sorted()
+    }
+
+    #[test]
+    fn top_ascending() {
+        assert_eq!(top_of::<3>(1..=10), [10, 9, 8]);
+    }
+
+    #[test]
+    fn top_descending() {
+        assert_eq!(top_of::<2>([5, 4, 3]), [5, 4]);
+        assert_eq!(top_of::<3>((1..=10).rev()), [10, 9, 8]);
+        // A mid-range value pushes the others down, rather than replacing one.
+        assert_eq!(top_of::<3>([9, 5, 1, 7]), [9, 7, 5]);
+    }
+
+    #[test]
+    fn top_random() {
+        // A fixed xorshift, so a failure can be reproduced.
+        let mut state = 0x9e37_79b9_u32;
+        let values: Vec<_> = (0..1000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state % 500
+            })
+            .collect();
+
+        let mut sorted = values.clone();
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(top_of::<5>(values.iter().copied()), sorted[..5]);
+    }
+
+    #[test]
+    fn top_duplicates_and_short_input() {
+        assert_eq!(top_of::<3>([4, 4, 2, 4]), [4, 4, 4]);
+        // Fewer values than slots leaves the fill.
+        assert_eq!(top_of::<3>([7]), [7, 0, 0]);
+        assert_eq!(top_of::<0>([1, 2, 3]), [0u32; 0]);
+    }
+
+    #[test]
+    fn top_with_fill() {
+        let mut top = Top::<i32, 2>::with_fill(i32::MIN);
+        for value in [-5, -9, -1] {
+            top.push(value);
+        }
+        assert_eq!(top.iter().copied().collect::<Vec<_>>(), [-1, -5]);
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    ops::{Index, IndexMut},
+};
+
+use crate::parsers::{lines, GridParseError};
+
+const NEIGHBOURS_4: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const NEIGHBOURS_8: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// A 2D map, such as a puzzle input of one character per cell, stored row-major.
+///
+/// Cells are indexed by `(x, y)`, with `(0, 0)` the top left.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T> Grid<T> {
+    /// Wraps the row-major cells. Panics if they aren't a whole number of rows.
+    pub fn new(cells: Vec<T>, width: usize) -> Self {
+        let height = match width {
+            0 => 0,
+            _ => cells.len() / width,
+        };
+        assert_eq!(width * height, cells.len(), "Cells aren't whole rows");
+
+        Self {
+            cells,
+            width,
+            height,
+        }
+    }
+
+    /// Parses one cell from each character, with a row per line. Trailing newlines are
+    /// ignored, and `\r\n` line endings accepted.
+    pub fn parse(input: &str, mut cell: impl FnMut(char) -> T) -> Result<Self, GridParseError> {
+        let mut width = None;
+        let mut cells = Vec::with_capacity(input.len());
+
+        for (line_num, line) in lines(input) {
+            let start = cells.len();
+            cells.extend(line.chars().map(&mut cell));
+
+            let len = cells.len() - start;
+            match width {
+                None => width = Some(len),
+                Some(width) if width != len => {
+                    return Err(GridParseError::Ragged {
+                        line: line_num,
+                        len,
+                        width,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(Self::new(cells, width.unwrap_or(0)))
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The cells, row-major.
+    #[inline]
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    #[inline]
+    pub fn into_cells(self) -> Vec<T> {
+        self.cells
+    }
+
+    #[inline]
+    fn index_of(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+
+    fn expect_index(&self, x: usize, y: usize) -> usize {
+        self.index_of(x, y).unwrap_or_else(|| {
+            panic!(
+                "({}, {}) is outside the {}x{} grid",
+                x, y, self.width, self.height
+            )
+        })
+    }
+
+    /// The cell, or `None` if it's outside the grid.
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        self.index_of(x, y).map(|i| &self.cells[i])
+    }
+
+    /// The cell, or `None` if it's outside the grid.
+    #[inline]
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        self.index_of(x, y).map(|i| &mut self.cells[i])
+    }
+
+    /// Each row, top to bottom.
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[T]> + ExactSizeIterator {
+        // A zero width would panic, but the grid is then empty anyway.
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// The cells of the column, top to bottom. Empty if it's outside the grid.
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        let start = if x < self.width { x } else { self.cells.len() };
+        self.cells[start..].iter().step_by(self.width.max(1))
+    }
+
+    /// Each column, left to right.
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(|x| self.column(x))
+    }
+
+    /// Every cell with its coordinates, row-major.
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| ((i % width, i / width), cell))
+    }
+
+    fn neighbours<'a>(
+        &'a self,
+        x: usize,
+        y: usize,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = ((usize, usize), &'a T)> {
+        offsets.iter().filter_map(move |&(dx, dy)| {
+            let (nx, ny) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+            self.get(nx, ny).map(|cell| ((nx, ny), cell))
+        })
+    }
+
+    /// The cells above, left, right, and below, with their coordinates. Those outside the
+    /// grid are left out.
+    pub fn neighbours_4(&self, x: usize, y: usize) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.neighbours(x, y, &NEIGHBOURS_4)
+    }
+
+    /// Like [`neighbours_4`](Self::neighbours_4), but including the diagonals.
+    pub fn neighbours_8(&self, x: usize, y: usize) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.neighbours(x, y, &NEIGHBOURS_8)
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        &self.cells[self.expect_index(x, y)]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    #[inline]
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        let i = self.expect_index(x, y);
+        &mut self.cells[i]
+    }
+}
+
+fn fmt_rows<T>(grid: &Grid<T>, f: &mut Formatter<'_>, to_char: impl Fn(&T) -> char) -> FmtResult {
+    for row in grid.rows() {
+        let line: String = row.iter().map(&to_char).collect();
+        writeln!(f, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Prints the grid as it was in the input, for debugging.
+impl Display for Grid<char> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        fmt_rows(self, f, |&c| c)
+    }
+}
+
+/// Prints each cell as an ASCII character, for grids of the input's bytes.
+impl Display for Grid<u8> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        fmt_rows(self, f, |&b| b as char)
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::marker::PhantomData;
 use std::ptr::NonNull;
 
@@ -112,3 +113,253 @@ impl<'a, T, const N: usize> ArrChunksMut<'a, T, N> {
         }
     }
 }
+
+/// Array-based helpers for arbitrary iterators.
+pub trait IterArrExt: Iterator + Sized {
+    /// Collects exactly `N` items into an array, returning `None` if the iterator
+    /// yields more or fewer than that.
+    ///
+    /// Handy for destructuring, e.g. `let [a, b, c] = line.split(',').iter_arr()?;`
+    #[inline]
+    fn iter_arr<const N: usize>(mut self) -> Option<[Self::Item; N]> {
+        let mut items = Vec::with_capacity(N);
+        items.extend(self.by_ref().take(N));
+
+        if self.next().is_some() {
+            return None;
+        }
+
+        items.try_into().ok()
+    }
+
+    /// Yields non-overlapping chunks of `N` items by value.
+    ///
+    /// If the item count isn't a multiple of `N`, the final partial chunk isn't yielded,
+    /// but can be retrieved with [`IterArrChunks::remainder`]. A chunk size of 0 yields nothing.
+    #[inline]
+    fn iter_arr_chunks<const N: usize>(self) -> IterArrChunks<Self, N> {
+        IterArrChunks {
+            iter: self,
+            remainder: Vec::new(),
+        }
+    }
+
+    /// Yields overlapping windows of `N` items, like [`ArrWindows`] but for any iterator.
+    ///
+    /// Nothing is yielded if there are fewer than `N` items. A window size of 0 yields nothing.
+    #[inline]
+    fn iter_arr_windows<const N: usize>(self) -> IterArrWindows<Self, N>
+    where
+        Self::Item: Clone,
+    {
+        IterArrWindows {
+            iter: self,
+            window: VecDeque::with_capacity(N),
+        }
+    }
+}
+
+impl<I: Iterator> IterArrExt for I {}
+
+pub struct IterArrChunks<I: Iterator, const N: usize> {
+    iter: I,
+    remainder: Vec<I::Item>,
+}
+
+impl<I: Iterator, const N: usize> IterArrChunks<I, N> {
+    /// The items left over after the last full chunk. Only populated once the iterator
+    /// has been exhausted.
+    #[inline]
+    pub fn remainder(&self) -> &[I::Item] {
+        &self.remainder
+    }
+}
+
+impl<I: Iterator, const N: usize> Iterator for IterArrChunks<I, N> {
+    type Item = [I::Item; N];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if N == 0 || !self.remainder.is_empty() {
+            return None;
+        }
+
+        let mut chunk = Vec::with_capacity(N);
+        chunk.extend(self.iter.by_ref().take(N));
+
+        match chunk.try_into() {
+            Ok(chunk) => Some(chunk),
+            Err(partial) => {
+                self.remainder = partial;
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if N == 0 || !self.remainder.is_empty() {
+            return (0, Some(0));
+        }
+
+        let (lower, upper) = self.iter.size_hint();
+        (lower / N, upper.map(|u| u / N))
+    }
+}
+
+pub struct IterArrWindows<I: Iterator, const N: usize> {
+    iter: I,
+    window: VecDeque<I::Item>,
+}
+
+impl<I, const N: usize> Iterator for IterArrWindows<I, N>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = [I::Item; N];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if N == 0 {
+            return None;
+        }
+
+        if self.window.len() == N {
+            self.window.pop_front();
+        }
+        while self.window.len() < N {
+            self.window.push_back(self.iter.next()?);
+        }
+
+        Some(std::array::from_fn(|i| self.window[i].clone()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if N == 0 {
+            return (0, Some(0));
+        }
+
+        // Each item after the first full window gives another window.
+        let (lower, upper) = self.iter.size_hint();
+        let needed = match self.window.len() {
+            len if len == N => 0,
+            len => N - 1 - len,
+        };
+        (
+            lower.saturating_sub(needed),
+            upper.map(|u| u.saturating_sub(needed)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arr_windows() {
+        let windows: Vec<_> = ArrWindows::<_, 2>::new(&[1, 2, 3]).collect();
+        assert_eq!(windows, [&[1, 2], &[2, 3]]);
+
+        let mut windows = ArrWindows::<_, 3>::new(&[1, 2, 3]);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows.next(), Some(&[1, 2, 3]));
+        assert_eq!(windows.next(), None);
+
+        let windows = ArrWindows::<i32, 4>::new(&[1, 2, 3]);
+        assert_eq!(windows.len(), 0);
+        assert_eq!(windows.count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn arr_windows_of_zero() {
+        ArrWindows::<i32, 0>::new(&[1]);
+    }
+
+    #[test]
+    fn arr_chunks() {
+        let chunks: Vec<_> = ArrChunks::<_, 2>::new(&[1, 2, 3, 4, 5]).collect();
+        assert_eq!(chunks, [&[1, 2], &[3, 4]]);
+
+        let chunks: Vec<_> = ArrChunks::<_, 3>::new(&[1, 2, 3]).collect();
+        assert_eq!(chunks, [&[1, 2, 3]]);
+
+        assert_eq!(ArrChunks::<i32, 2>::new(&[]).count(), 0);
+        assert_eq!(ArrChunks::<_, 4>::new(&[1, 2, 3]).count(), 0);
+    }
+
+    #[test]
+    fn arr_chunks_mut() {
+        let mut values = [1, 2, 3, 4, 5];
+        let mut chunks = ArrChunksMut::<_, 2>::new(&mut values);
+        assert_eq!(chunks.len(), 2);
+        for chunk in chunks.by_ref() {
+            chunk.swap(0, 1);
+        }
+        assert_eq!(chunks.len(), 0);
+        assert_eq!(values, [2, 1, 4, 3, 5]);
+
+        assert_eq!(ArrChunksMut::<i32, 4>::new(&mut [1, 2, 3]).count(), 0);
+    }
+
+    #[test]
+    fn iter_arr() {
+        assert_eq!("a,b,c".split(',').iter_arr(), Some(["a", "b", "c"]));
+        assert_eq!("a,b".split(',').iter_arr::<3>(), None);
+        assert_eq!("a,b,c,d".split(',').iter_arr::<3>(), None);
+
+        assert_eq!(std::iter::empty::<i32>().iter_arr(), Some([]));
+        assert_eq!([1].into_iter().iter_arr::<0>(), None);
+    }
+
+    #[test]
+    fn iter_arr_chunks() {
+        let mut chunks = (1..=7).iter_arr_chunks::<3>();
+        assert_eq!(chunks.size_hint(), (2, Some(2)));
+        assert_eq!(chunks.next(), Some([1, 2, 3]));
+        assert_eq!(chunks.next(), Some([4, 5, 6]));
+        assert_eq!(chunks.remainder(), &[] as &[i32]);
+        assert_eq!(chunks.next(), None);
+        assert_eq!(chunks.remainder(), &[7]);
+        // Once the remainder is taken, the chunks stay finished.
+        assert_eq!(chunks.next(), None);
+        assert_eq!(chunks.size_hint(), (0, Some(0)));
+
+        let mut chunks = (1..=6).iter_arr_chunks::<3>();
+        assert_eq!(chunks.by_ref().count(), 2);
+        assert!(chunks.remainder().is_empty());
+
+        let mut chunks = (1..=2).iter_arr_chunks::<3>();
+        assert_eq!(chunks.size_hint(), (0, Some(0)));
+        assert_eq!(chunks.next(), None);
+        assert_eq!(chunks.remainder(), &[1, 2]);
+
+        assert_eq!(std::iter::empty::<i32>().iter_arr_chunks::<2>().count(), 0);
+        assert_eq!((1..5).iter_arr_chunks::<0>().count(), 0);
+    }
+
+    #[test]
+    fn iter_arr_windows() {
+        let mut windows = (1..=4).iter_arr_windows::<2>();
+        assert_eq!(windows.size_hint(), (3, Some(3)));
+        assert_eq!(windows.next(), Some([1, 2]));
+        assert_eq!(windows.size_hint(), (2, Some(2)));
+        assert_eq!(windows.collect::<Vec<_>>(), [[2, 3], [3, 4]]);
+
+        let windows: Vec<_> = ["a", "b", "c"]
+            .into_iter()
+            .iter_arr_windows::<3>()
+            .collect();
+        assert_eq!(windows, [["a", "b", "c"]]);
+
+        let mut windows = (1..=2).iter_arr_windows::<3>();
+        assert_eq!(windows.size_hint(), (0, Some(0)));
+        assert_eq!(windows.next(), None);
+
+        assert_eq!(std::iter::empty::<i32>().iter_arr_windows::<2>().count(), 0);
+        assert_eq!((1..5).iter_arr_windows::<0>().count(), 0);
+    }
+}
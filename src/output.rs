@@ -0,0 +1,427 @@
+use std::{
+    env,
+    fmt::Display,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use console::{style, Color, Term};
+use indicatif::ProgressDrawTarget;
+use once_cell::sync::Lazy;
+
+use crate::{BenchError, ARGS};
+
+pub(crate) static OUTPUT: Lazy<Output> =
+    Lazy::new(|| Output::resolve(ARGS.progress_stream, ARGS.color, ARGS.no_tty));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProgressStream {
+    Auto,
+    Stdout,
+    Stderr,
+    None,
+}
+
+impl FromStr for ProgressStream {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ProgressStream::Auto),
+            "stdout" => Ok(ProgressStream::Stdout),
+            "stderr" => Ok(ProgressStream::Stderr),
+            "none" => Ok(ProgressStream::None),
+            _ => Err(format!(
+                "invalid progress stream '{}', expected auto, stdout, stderr, or none",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "invalid color choice '{}', expected auto, always, or never",
+                s
+            )),
+        }
+    }
+}
+
+// Environment overrides, for terminals where detection goes wrong. These are read before
+// asking console anything about the terminal.
+const FORCE_STYLE_VAR: &str = "AOC_FORCE_STYLE";
+const TERM_WIDTH_VAR: &str = "AOC_TERM_WIDTH";
+
+// Used when the terminal reports a size of zero, or with `--no-tty`.
+const FALLBACK_TERM_SIZE: (u16, u16) = (24, 100);
+
+fn env_override<T: FromStr>(var: &str) -> Option<T> {
+    let value = env::var(var).ok()?;
+    let parsed = value.parse().ok();
+    if parsed.is_none() {
+        eprintln!("Ignoring invalid {}: '{}'", var, value);
+    }
+    parsed
+}
+
+fn is_dumb_term() -> bool {
+    env::var_os("TERM").is_some_and(|term| term == "dumb")
+}
+
+// Progress stays with the table unless stdout is redirected, when it moves to stderr so it
+// can still be watched while the table goes to the file.
+fn auto_progress_stream(stdout_is_term: bool, stderr_is_term: bool) -> ProgressStream {
+    match (stdout_is_term, stderr_is_term) {
+        (true, _) => ProgressStream::Stdout,
+        (false, true) => ProgressStream::Stderr,
+        (false, false) => ProgressStream::None,
+    }
+}
+
+/// Every decision about whether to emit escape sequences goes through this, so that
+/// turning style off really means no escape sequences.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StyleConfig {
+    colours: bool,
+    /// Whether the cursor can be moved to redraw progress in place.
+    redraw: bool,
+}
+
+impl StyleConfig {
+    // An explicit `--color` wins, then `AOC_FORCE_STYLE`, then the environment's hints.
+    // See https://no-color.org for NO_COLOR.
+    fn resolve(color: ColorChoice, no_tty: bool) -> Self {
+        let forced = env_override(FORCE_STYLE_VAR);
+        let choice = match color {
+            ColorChoice::Auto => forced.unwrap_or(ColorChoice::Auto),
+            explicit => explicit,
+        };
+        // `--color never` is only about colour, but an environment that can't handle styles
+        // can't handle cursor movement either.
+        let redraw = !no_tty && !is_dumb_term() && forced != Some(ColorChoice::Never);
+
+        let colours = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                !no_tty
+                    && !is_dumb_term()
+                    && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && Term::stdout().is_term()
+            }
+        };
+
+        // Progress bars are styled by indicatif via console, so keep it in agreement.
+        console::set_colors_enabled(colours);
+        console::set_colors_enabled_stderr(colours);
+
+        Self { colours, redraw }
+    }
+
+    /// Whether output is styled. Without colour, the same information is shown with symbols.
+    pub(crate) fn has_colour(self) -> bool {
+        self.colours
+    }
+
+    pub(crate) fn paint(self, text: impl Display, colour: Color) -> String {
+        if self.colours {
+            style(text).fg(colour).force_styling(true).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub(crate) fn bold(self, text: impl Display) -> String {
+        if self.colours {
+            style(text).bold().force_styling(true).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Picks the progress bar template to use, as templates can contain styles.
+    pub(crate) fn template(self, styled: &'static str, plain: &'static str) -> &'static str {
+        if self.colours {
+            styled
+        } else {
+            plain
+        }
+    }
+}
+
+/// Decides where the live progress goes, which terminal the layout is sized from, and how
+/// output is styled.
+///
+/// The final tables are always printed to stdout.
+pub(crate) struct Output {
+    progress: ProgressStream,
+    style: StyleConfig,
+    term_width: Option<u16>,
+    /// Whether to act as if there's no terminal, so the layout doesn't depend on one.
+    no_tty: bool,
+    /// Set once drawing the progress failed, after which it's hidden for the rest of the run.
+    progress_broken: AtomicBool,
+}
+
+impl Output {
+    fn resolve(requested: ProgressStream, color: ColorChoice, no_tty: bool) -> Self {
+        let style = StyleConfig::resolve(color, no_tty);
+        let term_width = env_override(TERM_WIDTH_VAR);
+
+        let progress = match requested {
+            ProgressStream::Auto if !style.redraw => ProgressStream::None,
+            ProgressStream::Auto => {
+                auto_progress_stream(Term::stdout().is_term(), Term::stderr().is_term())
+            }
+            other => other,
+        };
+
+        Self {
+            progress,
+            style,
+            term_width,
+            no_tty,
+            progress_broken: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn style(&self) -> StyleConfig {
+        self.style
+    }
+
+    pub(crate) fn has_progress(&self) -> bool {
+        self.progress != ProgressStream::None && !self.is_progress_broken()
+    }
+
+    /// Whether drawing the progress failed, so it's no longer drawn.
+    pub(crate) fn is_progress_broken(&self) -> bool {
+        self.progress_broken.load(Ordering::Relaxed)
+    }
+
+    /// Stops drawing the progress for the rest of the run, warning about why.
+    pub(crate) fn disable_broken_progress(&self, reason: impl Display) {
+        if !self.progress_broken.swap(true, Ordering::Relaxed) {
+            // The terminal may well be gone, which shouldn't bring the run down with it.
+            let _ = writeln!(
+                io::stderr(),
+                "Progress display failed, continuing without it: {}",
+                reason
+            );
+        }
+    }
+
+    pub(crate) fn draw_target(&self) -> ProgressDrawTarget {
+        if self.is_progress_broken() {
+            return ProgressDrawTarget::hidden();
+        }
+
+        match self.progress {
+            ProgressStream::Stdout => ProgressDrawTarget::stdout(),
+            ProgressStream::Stderr => ProgressDrawTarget::stderr(),
+            ProgressStream::Auto | ProgressStream::None => ProgressDrawTarget::hidden(),
+        }
+    }
+
+    /// The current width of the terminal. Queried fresh each time, so output can follow the
+    /// terminal being resized.
+    pub(crate) fn term_width(&self) -> usize {
+        self.term_size().1 as usize
+    }
+
+    /// Returns the size as (rows, columns), preferring the terminal the user is watching.
+    pub(crate) fn term_size(&self) -> (u16, u16) {
+        let (rows, cols) = match self.progress {
+            _ if self.no_tty => (0, 0),
+            ProgressStream::Stderr if !Term::stdout().is_term() => Term::stderr().size(),
+            _ => Term::stdout().size(),
+        };
+
+        let rows = if rows == 0 {
+            FALLBACK_TERM_SIZE.0
+        } else {
+            rows
+        };
+        let cols = match self.term_width {
+            Some(width) => width,
+            None if cols == 0 => FALLBACK_TERM_SIZE.1,
+            None => cols,
+        };
+
+        (rows, cols)
+    }
+}
+
+/// Writes the file via a temporary file in the same directory, so anything watching it
+/// never sees it half-written.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), BenchError> {
+    let to_error = |inner| BenchError::OutputFileError {
+        inner,
+        name: path.display().to_string(),
+    };
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut file = tempfile::NamedTempFile::new_in(dir).map_err(to_error)?;
+    file.write_all(contents).map_err(to_error)?;
+    file.persist(path).map_err(|e| to_error(e.error))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_progress_follows_stdout() {
+        assert_eq!(auto_progress_stream(true, true), ProgressStream::Stdout);
+        assert_eq!(auto_progress_stream(true, false), ProgressStream::Stdout);
+        assert_eq!(auto_progress_stream(false, true), ProgressStream::Stderr);
+        assert_eq!(auto_progress_stream(false, false), ProgressStream::None);
+    }
+
+    #[cfg(target_os = "linux")]
+    mod redirected {
+        use std::{
+            fs::{File, OpenOptions},
+            io::Read,
+            os::unix::{fs::OpenOptionsExt, io::FromRawFd},
+            process::{Command, Stdio},
+        };
+
+        use std::time::Duration;
+
+        use console::Color;
+        use indicatif::{ProgressBar, ProgressStyle};
+
+        use crate::{
+            output::{FORCE_STYLE_VAR, OUTPUT},
+            print_footer, print_header, render_day_title, render_duration, render_row_label,
+            RowStatus,
+        };
+
+        const CHILD_VAR: &str = "AOC_OUTPUT_TEST_CHILD";
+
+        // A pseudo-terminal, standing in for the terminal the user is watching.
+        fn open_pty() -> (File, File) {
+            unsafe {
+                let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+                assert!(master >= 0, "posix_openpt failed");
+                assert_eq!(libc::grantpt(master), 0);
+                assert_eq!(libc::unlockpt(master), 0);
+
+                let mut name = [0 as libc::c_char; 128];
+                assert_eq!(libc::ptsname_r(master, name.as_mut_ptr(), name.len()), 0);
+                let name = std::ffi::CStr::from_ptr(name.as_ptr()).to_str().unwrap();
+                let slave = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .custom_flags(libc::O_NOCTTY)
+                    .open(name)
+                    .unwrap();
+
+                (File::from_raw_fd(master), slave)
+            }
+        }
+
+        // Run by `progress_goes_to_stderr` in its own process, as a run with stdout piped to
+        // a file and stderr on the terminal.
+        #[test]
+        fn mock_run() {
+            if std::env::var_os(CHILD_VAR).is_none() {
+                return;
+            }
+
+            let progress = ProgressBar::with_draw_target(Some(1), OUTPUT.draw_target())
+                .with_style(ProgressStyle::default_spinner().template("{msg}").unwrap());
+            progress.set_message("benching day 1");
+            progress.finish_with_message("benched day 1");
+
+            println!(
+                "table: {}",
+                OUTPUT.style().paint("day 1 | 42", Color::Green)
+            );
+
+            // The harness's own table, with its colours and markers.
+            let term_width = OUTPUT.term_width();
+            print_header(term_width);
+            println!("{}", render_day_title(1, "Mock", term_width));
+            let slow = Duration::from_secs(2);
+            for (label, status) in [
+                ("1.1", RowStatus::Ok),
+                ("1.2", RowStatus::Warning),
+                ("1.p", RowStatus::Error),
+            ] {
+                println!(
+                    "{} | {}",
+                    render_row_label(label, status),
+                    render_duration(slow, true)
+                );
+            }
+            print_footer(slow * 3, false, term_width);
+        }
+
+        #[test]
+        fn progress_goes_to_stderr() {
+            let (mut master, slave) = open_pty();
+            let child = Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "output::tests::redirected::mock_run"])
+                .args(["--nocapture", "--test-threads=1"])
+                .env(CHILD_VAR, "1")
+                .env("TERM", "xterm")
+                .env_remove(FORCE_STYLE_VAR)
+                .env_remove("NO_COLOR")
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(slave)
+                .spawn()
+                .unwrap();
+            let output = child.wait_with_output().unwrap();
+            assert!(output.status.success());
+
+            // With the child gone, reading the terminal fails once it's drained.
+            let mut terminal = Vec::new();
+            let mut buf = [0; 4096];
+            while let Ok(n @ 1..) = master.read(&mut buf) {
+                terminal.extend_from_slice(&buf[..n]);
+            }
+            let terminal = String::from_utf8_lossy(&terminal);
+            assert!(
+                terminal.contains("benched day 1"),
+                "terminal: {:?}",
+                terminal
+            );
+
+            // The table is clean text, without the progress.
+            let stdout = String::from_utf8(output.stdout).unwrap();
+            assert!(stdout.contains("table: day 1 | 42"), "stdout: {:?}", stdout);
+            assert!(!stdout.contains("benched"), "stdout: {:?}", stdout);
+            // Without colour, the rows and slow times are marked with symbols instead.
+            assert!(stdout.contains("[W]1.2"), "stdout: {:?}", stdout);
+            assert!(stdout.contains("[E]1.p"), "stdout: {:?}", stdout);
+            assert!(stdout.contains("2.000 s!"), "stdout: {:?}", stdout);
+            assert!(stdout.contains(" Total | "), "stdout: {:?}", stdout);
+            assert!(!stdout.contains('\x1b'), "stdout: {:?}", stdout);
+        }
+    }
+}
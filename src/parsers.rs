@@ -0,0 +1,313 @@
+//! Parsers for input layouts that come up every year.
+//!
+//! They all ignore trailing newlines and accept `\r\n` line endings, so inputs saved on Windows
+//! parse the same.
+
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GridParseError {
+    #[error("Line {line} has {len} cells, but the grid is {width} wide")]
+    Ragged {
+        /// One-based line number.
+        line: usize,
+        len: usize,
+        width: usize,
+    },
+    #[error("Line {line}, column {column}: '{found}' isn't a digit")]
+    NotDigit {
+        /// One-based line number.
+        line: usize,
+        /// One-based column number.
+        column: usize,
+        found: char,
+    },
+}
+
+#[derive(Debug, Error)]
+#[error("Line {line}: failed to parse '{text}': {error}")]
+pub struct LinesError<E> {
+    /// One-based line number.
+    pub line: usize,
+    pub text: String,
+    pub error: E,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Number {index}: failed to parse '{token}': {reason}")]
+pub struct NumberListError {
+    /// Zero-based index of the number in the list.
+    pub index: usize,
+    pub token: String,
+    pub reason: String,
+}
+
+pub(crate) fn lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input
+        .trim_end_matches(['\r', '\n'])
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+}
+
+/// Parses a grid of single digits, such as a height map, into a flat row-major `Vec` of
+/// their values, along with the grid's width.
+///
+/// An empty input gives an empty grid with a width of zero.
+pub fn grid_of_digits(input: &str) -> Result<(Vec<u8>, usize), GridParseError> {
+    if input.trim_end_matches(['\r', '\n']).is_empty() {
+        return Ok((Vec::new(), 0));
+    }
+
+    let mut width = None;
+    let mut cells = Vec::with_capacity(input.len());
+
+    for (line_num, line) in lines(input) {
+        let start = cells.len();
+        for (column, c) in line.chars().enumerate() {
+            let digit = c.to_digit(10).ok_or(GridParseError::NotDigit {
+                line: line_num,
+                column: column + 1,
+                found: c,
+            })?;
+            cells.push(digit as u8);
+        }
+
+        let len = cells.len() - start;
+        match width {
+            None => width = Some(len),
+            Some(width) if width != len => {
+                return Err(GridParseError::Ragged {
+                    line: line_num,
+                    len,
+                    width,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    Ok((cells, width.unwrap_or(0)))
+}
+
+/// Parses each line of the input with its [`FromStr`] implementation.
+///
+/// The first line to fail is reported with its line number and text.
+pub fn lines_of<T: FromStr>(input: &str) -> Result<Vec<T>, LinesError<T::Err>> {
+    lines_with(input, str::parse)
+}
+
+/// Parses each line of the input with the function, such as a closure wrapping a parser
+/// combinator.
+///
+/// The first line to fail is reported with its line number and text.
+pub fn lines_with<T, E>(
+    input: &str,
+    mut parse: impl FnMut(&str) -> Result<T, E>,
+) -> Result<Vec<T>, LinesError<E>> {
+    if input.trim_end_matches(['\r', '\n']).is_empty() {
+        return Ok(Vec::new());
+    }
+
+    lines(input)
+        .map(|(line, text)| {
+            parse(text).map_err(|error| LinesError {
+                line,
+                text: text.to_owned(),
+                error,
+            })
+        })
+        .collect()
+}
+
+/// Parses a list of numbers separated by the delimiter, such as `1,2,3,-4`, with their
+/// [`FromStr`] implementation. An empty or whitespace delimiter splits on any run of
+/// whitespace, for inputs in columns.
+///
+/// Whitespace around each number is ignored, as is a trailing delimiter. The first number to
+/// fail is reported with its index and text.
+pub fn number_list<F>(input: &str, delim: &str) -> Result<Vec<F>, NumberListError>
+where
+    F: FromStr,
+    F::Err: Display,
+{
+    let input = input.trim();
+    if delim.trim().is_empty() {
+        return parse_numbers(input.split_whitespace());
+    }
+
+    let input = input.strip_suffix(delim).unwrap_or(input).trim_end();
+    if input.is_empty() {
+        return Ok(Vec::new());
+    }
+    parse_numbers(input.split(delim).map(str::trim))
+}
+
+fn parse_numbers<'a, F>(tokens: impl Iterator<Item = &'a str>) -> Result<Vec<F>, NumberListError>
+where
+    F: FromStr,
+    F::Err: Display,
+{
+    tokens
+        .enumerate()
+        .map(|(index, token)| {
+            token.parse().map_err(|e: F::Err| NumberListError {
+                index,
+                token: token.to_owned(),
+                reason: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::ParseIntError;
+
+    use super::*;
+    use crate::UserError;
+
+    #[test]
+    fn digit_grid() {
+        assert_eq!(
+            grid_of_digits("123\n456\n"),
+            Ok((vec![1, 2, 3, 4, 5, 6], 3))
+        );
+        assert_eq!(
+            grid_of_digits("12\r\n34\r\n\r\n"),
+            Ok((vec![1, 2, 3, 4], 2))
+        );
+        assert_eq!(grid_of_digits("7"), Ok((vec![7], 1)));
+    }
+
+    #[test]
+    fn empty_digit_grid() {
+        assert_eq!(grid_of_digits(""), Ok((Vec::new(), 0)));
+        assert_eq!(grid_of_digits("\n\r\n"), Ok((Vec::new(), 0)));
+    }
+
+    #[test]
+    fn ragged_digit_grid() {
+        assert_eq!(
+            grid_of_digits("123\n45\n678"),
+            Err(GridParseError::Ragged {
+                line: 2,
+                len: 2,
+                width: 3
+            })
+        );
+        // A blank line in the middle is a row of no cells.
+        assert_eq!(
+            grid_of_digits("12\n\n34"),
+            Err(GridParseError::Ragged {
+                line: 2,
+                len: 0,
+                width: 2
+            })
+        );
+        assert_eq!(
+            grid_of_digits("12\n3x").unwrap_err().to_string(),
+            "Line 2, column 2: 'x' isn't a digit"
+        );
+        assert_eq!(
+            grid_of_digits("123\n45").unwrap_err().to_string(),
+            "Line 2 has 2 cells, but the grid is 3 wide"
+        );
+    }
+
+    #[test]
+    fn lines_of_values() {
+        assert_eq!(lines_of::<u32>("1\n20\n300\n").unwrap(), [1, 20, 300]);
+        assert_eq!(lines_of::<i8>("-1\r\n2\r\n").unwrap(), [-1, 2]);
+        assert!(lines_of::<u32>("").unwrap().is_empty());
+        assert!(lines_of::<u32>("\r\n").unwrap().is_empty());
+
+        let err = lines_of::<u8>("1\n2\n300\n4").unwrap_err();
+        assert_eq!((err.line, err.text.as_str()), (3, "300"));
+    }
+
+    #[test]
+    fn lines_with_parser() {
+        let pairs = lines_with("1-2\r\n3-4\r\n", |line| {
+            let (a, b) = line.split_once('-').ok_or("no dash")?;
+            Ok::<_, Box<dyn std::error::Error>>((a.parse::<u8>()?, b.parse::<u8>()?))
+        });
+        assert_eq!(pairs.unwrap(), [(1, 2), (3, 4)]);
+
+        // Trailing newlines don't give a phantom empty line to fail on.
+        let parse = |line: &str| line.parse::<u32>();
+        assert_eq!(lines_with("5\n\n\n", parse).unwrap(), [5]);
+        assert!(lines_with("\n", parse).unwrap().is_empty());
+        // But an empty line in the middle does fail.
+        assert_eq!(lines_with("5\n\n6", parse).unwrap_err().line, 2);
+    }
+
+    #[test]
+    fn line_error_context() {
+        let err: LinesError<ParseIntError> = lines_of::<u32>("12\n3a\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 2: failed to parse '3a': invalid digit found in string"
+        );
+
+        // It can be returned from a day's function.
+        let err = UserError(err);
+        assert_eq!(
+            err.to_string(),
+            "Line 2: failed to parse '3a': invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn number_lists() {
+        assert_eq!(number_list::<i64>("1,2,3,-4", ","), Ok(vec![1, 2, 3, -4]));
+        assert_eq!(number_list::<u8>(" 1 , 2 ,3\n", ","), Ok(vec![1, 2, 3]));
+        assert_eq!(
+            number_list::<i64>("10 -> 20 -> 30", "->"),
+            Ok(vec![10, 20, 30])
+        );
+        // Columns, split on any whitespace.
+        assert_eq!(number_list::<u8>("1  2\t3\r\n", " "), Ok(vec![1, 2, 3]));
+        assert_eq!(number_list::<u8>("1 2 3", ""), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn empty_number_lists() {
+        assert_eq!(number_list::<u8>("", ","), Ok(Vec::new()));
+        assert_eq!(number_list::<u8>(" \n", ","), Ok(Vec::new()));
+        assert_eq!(number_list::<u8>("", " "), Ok(Vec::new()));
+        assert_eq!(number_list::<u8>(",", ","), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn trailing_delimiter() {
+        assert_eq!(number_list::<u8>("1,2,3,", ","), Ok(vec![1, 2, 3]));
+        assert_eq!(number_list::<u8>("1,2,3,\n", ","), Ok(vec![1, 2, 3]));
+        // Only one is ignored, the next is an empty number.
+        assert_eq!(
+            number_list::<u8>("1,2,,", ","),
+            Err(NumberListError {
+                index: 2,
+                token: String::new(),
+                reason: "cannot parse integer from empty string".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn number_list_errors() {
+        // A negative number doesn't fit an unsigned target, but does a signed one.
+        let err = number_list::<u8>("1,-2,3", ",").unwrap_err();
+        assert_eq!((err.index, err.token.as_str()), (1, "-2"));
+        assert_eq!(number_list::<i64>("1,-2,3", ","), Ok(vec![1, -2, 3]));
+
+        let err = number_list::<u8>("1,2,256", ",").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Number 2: failed to parse '256': number too large to fit in target type"
+        );
+    }
+}
@@ -0,0 +1,751 @@
+use std::{
+    fs, iter,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+    thread,
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::{
+    alloc,
+    baseline::Baseline,
+    bench::{SampleMode, SetupFunction},
+    clock::Timestamp,
+    day_input, function_row_label, input_variants, is_function_selected, platform,
+    provenance::InputProvenance,
+    records::Records,
+    render_duration, Day, ARGS, RUNNING_YEAR,
+};
+
+// With no data from a previous run, we don't know how long the answer and memory
+// passes take, so we'll just assume something reasonable.
+const DEFAULT_UNTIMED_ESTIMATE: Duration = Duration::from_secs(1);
+
+// Functions timed side by side for this long spend most of it fighting over the caches...
+const SEQUENTIAL_TIMING_MIN_BENCH_TIME: u64 = 10;
+// ...and with only a few of them, timing one at a time doesn't make the run much longer.
+const SEQUENTIAL_TIMING_MAX_FUNCTIONS: usize = 8;
+
+static SEQUENTIAL_TIMING: AtomicBool = AtomicBool::new(false);
+static TIMING_LOCK: Mutex<()> = Mutex::new(());
+
+// The UI threads tend to end up on the first cores, so pinned workers keep off them.
+const RESERVED_CORES: usize = 2;
+
+static PERFORMANCE_CORES: Lazy<Vec<usize>> = Lazy::new(platform::performance_cores);
+static PIN_CPUS: Lazy<&'static [usize]> = Lazy::new(|| match PERFORMANCE_CORES.as_slice() {
+    cores if cores.len() > RESERVED_CORES => &cores[RESERVED_CORES..],
+    cores => cores,
+});
+// The CPUs the workers were actually pinned to.
+static PINNED_CPUS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// The name the parse function is benched under, so its row is `NN.p`.
+pub(crate) const PARSE_NAME: &str = "p";
+
+/// One of a day's functions, as benched.
+#[derive(Clone)]
+pub(crate) struct DayFunction {
+    /// Which part of the puzzle this function solves, or `None` for other functions.
+    pub(crate) part: Option<u8>,
+    /// The registered name of other functions, empty for the parts.
+    pub(crate) name: &'static str,
+    pub(crate) function: SetupFunction,
+    /// The answer it should give, if the day declares one.
+    pub(crate) expected: Option<&'static str>,
+    /// Whether this is the day's parse function, which is benched like an other function
+    /// named [`PARSE_NAME`].
+    pub(crate) is_parse: bool,
+    /// The input variant it's given, e.g. `alt1` for `day07_alt1.txt`, or `None` for the
+    /// day's own input.
+    pub(crate) variant: Option<String>,
+}
+
+impl DayFunction {
+    pub(crate) fn title(&self) -> String {
+        let title = match self.part {
+            Some(part) => format!("Part {}", part),
+            None if self.is_parse => "Parse".to_owned(),
+            None => self.name.to_owned(),
+        };
+
+        match &self.variant {
+            Some(variant) => format!("{} ({})", title, variant),
+            None => title,
+        }
+    }
+
+    /// Identifies the function in its row label: the part number or the other function's
+    /// name, followed by the input variant, e.g. `1/alt1`.
+    pub(crate) fn row_id(&self) -> String {
+        let id = self
+            .part
+            .map_or_else(|| self.name.to_owned(), |part| part.to_string());
+
+        match &self.variant {
+            Some(variant) => format!("{}/{}", id, variant),
+            None => id,
+        }
+    }
+
+    /// Where `--dump-samples` writes the function's timing samples, e.g. `day07_part1.csv`,
+    /// or `day07_part1_alt1.csv` for an input variant.
+    pub(crate) fn samples_path(&self, day: u8) -> Option<PathBuf> {
+        let dir = ARGS.dump_samples.as_ref()?;
+        let function = match self.part {
+            Some(part) => format!("part{}", part),
+            None if self.is_parse => "parse".to_owned(),
+            None => self.name.to_owned(),
+        };
+
+        let file = match &self.variant {
+            Some(variant) => format!("day{:02}_{}_{}.csv", day, function, variant),
+            None => format!("day{:02}_{}.csv", day, function),
+        };
+        Some(dir.join(file))
+    }
+}
+
+/// Expands a day into all of its functions, the parts followed by the others.
+pub(crate) fn day_functions(day: &Day) -> impl Iterator<Item = DayFunction> + '_ {
+    let parts = iter::once((day.part_1, day.expected_p1))
+        .chain(day.part_2.map(|part_2| (part_2, day.expected_p2)))
+        .zip(1..)
+        .map(|((function, expected), part)| DayFunction {
+            part: Some(part),
+            name: "",
+            function,
+            expected,
+            is_parse: false,
+            variant: None,
+        });
+    let others = day.other.iter().map(|&(name, function)| DayFunction {
+        part: None,
+        name,
+        function,
+        expected: None,
+        is_parse: false,
+        variant: None,
+    });
+
+    parts.chain(others)
+}
+
+/// Like [`day_functions`], but led by the parse function if the day has one.
+fn benched_functions(day: &Day) -> impl Iterator<Item = DayFunction> + '_ {
+    let parse = day.parse.map(|function| DayFunction {
+        part: None,
+        name: PARSE_NAME,
+        function,
+        expected: None,
+        is_parse: true,
+        variant: None,
+    });
+
+    parse.into_iter().chain(day_functions(day))
+}
+
+/// How the benched functions are spread over the worker threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Parallelism {
+    pub(crate) worker_threads: usize,
+    /// Whether the timing passes run one at a time. The answer and memory passes still
+    /// run in parallel.
+    pub(crate) sequential_timing: bool,
+}
+
+/// Decides how many worker threads to use for the functions, and whether to time them
+/// sequentially.
+///
+/// `bench_time` is the number of seconds each function is timed for, if known. An explicit
+/// thread count is still capped at the function count, but is otherwise always honoured.
+pub(crate) fn plan_parallelism(
+    function_count: usize,
+    bench_time: Option<u64>,
+    explicit_threads: Option<usize>,
+    physical_cores: usize,
+) -> Parallelism {
+    // We should limit the number of threads in the pool. Having too many
+    // results in them basically fighting for priority with the two update threads
+    // negatively effecting the benchmark.
+    let threads = explicit_threads.unwrap_or_else(|| physical_cores.saturating_sub(2));
+    let worker_threads = threads.min(function_count).max(1);
+
+    let sequential_timing = explicit_threads.is_none()
+        && worker_threads > 1
+        && function_count <= SEQUENTIAL_TIMING_MAX_FUNCTIONS
+        && bench_time.is_some_and(|time| time > SEQUENTIAL_TIMING_MIN_BENCH_TIME);
+
+    Parallelism {
+        worker_threads,
+        sequential_timing,
+    }
+}
+
+// The inputs the day's functions are benched with: the `--input-suffix` variant, if the day
+// has one, or the day's own input, followed by its variants with `--all-inputs`.
+fn day_variants(day: u8) -> Vec<Option<String>> {
+    if ARGS.example.is_some() {
+        return vec![None];
+    }
+
+    match &ARGS.input_suffix {
+        Some(suffix) if day_input(day, Some(suffix)).exists() => vec![Some(suffix.clone())],
+        Some(_) => Vec::new(),
+        None if ARGS.all_inputs => iter::once(None)
+            .chain(input_variants(day).into_iter().map(Some))
+            .collect(),
+        None => vec![None],
+    }
+}
+
+/// The functions of the days that pass the days filter, in the order they're benched. Each
+/// is given once for every input it's benched with.
+pub(crate) fn selected_functions<'a>(days: &[&'a Day]) -> Vec<(&'a Day, DayFunction)> {
+    let mut functions = Vec::new();
+    for &day in days {
+        let variants = day_variants(day.day);
+        let selected =
+            benched_functions(day).filter(|f| is_function_selected(day.day, f.part, f.name));
+        for f in selected {
+            functions.extend(variants.iter().map(|variant| {
+                let f = DayFunction {
+                    variant: variant.clone(),
+                    ..f.clone()
+                };
+                (day, f)
+            }));
+        }
+    }
+
+    functions
+}
+
+/// Plans the parallelism for benching this many functions with the current arguments.
+pub(crate) fn parallelism(function_count: usize) -> Parallelism {
+    let bench_time = match ARGS.sample_mode() {
+        _ if ARGS.run_type().is_run_only() => None,
+        SampleMode::Timed(bench_time) => Some(bench_time),
+        // We have no idea how long each iteration takes.
+        SampleMode::Iterations(_) => None,
+    };
+
+    // Pinned workers only go on the performance cores, so there are fewer to go around on
+    // hybrid CPUs.
+    let cores = match PERFORMANCE_CORES.len() {
+        cores if should_pin() && cores > 0 => cores,
+        _ => num_cpus::get_physical(),
+    };
+
+    plan_parallelism(function_count, bench_time, ARGS.num_threads, cores)
+}
+
+/// Whether `--threads 0` asked for the functions to be benched one at a time on a pinned core,
+/// with the progress only redrawn between them, for the steadiest timings.
+pub(crate) fn is_sequential_pinned() -> bool {
+    ARGS.num_threads == Some(0)
+}
+
+fn should_pin() -> bool {
+    ARGS.pin_threads || is_sequential_pinned()
+}
+
+/// Prints the planned parallelism, if it isn't simply timing everything in parallel.
+pub(crate) fn print_parallelism(parallelism: Parallelism, function_count: usize) {
+    if is_sequential_pinned() {
+        println!(
+            "{} functions, benching one at a time on a pinned core",
+            function_count
+        );
+    } else if parallelism.sequential_timing {
+        println!(
+            "{} functions, timing sequentially due to long bench time",
+            function_count
+        );
+    }
+}
+
+/// Holds the timing lock while timing sequentially, so only one function is timed at once.
+pub(crate) fn timing_guard() -> Option<MutexGuard<'static, ()>> {
+    SEQUENTIAL_TIMING
+        .load(Ordering::Relaxed)
+        .then(|| TIMING_LOCK.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+pub(crate) fn build_worker_pool(parallelism: Parallelism) -> ThreadPool {
+    SEQUENTIAL_TIMING.store(parallelism.sequential_timing, Ordering::Relaxed);
+    PINNED_CPUS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+
+    // Only the workers are changed, so the UI threads stay responsive. The functions run on
+    // threads spawned by the workers, which inherit both the affinity and priority.
+    ThreadPoolBuilder::new()
+        .num_threads(parallelism.worker_threads)
+        .start_handler(|index| {
+            alloc::exclude_current_thread();
+
+            if should_pin() && !PIN_CPUS.is_empty() {
+                let cpu = PIN_CPUS[index % PIN_CPUS.len()];
+                if platform::pin_thread(cpu) {
+                    PINNED_CPUS
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push(cpu);
+                }
+            }
+
+            match ARGS.nice {
+                Some(level) => {
+                    platform::set_thread_nice(level);
+                }
+                None if ARGS.background => {
+                    platform::lower_thread_priority();
+                }
+                None => {}
+            }
+        })
+        .build()
+        .expect("Failed to build threadpool")
+}
+
+/// Prints which CPUs the workers were pinned to, as timings are only comparable with runs
+/// pinned the same way.
+pub(crate) fn print_pinning() {
+    if !should_pin() {
+        return;
+    }
+
+    let mut pinned = PINNED_CPUS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    pinned.sort_unstable();
+    pinned.dedup();
+
+    if pinned.is_empty() {
+        println!("Worker threads not pinned, it isn't supported here");
+    } else {
+        let cpus: Vec<_> = pinned.iter().map(usize::to_string).collect();
+        println!("Worker threads pinned to CPUs {}", cpus.join(", "));
+    }
+}
+
+/// Runs the function on its own thread with the `--stack-size` stack, so deeply recursive
+/// solutions don't overflow the smaller default stack.
+///
+/// Returns the panic payload if the function panicked, like `catch_unwind`.
+pub(crate) fn run_with_stack<T: Send>(f: impl FnOnce() -> T + Send) -> thread::Result<T> {
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .name("aoc_lib bench".to_owned())
+            .stack_size(ARGS.stack_size)
+            .spawn_scoped(scope, || {
+                alloc::exclude_current_thread();
+                f()
+            })
+            .expect("Failed to spawn benchmark thread")
+            .join()
+    })
+}
+
+/// Like [`run_with_stack`], but doesn't wait for the thread, so it can be abandoned.
+pub(crate) fn spawn_with_stack(f: impl FnOnce() + Send + 'static) {
+    thread::Builder::new()
+        .name("aoc_lib bench".to_owned())
+        .stack_size(ARGS.stack_size)
+        .spawn(|| {
+            alloc::exclude_current_thread();
+            f()
+        })
+        .expect("Failed to spawn benchmark thread");
+}
+
+/// Estimated wall-clock cost of benching a single function.
+pub(crate) fn estimate_bench_cost(sample_mode: SampleMode) -> Duration {
+    let cost = match sample_mode {
+        SampleMode::Timed(bench_time) => Duration::from_secs(bench_time) + DEFAULT_UNTIMED_ESTIMATE,
+        // We have no idea how long each iteration takes.
+        SampleMode::Iterations(_) => DEFAULT_UNTIMED_ESTIMATE,
+    };
+    cost + ARGS.warmup
+}
+
+/// Estimated wall-clock cost of benching a single function, which took `mean` per call when
+/// it was last saved.
+pub(crate) fn estimate_bench_cost_from_mean(sample_mode: SampleMode, mean: Duration) -> Duration {
+    // The answer and memory passes are a call each.
+    let cost = match sample_mode {
+        SampleMode::Timed(bench_time) => Duration::from_secs(bench_time) + mean * 2,
+        SampleMode::Iterations(count) => {
+            mean.saturating_mul(u32::try_from(count.saturating_add(2)).unwrap_or(u32::MAX))
+        }
+    };
+    cost.saturating_add(ARGS.warmup)
+}
+
+/// Timings saved by earlier runs, from the baseline and records in use.
+struct SavedTimings {
+    baseline: Option<Baseline>,
+    records: Option<Records>,
+}
+
+impl SavedTimings {
+    // A missing or corrupt file is reported when the run gets to it, so here it only means
+    // there are no timings.
+    fn load() -> Self {
+        Self {
+            baseline: ARGS
+                .baseline
+                .as_deref()
+                .and_then(|name| Baseline::load(name).ok()),
+            records: ARGS
+                .records_file
+                .as_deref()
+                .and_then(|path| Records::load(path).ok()),
+        }
+    }
+
+    // The function's most recently saved mean, and when it was saved.
+    fn find(&self, day: &Day, f: &DayFunction) -> Option<(Duration, Timestamp)> {
+        // Only runs on the day's own input are saved.
+        if f.variant.is_some() {
+            return None;
+        }
+        let year = RUNNING_YEAR.load(Ordering::Relaxed);
+
+        let from_baseline = self
+            .baseline
+            .as_ref()
+            .and_then(|baseline| baseline.mean(year, day.day, f.part, f.name));
+        // Records are only kept for the input they were set on. The hash is cached, so the
+        // run doesn't hash the input again.
+        let from_records = self.records.as_ref().and_then(|records| {
+            let input_file = day_input(day.day, None);
+            let path = input_file.path();
+            let contents = fs::read_to_string(&path).ok()?;
+            let input = InputProvenance::shared(path, &contents, input_file.is_default());
+            records.mean(year, day.day, f.part, f.name, &input.sha256)
+        });
+
+        match (from_baseline, from_records) {
+            (Some(a), Some(b)) => Some(if a.1 >= b.1 { a } else { b }),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Estimated wall-clock time of benching this many functions.
+pub(crate) fn estimate_run_time(function_count: usize, parallelism: Parallelism) -> Duration {
+    let lanes = if parallelism.sequential_timing {
+        1
+    } else {
+        parallelism.worker_threads
+    };
+    let rounds = function_count.div_ceil(lanes.max(1));
+
+    estimate_bench_cost(ARGS.sample_mode()) * rounds as u32
+}
+
+/// Greedily selects which functions to bench so the run fits in the given wall-clock budget.
+///
+/// The costs are expected in priority order. Each function is placed on the least-loaded
+/// of `lanes` workers, and skipped if that would exceed the budget. Returns whether each
+/// function was selected.
+pub(crate) fn select_within_budget(
+    costs: &[Duration],
+    budget: Duration,
+    lanes: usize,
+) -> Vec<bool> {
+    let mut lane_loads = vec![Duration::ZERO; lanes.max(1)];
+
+    costs
+        .iter()
+        .map(|&cost| {
+            let lane = lane_loads.iter_mut().min().expect("at least one lane");
+
+            if *lane + cost <= budget {
+                *lane += cost;
+                true
+            } else {
+                false
+            }
+        })
+        .collect()
+}
+
+/// Like [`select_within_budget`], but the stalest functions are given priority: those with
+/// no saved timing first, then those saved longest ago, otherwise in the order given.
+pub(crate) fn select_stalest_within_budget(
+    costs: &[(Duration, Option<Timestamp>)],
+    budget: Duration,
+    lanes: usize,
+) -> Vec<bool> {
+    let mut order: Vec<_> = (0..costs.len()).collect();
+    order.sort_by_key(|&i| costs[i].1);
+
+    let prioritised: Vec<_> = order.iter().map(|&i| costs[i].0).collect();
+    let mut selection = vec![false; costs.len()];
+    for (i, is_selected) in order
+        .into_iter()
+        .zip(select_within_budget(&prioritised, budget, lanes))
+    {
+        selection[i] = is_selected;
+    }
+
+    selection
+}
+
+/// Applies the `--wall-budget` option, if given, printing any functions that were skipped.
+///
+/// Each function's cost is estimated from its timing in the baseline or records, if either
+/// has one. Returns the functions to bench, in the order given, and whether any were
+/// skipped.
+pub(crate) fn apply_wall_budget(
+    funcs: Vec<(&Day, DayFunction)>,
+    parallelism: Parallelism,
+) -> (Vec<(&Day, DayFunction)>, bool) {
+    let Some(budget) = ARGS.wall_budget else {
+        return (funcs, false);
+    };
+
+    let saved = SavedTimings::load();
+    let costs: Vec<_> = funcs
+        .iter()
+        .map(|(day, f)| {
+            let sample_mode = ARGS.day_sample_mode(day);
+            match saved.find(day, f) {
+                Some((mean, saved)) => (
+                    estimate_bench_cost_from_mean(sample_mode, mean),
+                    Some(saved),
+                ),
+                None => (estimate_bench_cost(sample_mode), None),
+            }
+        })
+        .collect();
+    // The cost is dominated by the timing, so sequential timing leaves one lane.
+    let lanes = if parallelism.sequential_timing {
+        1
+    } else {
+        parallelism.worker_threads
+    };
+    let selection = select_stalest_within_budget(&costs, budget, lanes);
+
+    let (selected, skipped): (Vec<_>, Vec<_>) = funcs
+        .into_iter()
+        .zip(costs)
+        .zip(selection)
+        .partition(|(_, is_selected)| *is_selected);
+
+    let selected = selected.into_iter().map(|((f, _), _)| f).collect();
+    if skipped.is_empty() {
+        return (selected, false);
+    }
+
+    let skipped_labels: Vec<_> = skipped
+        .iter()
+        .map(|(((day, f), _), _)| {
+            function_row_label(day.day, &f.row_id())
+                .trim_start()
+                .to_owned()
+        })
+        .collect();
+    let skipped_cost: Duration = skipped.iter().map(|((_, (cost, _)), _)| *cost).sum();
+    println!(
+        "Wall budget of {} exceeded, skipping {} functions (estimated {}): {}",
+        render_duration(budget, false).trim(),
+        skipped_labels.len(),
+        render_duration(skipped_cost, false).trim(),
+        skipped_labels.join(", ")
+    );
+
+    (selected, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn at(s: &str) -> Option<Timestamp> {
+        Some(Timestamp::parse_rfc3339(s).unwrap())
+    }
+
+    fn plan(
+        function_count: usize,
+        bench_time: Option<u64>,
+        explicit_threads: Option<usize>,
+        cores: usize,
+    ) -> (usize, bool) {
+        let parallelism = plan_parallelism(function_count, bench_time, explicit_threads, cores);
+        (parallelism.worker_threads, parallelism.sequential_timing)
+    }
+
+    #[test]
+    fn parallelism_cases() {
+        // The example from the request: 4 functions timed for 30s on 16 cores.
+        assert_eq!(plan(4, Some(30), None, 16), (4, true));
+        // Two cores are left for the UI threads.
+        assert_eq!(plan(50, Some(3), None, 16), (14, false));
+        // Too many functions to time one at a time.
+        assert_eq!(plan(9, Some(30), None, 16), (9, false));
+        assert_eq!(plan(8, Some(30), None, 16), (8, true));
+        // The threshold is exclusive.
+        assert_eq!(plan(4, Some(10), None, 16), (4, false));
+        assert_eq!(plan(4, Some(11), None, 16), (4, true));
+        // Iterations have no bench time, so are timed in parallel.
+        assert_eq!(plan(4, None, None, 16), (4, false));
+        // A single worker is already sequential.
+        assert_eq!(plan(1, Some(30), None, 16), (1, false));
+        assert_eq!(plan(4, Some(30), None, 2), (1, false));
+        // An explicit thread count is honoured, up to the function count.
+        assert_eq!(plan(4, Some(30), Some(2), 16), (2, false));
+        assert_eq!(plan(4, Some(30), Some(64), 16), (4, false));
+        assert_eq!(plan(4, Some(30), Some(0), 16), (1, false));
+    }
+
+    #[test]
+    fn parallelism_matrix() {
+        for function_count in [0, 1, 2, 4, 8, 9, 50] {
+            for bench_time in [None, Some(0), Some(3), Some(10), Some(11), Some(30)] {
+                for explicit_threads in [None, Some(0), Some(1), Some(3), Some(64)] {
+                    for cores in [1, 2, 4, 16] {
+                        let case = (function_count, bench_time, explicit_threads, cores);
+                        let (threads, sequential) =
+                            plan(function_count, bench_time, explicit_threads, cores);
+
+                        assert!(threads >= 1, "{:?}", case);
+                        assert!(threads <= function_count.max(1), "{:?}", case);
+                        match explicit_threads {
+                            Some(explicit) => {
+                                assert_eq!(
+                                    threads,
+                                    explicit.min(function_count).max(1),
+                                    "{:?}",
+                                    case
+                                );
+                                assert!(!sequential, "{:?}", case);
+                            }
+                            None => {
+                                assert!(threads <= cores.saturating_sub(2).max(1), "{:?}", case)
+                            }
+                        }
+                        // Only timed one at a time when it was left to us, and timing
+                        // side by side would be long and crowded.
+                        let expected = explicit_threads.is_none()
+                            && threads > 1
+                            && function_count <= SEQUENTIAL_TIMING_MAX_FUNCTIONS
+                            && bench_time.is_some_and(|t| t > SEQUENTIAL_TIMING_MIN_BENCH_TIME);
+                        assert_eq!(sequential, expected, "{:?}", case);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn select_fills_the_least_loaded_lane() {
+        let costs = [secs(4), secs(3), secs(2), secs(2), secs(1)];
+
+        // One lane takes them in order, skipping any that don't fit what's left.
+        let selection = select_within_budget(&costs, secs(6), 1);
+        assert_eq!(selection, [true, false, true, false, false]);
+
+        // Two lanes: 4 | 3, then 2 joins the 3, then neither has room for another 2, but
+        // the 1 fits next to the 4.
+        let selection = select_within_budget(&costs, secs(5), 2);
+        assert_eq!(selection, [true, true, true, false, true]);
+
+        // A function that can't fit on its own is skipped, without stopping the rest.
+        let selection = select_within_budget(&[secs(10), secs(1)], secs(5), 4);
+        assert_eq!(selection, [false, true]);
+
+        // No lanes is treated as one.
+        let selection = select_within_budget(&[secs(1), secs(1)], secs(1), 0);
+        assert_eq!(selection, [true, false]);
+
+        assert!(select_within_budget(&[], secs(1), 2).is_empty());
+    }
+
+    #[test]
+    fn select_prefers_the_stalest() {
+        let costs = [
+            (secs(2), at("2023-12-10T00:00:00Z")),
+            (secs(2), at("2023-12-01T00:00:00Z")),
+            (secs(2), None),
+            (secs(2), at("2023-12-05T00:00:00Z")),
+            (secs(2), None),
+        ];
+
+        // Never saved comes first in the order given, then the oldest saved.
+        let selection = select_stalest_within_budget(&costs, secs(6), 1);
+        assert_eq!(selection, [false, true, true, false, true]);
+
+        let selection = select_stalest_within_budget(&costs, secs(10), 1);
+        assert_eq!(selection, [true; 5]);
+    }
+
+    #[test]
+    fn cost_from_saved_mean() {
+        let mean = Duration::from_millis(1);
+
+        // Every iteration, plus the answer and memory passes.
+        assert_eq!(
+            estimate_bench_cost_from_mean(SampleMode::Iterations(10), mean),
+            Duration::from_millis(12) + ARGS.warmup
+        );
+        assert_eq!(
+            estimate_bench_cost_from_mean(SampleMode::Timed(3), mean),
+            secs(3) + Duration::from_millis(2) + ARGS.warmup
+        );
+        // A slow function saturates rather than overflowing.
+        assert_eq!(
+            estimate_bench_cost_from_mean(SampleMode::Iterations(10), secs(u64::MAX / 4)),
+            Duration::MAX
+        );
+    }
+
+    // Uses about 4 KiB of stack per level.
+    #[cfg(all(target_os = "linux", feature = "stack-usage"))]
+    fn recurse(depth: usize) -> u64 {
+        let frame = std::hint::black_box([depth as u8; 4096]);
+        match depth {
+            0 => frame[0] as u64,
+            _ => recurse(depth - 1) + frame[4095] as u64,
+        }
+    }
+
+    // The reading comes from /proc/self/smaps.
+    #[cfg(all(target_os = "linux", feature = "stack-usage"))]
+    #[test]
+    fn deep_recursion_on_bench_stack() {
+        const DEFAULT_STACK: usize = 2 << 20;
+
+        // 1024 levels is about 4 MiB, twice the default thread stack.
+        let (sum, peak) = run_with_stack(|| {
+            let sum = recurse(1024);
+            (sum, crate::platform::peak_stack_usage())
+        })
+        .unwrap();
+
+        assert_eq!(
+            sum,
+            (0..=1024)
+                .map(|depth: usize| depth as u8 as u64)
+                .sum::<u64>()
+        );
+        let peak = peak.unwrap();
+        assert!(peak > DEFAULT_STACK, "{}", peak);
+        assert!(peak <= ARGS.stack_size, "{}", peak);
+    }
+}
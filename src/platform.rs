@@ -0,0 +1,175 @@
+// Platform-specific scheduling tweaks and measurements. These are all best-effort, so
+// unsupported platforms just don't do anything.
+
+/// Lowers the scheduling priority of the calling thread. Returns whether it succeeded.
+#[cfg(target_os = "linux")]
+pub(crate) fn lower_thread_priority() -> bool {
+    const BACKGROUND_NICE: libc::c_int = 10;
+
+    // SAFETY: gettid has no preconditions, and on Linux the nice value is per-thread.
+    unsafe {
+        let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+        libc::setpriority(libc::PRIO_PROCESS, tid, BACKGROUND_NICE) == 0
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn lower_thread_priority() -> bool {
+    const PRIO_DARWIN_THREAD: libc::c_int = 3;
+    const PRIO_DARWIN_BG: libc::c_int = 0x1000;
+
+    // SAFETY: A `who` of 0 refers to the calling thread.
+    unsafe { libc::setpriority(PRIO_DARWIN_THREAD, 0, PRIO_DARWIN_BG) == 0 }
+}
+
+#[cfg(windows)]
+pub(crate) fn lower_thread_priority() -> bool {
+    const THREAD_PRIORITY_BELOW_NORMAL: i32 = -1;
+
+    extern "system" {
+        fn GetCurrentThread() -> isize;
+        fn SetThreadPriority(thread: isize, priority: i32) -> i32;
+    }
+
+    // SAFETY: GetCurrentThread returns a pseudo-handle that is always valid for the calling thread.
+    unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_BELOW_NORMAL) != 0 }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub(crate) fn lower_thread_priority() -> bool {
+    false
+}
+
+/// Sets the nice value of the calling thread. Raising the priority usually needs privileges.
+/// Returns whether it succeeded.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_thread_nice(level: i32) -> bool {
+    // SAFETY: gettid has no preconditions, and on Linux the nice value is per-thread.
+    unsafe {
+        let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+        libc::setpriority(libc::PRIO_PROCESS, tid, level) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn set_thread_nice(_level: i32) -> bool {
+    false
+}
+
+// Parses a kernel CPU list, such as `0-3,8,10-11`.
+#[cfg(target_os = "linux")]
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => cpus.extend(start.parse::<usize>().ok()?..=end.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+/// One logical CPU for each physical core, in order. On hybrid CPUs only the performance
+/// cores are included, as timings on the efficiency cores aren't comparable.
+///
+/// Empty if the topology couldn't be read.
+#[cfg(target_os = "linux")]
+pub(crate) fn performance_cores() -> Vec<usize> {
+    use std::fs;
+
+    let read = |path: &str| fs::read_to_string(path).ok();
+    let Some(online) = read("/sys/devices/system/cpu/online").and_then(|l| parse_cpu_list(&l))
+    else {
+        return Vec::new();
+    };
+    // Only exists on hybrid Intel CPUs.
+    let performance = read("/sys/devices/cpu_core/cpus").and_then(|l| parse_cpu_list(&l));
+
+    let mut seen_cores = Vec::new();
+    let mut cores = Vec::new();
+    for cpu in online {
+        if performance.as_ref().is_some_and(|p| !p.contains(&cpu)) {
+            continue;
+        }
+
+        let topology = |name: &str| {
+            read(&format!(
+                "/sys/devices/system/cpu/cpu{}/topology/{}",
+                cpu, name
+            ))
+            .and_then(|id| id.trim().parse::<usize>().ok())
+        };
+        // Hyperthreads share a core, so only the first of them is kept.
+        let core = (topology("physical_package_id"), topology("core_id"));
+        if !seen_cores.contains(&core) {
+            seen_cores.push(core);
+            cores.push(cpu);
+        }
+    }
+
+    cores
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn performance_cores() -> Vec<usize> {
+    Vec::new()
+}
+
+/// Restricts the calling thread, and any threads it spawns, to the logical CPU. Returns
+/// whether it succeeded.
+#[cfg(target_os = "linux")]
+pub(crate) fn pin_thread(cpu: usize) -> bool {
+    // SAFETY: An all-zero cpu_set_t is an empty set, and a pid of 0 refers to the calling
+    // thread.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn pin_thread(_cpu: usize) -> bool {
+    false
+}
+
+/// Approximate peak stack use of the calling thread, in bytes.
+///
+/// This counts the resident pages of the thread's stack mapping, so it's only meaningful on
+/// a fresh thread, and is rounded up to the page size.
+#[cfg(all(target_os = "linux", feature = "stack-usage"))]
+pub(crate) fn peak_stack_usage() -> Option<usize> {
+    let marker = 0u8;
+    let address = std::hint::black_box(&marker) as *const u8 as usize;
+
+    let smaps = std::fs::read_to_string("/proc/self/smaps").ok()?;
+    let mut is_stack_mapping = false;
+    for line in smaps.lines() {
+        // Each mapping starts with a header line such as `7ffc1000-7ffc3000 rw-p ...`, and the
+        // field lines are of the form `Rss:     12 kB`.
+        let range = line
+            .split_once(' ')
+            .and_then(|(range, _)| range.split_once('-'));
+        if let Some((start, end)) = range {
+            if let (Ok(start), Ok(end)) = (
+                usize::from_str_radix(start, 16),
+                usize::from_str_radix(end, 16),
+            ) {
+                is_stack_mapping = (start..end).contains(&address);
+                continue;
+            }
+        }
+
+        if let Some(rss) = line.strip_prefix("Rss:").filter(|_| is_stack_mapping) {
+            let kib: usize = rss.trim().trim_end_matches("kB").trim().parse().ok()?;
+            return Some(kib * 1024);
+        }
+    }
+
+    None
+}
+
+#[cfg(not(all(target_os = "linux", feature = "stack-usage")))]
+pub(crate) fn peak_stack_usage() -> Option<usize> {
+    None
+}
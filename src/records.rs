@@ -0,0 +1,381 @@
+use std::{fs, io, path::Path, time::Duration};
+
+use bytesize::ByteSize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::Timestamp, output::write_atomic, render_duration, speedup::Measurement, BenchError, ARGS,
+};
+
+// A new best has to beat the record by more than this fraction, so a record isn't broken
+// by noise alone.
+const NOISE_THRESHOLD: f64 = 0.02;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Best {
+    value: u64,
+    set: Timestamp,
+}
+
+impl Best {
+    fn is_beaten_by(&self, value: u64) -> bool {
+        (value as f64) < self.value as f64 * (1.0 - NOISE_THRESHOLD)
+    }
+}
+
+/// The best known results of a function, for one input. Changing the input starts a new
+/// record, as the old one isn't comparable.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    year: u16,
+    day: u8,
+    part: Option<u8>,
+    name: String,
+    /// The SHA-256 of the input. Records from before it was kept have none, so are never
+    /// matched.
+    #[serde(default)]
+    input_sha256: String,
+    mean_ns: Option<Best>,
+    peak_memory: Option<Best>,
+}
+
+impl Record {
+    fn title(&self) -> String {
+        match self.part {
+            Some(part) => format!("day {} part {}", self.day, part),
+            None => format!("day {} {}", self.day, self.name),
+        }
+    }
+
+    // The parts come before the other functions.
+    fn sort_key(&self) -> (u16, u8, bool, Option<u8>, &str) {
+        (
+            self.year,
+            self.day,
+            self.part.is_none(),
+            self.part,
+            &self.name,
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SavedRecords {
+    records: Vec<Record>,
+}
+
+fn read_saved(path: &Path) -> Result<SavedRecords, BenchError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        // No records have been set yet.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SavedRecords::default()),
+        Err(e) => {
+            return Err(BenchError::SavedResultsError {
+                inner: e,
+                name: path.display().to_string(),
+            })
+        }
+    };
+
+    serde_json::from_str(&contents).map_err(|e| BenchError::ImportError {
+        inner: e,
+        name: path.display().to_string(),
+    })
+}
+
+/// The saved records, for estimating how long benching a function will take.
+pub(crate) struct Records {
+    saved: SavedRecords,
+}
+
+impl Records {
+    pub(crate) fn load(path: &Path) -> Result<Self, BenchError> {
+        read_saved(path).map(|saved| Self { saved })
+    }
+
+    /// The function's record mean on the input with this SHA-256, and when it was set.
+    pub(crate) fn mean(
+        &self,
+        year: u16,
+        day: u8,
+        part: Option<u8>,
+        name: &str,
+        input_sha256: &str,
+    ) -> Option<(Duration, Timestamp)> {
+        self.saved
+            .records
+            .iter()
+            .find(|r| {
+                r.year == year
+                    && r.day == day
+                    && r.part == part
+                    && (part.is_some() || r.name == name)
+                    && r.input_sha256 == input_sha256
+            })
+            .and_then(|r| r.mean_ns)
+            .map(|best| (Duration::from_nanos(best.value), best.set))
+    }
+}
+
+/// A record broken by this run, and the previous best.
+struct Broken {
+    title: String,
+    kind: &'static str,
+    new: String,
+    old: String,
+    old_set: Timestamp,
+}
+
+// Updates the best with the value if it's the first, or beats the record. Returns the
+// previous best if a record was broken.
+fn update(best: &mut Option<Best>, value: u64, now: Timestamp) -> Option<Best> {
+    match best {
+        Some(old) if old.is_beaten_by(value) => {
+            let old = *old;
+            *best = Some(Best { value, set: now });
+            Some(old)
+        }
+        Some(_) => None,
+        None => {
+            *best = Some(Best { value, set: now });
+            None
+        }
+    }
+}
+
+/// Updates the records in the file with the measurements, printing any that were broken.
+///
+/// Debug builds and `--background` runs aren't representative, so they never set records.
+pub(crate) fn update_records(
+    year: u16,
+    measurements: &[Measurement],
+    file: Option<&Path>,
+) -> Result<(), BenchError> {
+    let Some(path) = file else {
+        return Ok(());
+    };
+    if cfg!(debug_assertions) || ARGS.background || measurements.is_empty() {
+        return Ok(());
+    }
+
+    let mut saved = read_saved(path)?;
+    let broken = apply_measurements(&mut saved, year, measurements, Timestamp::now());
+
+    if !broken.is_empty() {
+        println!();
+        for record in broken {
+            println!(
+                "New personal best{} for {}: {} (previously {}, set {})",
+                record.kind, record.title, record.new, record.old, record.old_set
+            );
+        }
+    }
+
+    saved
+        .records
+        .sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    let json = serde_json::to_string_pretty(&saved).expect("Failed to serialize records");
+    write_atomic(path, json.as_bytes())
+}
+
+// Updates the records with the measurements, adding those of functions and inputs without
+// one, and returns the records broken.
+fn apply_measurements(
+    saved: &mut SavedRecords,
+    year: u16,
+    measurements: &[Measurement],
+    now: Timestamp,
+) -> Vec<Broken> {
+    let mut broken = Vec::new();
+
+    for measurement in measurements {
+        // A function given no input can't be compared with anything.
+        let Some(input) = &measurement.input else {
+            continue;
+        };
+
+        let record = match saved.records.iter().position(|r| {
+            r.year == year
+                && r.day == measurement.day
+                && r.part == measurement.part
+                && r.name == measurement.name
+                && r.input_sha256 == input.sha256
+        }) {
+            Some(i) => &mut saved.records[i],
+            None => {
+                saved.records.push(Record {
+                    year,
+                    day: measurement.day,
+                    part: measurement.part,
+                    name: measurement.name.clone(),
+                    input_sha256: input.sha256.clone(),
+                    mean_ns: None,
+                    peak_memory: None,
+                });
+                saved.records.last_mut().unwrap()
+            }
+        };
+
+        let mean_ns = measurement.mean.as_nanos() as u64;
+        if let Some(old) = update(&mut record.mean_ns, mean_ns, now) {
+            broken.push(Broken {
+                title: record.title(),
+                kind: "",
+                new: render_duration(measurement.mean, false).trim().to_owned(),
+                old: render_duration(Duration::from_nanos(old.value), false)
+                    .trim()
+                    .to_owned(),
+                old_set: old.set,
+            });
+        }
+
+        if let Some(peak) = measurement.max_memory {
+            if let Some(old) = update(&mut record.peak_memory, peak as u64, now) {
+                broken.push(Broken {
+                    title: record.title(),
+                    kind: " peak memory",
+                    new: ByteSize(peak as u64).to_string(),
+                    old: ByteSize(old.value).to_string(),
+                    old_set: old.set,
+                });
+            }
+        }
+    }
+
+    broken
+}
+
+/// Lists every record in the file, with when it was set.
+pub(crate) fn print_records(path: &Path) -> Result<(), BenchError> {
+    let saved = read_saved(path)?;
+    if saved.records.is_empty() {
+        println!("No records in {}", path.display());
+        return Ok(());
+    }
+
+    println!(" -- Personal Records --");
+    for record in &saved.records {
+        let mut line = format!(
+            "  {} {} (input {:.16}):",
+            record.year,
+            record.title(),
+            record.input_sha256
+        );
+        if let Some(mean) = record.mean_ns {
+            line += &format!(
+                " {} (set {})",
+                render_duration(Duration::from_nanos(mean.value), false).trim(),
+                mean.set
+            );
+        }
+        if let Some(peak) = record.peak_memory {
+            line += &format!(", peak memory {} (set {})", ByteSize(peak.value), peak.set);
+        }
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::InputProvenance;
+
+    fn measurement(mean_us: u64, input: &str) -> Measurement {
+        Measurement {
+            day: 16,
+            part: Some(2),
+            name: String::new(),
+            mean: Duration::from_micros(mean_us),
+            std_dev: Duration::ZERO,
+            max_memory: None,
+            num_allocs: None,
+            input: Some(InputProvenance::shared(
+                "./inputs/day16.txt".to_owned(),
+                input,
+                true,
+            )),
+        }
+    }
+
+    fn at(s: &str) -> Timestamp {
+        Timestamp::parse_rfc3339(s).unwrap()
+    }
+
+    fn record_mean(saved: &SavedRecords, input: &str) -> Option<u64> {
+        let sha256 = &InputProvenance::new(String::new(), input, true).sha256;
+        saved
+            .records
+            .iter()
+            .find(|r| &r.input_sha256 == sha256)
+            .and_then(|r| r.mean_ns)
+            .map(|best| best.value)
+    }
+
+    // Sets a record of 3.4ms on the input.
+    fn with_record(input: &str) -> SavedRecords {
+        let mut saved = SavedRecords::default();
+        let first = apply_measurements(
+            &mut saved,
+            2023,
+            &[measurement(3400, input)],
+            at("2023-01-02T00:00:00Z"),
+        );
+        // The first time isn't a record broken, just a record.
+        assert!(first.is_empty());
+        saved
+    }
+
+    #[test]
+    fn faster_mean_breaks_the_record() {
+        let mut saved = with_record("input");
+        let broken = apply_measurements(
+            &mut saved,
+            2023,
+            &[measurement(3100, "input")],
+            at("2023-02-01T00:00:00Z"),
+        );
+
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].title, "day 16 part 2");
+        assert_eq!(broken[0].new, "3.100 ms");
+        assert_eq!(broken[0].old, "3.400 ms");
+        assert_eq!(broken[0].old_set, at("2023-01-02T00:00:00Z"));
+        assert_eq!(record_mean(&saved, "input"), Some(3_100_000));
+        assert_eq!(saved.records.len(), 1);
+    }
+
+    #[test]
+    fn noise_doesnt_break_the_record() {
+        let mut saved = with_record("input");
+        // 1% faster is within the noise threshold, and slower is never a record.
+        for mean_us in [3366, 3400, 3500] {
+            let broken = apply_measurements(
+                &mut saved,
+                2023,
+                &[measurement(mean_us, "input")],
+                at("2023-02-01T00:00:00Z"),
+            );
+            assert!(broken.is_empty(), "{}", mean_us);
+        }
+
+        assert_eq!(record_mean(&saved, "input"), Some(3_400_000));
+    }
+
+    #[test]
+    fn new_input_starts_a_new_record() {
+        let mut saved = with_record("input");
+        let broken = apply_measurements(
+            &mut saved,
+            2023,
+            &[measurement(1000, "other input")],
+            at("2023-02-01T00:00:00Z"),
+        );
+
+        assert!(broken.is_empty());
+        assert_eq!(saved.records.len(), 2);
+        assert_eq!(record_mean(&saved, "input"), Some(3_400_000));
+        assert_eq!(record_mean(&saved, "other input"), Some(1_000_000));
+    }
+}
@@ -0,0 +1,215 @@
+//! Generates the skeleton of a new year's solutions crate.
+//!
+//! Every file of the template has `{{year}}` replaced by the year, in both its path and its
+//! contents. A custom template is a directory laid out like the crate to generate.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::BenchError;
+
+const YEAR_PLACEHOLDER: &str = "{{year}}";
+
+const DEFAULT_TEMPLATE: &[(&str, &str)] = &[
+    (
+        "Cargo.toml",
+        r#"[package]
+name = "aoc_{{year}}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+aoc_lib = { git = "https://github.com/Measter/aoc_lib" }
+"#,
+    ),
+    (
+        "src/main.rs",
+        r#"use aoc_lib::TracingAlloc;
+
+mod days;
+
+#[global_allocator]
+static ALLOC: TracingAlloc = TracingAlloc;
+
+fn main() {
+    aoc_lib::main_helper(aoc_lib::run(&ALLOC, {{year}}, days::DAYS));
+}
+"#,
+    ),
+    (
+        "src/days/mod.rs",
+        r#"use aoc_lib::Day;
+
+pub const DAYS: &[Day] = &[];
+"#,
+    ),
+    ("inputs/.gitkeep", ""),
+    ("example_inputs/.gitkeep", ""),
+    (
+        ".gitignore",
+        r#"/target
+# Baselines are only comparable on the machine they were saved on.
+/.aoc_baselines
+
+# Puzzle inputs mustn't be redistributed, but the examples come from the puzzle text.
+/inputs/*
+!/inputs/.gitkeep
+"#,
+    ),
+];
+
+fn fill(template: &str, year: u16) -> String {
+    template.replace(YEAR_PLACEHOLDER, &year.to_string())
+}
+
+fn dir_error(inner: io::Error, path: &Path) -> BenchError {
+    BenchError::InputDirError {
+        inner,
+        name: path.display().to_string(),
+    }
+}
+
+// Reads every file in the template directory, with paths relative to it.
+fn read_template(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(PathBuf, String)>,
+) -> Result<(), BenchError> {
+    let entries = fs::read_dir(dir).map_err(|e| dir_error(e, dir))?;
+    for entry in entries {
+        let path = entry.map_err(|e| dir_error(e, dir))?.path();
+        if path.is_dir() {
+            read_template(root, &path, files)?;
+            continue;
+        }
+
+        let contents = fs::read_to_string(&path).map_err(|e| BenchError::InputFileError {
+            inner: e,
+            name: path.display().to_string(),
+        })?;
+        let relative = path
+            .strip_prefix(root)
+            .expect("Template file outside template");
+        files.push((relative.to_owned(), contents));
+    }
+
+    Ok(())
+}
+
+// A missing directory is fine, it'll be created, but one with anything in it could be
+// someone's solutions.
+fn check_target(path: &Path) -> Result<(), BenchError> {
+    match fs::read_dir(path) {
+        Ok(mut entries) => match entries.next() {
+            Some(_) => Err(BenchError::ScaffoldTargetNotEmpty(
+                path.display().to_string(),
+            )),
+            None => Ok(()),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(dir_error(e, path)),
+    }
+}
+
+fn write_files(path: &Path, year: u16, files: &[(PathBuf, String)]) -> Result<(), BenchError> {
+    check_target(path)?;
+
+    for (relative, contents) in files {
+        let relative = fill(&relative.to_string_lossy(), year);
+        let file = path.join(relative);
+        let write = || {
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&file, fill(contents, year))
+        };
+        write().map_err(|e| BenchError::OutputFileError {
+            inner: e,
+            name: file.display().to_string(),
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Creates a solutions crate for the year at `path`, with the allocator and `run` call in
+/// `main.rs`, an empty list of days in `src/days/mod.rs`, and the input directories.
+///
+/// Refuses to write into a directory that isn't empty.
+pub fn new_year(path: impl AsRef<Path>, year: u16) -> Result<(), BenchError> {
+    let files: Vec<_> = DEFAULT_TEMPLATE
+        .iter()
+        .map(|&(relative, contents)| (PathBuf::from(relative), contents.to_owned()))
+        .collect();
+    write_files(path.as_ref(), year, &files)
+}
+
+/// Like [`new_year`], but copies the files from the template directory instead.
+pub fn new_year_from_template(
+    path: impl AsRef<Path>,
+    year: u16,
+    template: impl AsRef<Path>,
+) -> Result<(), BenchError> {
+    let template = template.as_ref();
+    let mut files = Vec::new();
+    read_template(template, template, &mut files)?;
+    write_files(path.as_ref(), year, &files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_year() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("aoc_2023");
+        new_year(&target, 2023).unwrap();
+
+        let read = |path: &str| fs::read_to_string(target.join(path)).unwrap();
+        assert!(read("Cargo.toml").contains("name = \"aoc_2023\"\n"));
+        assert!(read("src/main.rs").contains("aoc_lib::run(&ALLOC, 2023, days::DAYS)"));
+        assert!(read("src/days/mod.rs").contains("pub const DAYS: &[Day] = &[];"));
+        assert!(read(".gitignore").contains("/inputs/*\n"));
+        assert!(target.join("inputs/.gitkeep").is_file());
+        assert!(target.join("example_inputs/.gitkeep").is_file());
+        for (path, _) in DEFAULT_TEMPLATE {
+            assert!(!read(path).contains(YEAR_PLACEHOLDER), "{}", path);
+        }
+
+        // Running it again would overwrite the solutions.
+        let main = target.join("src/main.rs");
+        fs::write(&main, "fn main() {}").unwrap();
+        let err = new_year(&target, 2024).unwrap_err();
+        assert!(
+            matches!(err, BenchError::ScaffoldTargetNotEmpty(_)),
+            "{}",
+            err
+        );
+        assert_eq!(fs::read_to_string(main).unwrap(), "fn main() {}");
+    }
+
+    #[test]
+    fn generates_from_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("template");
+        fs::create_dir_all(template.join("src")).unwrap();
+        fs::write(template.join("src/aoc_{{year}}.rs"), "// Year {{year}}\n").unwrap();
+
+        let target = dir.path().join("out");
+        new_year_from_template(&target, 2019, &template).unwrap();
+        assert_eq!(
+            fs::read_to_string(target.join("src/aoc_2019.rs")).unwrap(),
+            "// Year 2019\n"
+        );
+
+        let err = new_year_from_template(&target, 2019, &template).unwrap_err();
+        assert!(
+            matches!(err, BenchError::ScaffoldTargetNotEmpty(_)),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::{self, IsTerminal};
+
+use console::Term;
+use once_cell::sync::OnceCell;
+
+use crate::{BenchError, Day, DaySelector};
+
+static PICKED: OnceCell<Vec<DaySelector>> = OnceCell::new();
+
+/// The days chosen with `--select`, if the picker was shown.
+pub(crate) fn picked() -> Option<&'static [DaySelector]> {
+    PICKED.get().map(Vec::as_slice)
+}
+
+fn parse_selection(line: &str) -> Result<Vec<DaySelector>, String> {
+    line.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Lists the registered days, and asks which to run. The chosen days are used in place of
+/// those given on the command line, so are checked against each year the same way.
+pub(crate) fn pick_days(years: &[(u16, &[Day])]) -> Result<&'static [DaySelector], BenchError> {
+    if !io::stdin().is_terminal() {
+        return Err(BenchError::NotInteractive);
+    }
+
+    let term = Term::stdout();
+    let map_err = |inner| BenchError::OutputFileError {
+        inner,
+        name: "<stdout>".to_owned(),
+    };
+
+    for &(year, days) in years {
+        if years.len() > 1 {
+            term.write_line(&format!("{}:", year)).map_err(map_err)?;
+        }
+        for day in days {
+            term.write_line(&format!("  {:>2}. {}", day.day, day.name))
+                .map_err(map_err)?;
+        }
+    }
+
+    let selection = loop {
+        term.write_str("Days to run, comma separated (e.g. 1, 3.2), or empty for all: ")
+            .map_err(map_err)?;
+        let line = term
+            .read_line()
+            .map_err(|inner| BenchError::InputFileError {
+                inner,
+                name: "<stdin>".to_owned(),
+            })?;
+
+        match parse_selection(&line) {
+            Ok(selection) => break selection,
+            Err(e) => term.write_line(&e).map_err(map_err)?,
+        }
+    };
+
+    Ok(PICKED.get_or_init(|| selection))
+}
@@ -0,0 +1,210 @@
+//! The `test` subcommand, which checks the days' functions against the answers to the
+//! puzzles' examples.
+//!
+//! The examples are registered on the [`Day`] with [`Day::examples`], and read from
+//! `example_inputs/` like those given with `--example`. A missing example or a function
+//! failing only fails that example, so the rest are still checked.
+
+use std::panic;
+
+use crate::{
+    bench::{panic_message, simple::LINE_BREAK_MARKER},
+    day_col_rule, day_col_width, day_label, describe_input_error, fit_day_col, function_row_label,
+    input, is_function_selected,
+    output::OUTPUT,
+    plan::{run_with_stack, PARSE_NAME},
+    render_day_title, render_row_label, Bench, BenchError, BenchEvent, Day, Example, RowStatus,
+    SampleMode, SetupFunction, TracingAlloc, ARGS,
+};
+
+/// An example from the puzzle, with the answer it should give.
+#[derive(Debug, Clone, Copy)]
+pub struct ExampleSpec {
+    /// Which example file to read, `example_inputs/dayNN_{input}-{id}.txt`.
+    pub input: Example,
+    pub id: &'static str,
+    pub expected: &'static str,
+    /// The function to run, which is the one the input is for unless set otherwise.
+    pub function: Example,
+}
+
+impl ExampleSpec {
+    /// The example `example_inputs/dayNN_{input}-{id}.txt`, given to the function it's for.
+    pub const fn new(input: Example, id: &'static str, expected: &'static str) -> Self {
+        Self {
+            input,
+            id,
+            expected,
+            function: input,
+        }
+    }
+
+    /// Gives the example to another function, such as a part 1 example that's also used for
+    /// part 2.
+    pub const fn function(mut self, function: Example) -> Self {
+        self.function = function;
+        self
+    }
+
+    fn label(&self) -> String {
+        format!("{}-{}", self.input, self.id)
+    }
+}
+
+// The function's part number, for the days filter, and its id in the table.
+fn find_function(day: &Day, function: Example) -> (Option<u8>, String, Option<SetupFunction>) {
+    match function {
+        Example::Parse => (None, PARSE_NAME.to_owned(), day.parse),
+        Example::Part1 => (Some(1), "1".to_owned(), Some(day.part_1)),
+        Example::Part2 => (Some(2), "2".to_owned(), day.part_2),
+        Example::Other(name) => {
+            let function = day.other.iter().find(|(n, _)| *n == name).map(|&(_, f)| f);
+            (None, name.to_owned(), function)
+        }
+    }
+}
+
+// Runs the function on the example, returning why it failed if it did.
+fn check_example(
+    alloc: &'static TracingAlloc,
+    day: &Day,
+    spec: &ExampleSpec,
+    function: Option<SetupFunction>,
+) -> Result<(), String> {
+    let function =
+        function.ok_or_else(|| format!("day {} has no {} function", day.day, spec.function))?;
+    let input = input(day.day)
+        .example(spec.input, spec.id)
+        .open()
+        .map_err(|e| describe_input_error(&e).unwrap_or_else(|| e.to_string()))?;
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let bench = Bench {
+        alloc,
+        id: 0,
+        chan: sender,
+        run_only: true,
+        sample_mode: SampleMode::Timed(0),
+        answer_history: None,
+        measure_memory: false,
+        measure_single: false,
+        input: None,
+        expected: None,
+        samples_path: None,
+        input_bytes: None,
+    };
+    match run_with_stack(|| function(&input, bench)) {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return Err(e.to_string()),
+        Err(payload) => return Err(format!("Panicked: {}", panic_message(&*payload))),
+    }
+
+    // The function has returned, so everything it sent is waiting in the channel. An
+    // alternate answer, such as ASCII art, counts too.
+    let mut answer = None;
+    for event in receiver.try_iter() {
+        match event {
+            BenchEvent::Answer { answer: a, .. } => answer = Some(a),
+            BenchEvent::Error { err, .. } | BenchEvent::LateError { err, .. } => return Err(err),
+            _ => {}
+        }
+    }
+
+    let answer = answer.ok_or_else(|| "no answer given".to_owned())?;
+    if answer.trim_end() == spec.expected.trim_end() {
+        Ok(())
+    } else if ARGS.censor {
+        Err("not the expected answer".to_owned())
+    } else {
+        let one_line = |s: &str| s.trim_end().replace('\n', LINE_BREAK_MARKER);
+        Err(format!(
+            "expected {}, got {}",
+            one_line(spec.expected),
+            one_line(&answer)
+        ))
+    }
+}
+
+/// Checks every selected function with examples against them, printing a row for each.
+pub(crate) fn run_examples(alloc: &'static TracingAlloc, days: &[&Day]) -> Result<(), BenchError> {
+    let examples: Vec<_> = days
+        .iter()
+        .flat_map(|day| day.examples.iter().map(move |spec| (*day, spec)))
+        .map(|(day, spec)| (day, spec, find_function(day, spec.function)))
+        .filter(|(day, _, (part, id, _))| is_function_selected(day.day, *part, id))
+        .collect();
+
+    if examples.is_empty() {
+        println!("No examples registered for the selected days");
+        return Ok(());
+    }
+
+    let labels: Vec<_> = examples
+        .iter()
+        .map(|(day, _, (_, id, _))| function_row_label(day.day, id))
+        .collect();
+    fit_day_col(&labels);
+    let example_width = examples
+        .iter()
+        .map(|(_, spec, _)| spec.label().chars().count())
+        .fold("Example".len(), usize::max);
+
+    let rule = format!(
+        "{}|_{:_<width$}_|________",
+        day_col_rule(),
+        "",
+        width = example_width
+    );
+    println!(
+        "{:>day$} | {:<width$} | Result",
+        "Day",
+        "Example",
+        day = day_col_width() + 2,
+        width = example_width
+    );
+    println!("{}", rule);
+
+    // A panicking function is only a failed example, so there's no need for the message.
+    let old_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let term_width = OUTPUT.term_width();
+    let mut failed = Vec::new();
+    let mut prev_day = None;
+    for ((day, spec, (_, id, function)), label) in examples.iter().zip(&labels) {
+        if prev_day != Some(day.day) {
+            println!("{}", render_day_title(day.day, day.name, term_width));
+            prev_day = Some(day.day);
+        }
+
+        let (status, result) = match check_example(alloc, day, spec, *function) {
+            Ok(()) => (RowStatus::Ok, "ok".to_owned()),
+            Err(reason) => {
+                failed.push(format!("{}.{} {}", day_label(day.day), id, spec.label()));
+                (RowStatus::Error, reason)
+            }
+        };
+        println!(
+            "{} | {:<width$} | {}",
+            render_row_label(label, status),
+            spec.label(),
+            result,
+            width = example_width
+        );
+    }
+
+    panic::set_hook(old_panic_hook);
+
+    println!("{}", rule);
+    println!(
+        "{} of {} example(s) passed",
+        examples.len() - failed.len(),
+        examples.len()
+    );
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(BenchError::FailedExamples(failed))
+    }
+}
@@ -0,0 +1,180 @@
+use std::{fs, io, path::Path, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{clock::Timestamp, output::write_atomic, provenance::InputProvenance, BenchError};
+
+/// The reference implementation of a part is registered as an other function with this
+/// name, e.g. `part2_reference`.
+fn reference_part(name: &str) -> Option<u8> {
+    name.strip_prefix("part")?
+        .strip_suffix("_reference")?
+        .parse()
+        .ok()
+}
+
+/// The timing of a function from this run, which may be a part or a reference.
+#[derive(Clone)]
+pub(crate) struct Measurement {
+    pub(crate) day: u8,
+    pub(crate) part: Option<u8>,
+    pub(crate) name: String,
+    pub(crate) mean: Duration,
+    pub(crate) std_dev: Duration,
+    pub(crate) max_memory: Option<usize>,
+    pub(crate) num_allocs: Option<usize>,
+    /// The input the function was given.
+    pub(crate) input: Option<Arc<InputProvenance>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Side {
+    mean_ns: u64,
+    std_dev_ns: u64,
+    measured: Timestamp,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Speedup {
+    year: u16,
+    day: u8,
+    part: u8,
+    part_timing: Side,
+    reference_timing: Side,
+}
+
+impl Speedup {
+    fn factor(&self) -> f64 {
+        self.reference_timing.mean_ns as f64 / self.part_timing.mean_ns.max(1) as f64
+    }
+
+    // Assumes the two timings are independent, so their relative errors add in quadrature.
+    fn uncertainty(&self) -> f64 {
+        let relative = |side: &Side| side.std_dev_ns as f64 / side.mean_ns.max(1) as f64;
+        let combined = relative(&self.part_timing).hypot(relative(&self.reference_timing));
+        self.factor() * combined
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SavedSpeedups {
+    speedups: Vec<Speedup>,
+}
+
+fn read_saved(path: &Path) -> Result<SavedSpeedups, BenchError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        // Nothing has been saved yet.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SavedSpeedups::default()),
+        Err(e) => {
+            return Err(BenchError::InputFileError {
+                inner: e,
+                name: path.display().to_string(),
+            })
+        }
+    };
+
+    serde_json::from_str(&contents).map_err(|e| BenchError::ImportError {
+        inner: e,
+        name: path.display().to_string(),
+    })
+}
+
+/// Works out how much faster each part is than its reference, updating and saving the
+/// speedups in the file if given, and prints them.
+///
+/// A saved speedup is kept when its reference is deleted, so it's still shown along with
+/// how old it is.
+pub(crate) fn report_speedups(
+    year: u16,
+    measurements: &[Measurement],
+    file: Option<&Path>,
+) -> Result<(), BenchError> {
+    let mut saved = match file {
+        Some(path) => read_saved(path)?,
+        None => SavedSpeedups::default(),
+    };
+
+    let now = Timestamp::now();
+    let side = |m: &Measurement| Side {
+        mean_ns: m.mean.as_nanos() as u64,
+        std_dev_ns: m.std_dev.as_nanos() as u64,
+        measured: now,
+    };
+
+    let mut fresh = Vec::new();
+    for reference in measurements {
+        let Some(part) = reference_part(&reference.name).filter(|_| reference.part.is_none())
+        else {
+            continue;
+        };
+        let Some(measured_part) = measurements
+            .iter()
+            .find(|m| m.day == reference.day && m.part == Some(part))
+        else {
+            continue;
+        };
+
+        let speedup = Speedup {
+            year,
+            day: reference.day,
+            part,
+            part_timing: side(measured_part),
+            reference_timing: side(reference),
+        };
+        fresh.push((reference.day, part));
+
+        match saved
+            .speedups
+            .iter_mut()
+            .find(|s| s.year == year && s.day == reference.day && s.part == part)
+        {
+            Some(existing) => *existing = speedup,
+            None => saved.speedups.push(speedup),
+        }
+    }
+
+    saved.speedups.sort_by_key(|s| (s.year, s.day, s.part));
+
+    // Only show the days that were benched this time.
+    let shown: Vec<_> = saved
+        .speedups
+        .iter()
+        .filter(|s| s.year == year && measurements.iter().any(|m| m.day == s.day))
+        .collect();
+    if !shown.is_empty() {
+        println!("\n -- Speedup vs Reference --");
+        for speedup in shown {
+            print!(
+                "  Day {} Part {}: {:.1}× faster (± {:.1}×)",
+                speedup.day,
+                speedup.part,
+                speedup.factor(),
+                speedup.uncertainty()
+            );
+
+            if fresh.contains(&(speedup.day, speedup.part)) {
+                println!();
+            } else {
+                let oldest = speedup
+                    .part_timing
+                    .measured
+                    .min(speedup.reference_timing.measured);
+                println!(
+                    ", saved: part measured {}, reference {} ({} days old)",
+                    speedup.part_timing.measured,
+                    speedup.reference_timing.measured,
+                    now.days_since(oldest)
+                );
+            }
+        }
+        println!("The ± combines both standard deviations, assuming the timings are independent.");
+    }
+
+    if let Some(path) = file {
+        let json = serde_json::to_string_pretty(&saved).expect("Failed to serialize speedups");
+        write_atomic(path, json.as_bytes())?;
+    }
+
+    Ok(())
+}